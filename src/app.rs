@@ -17,7 +17,7 @@
 
 use std::{
     io::stdout,
-    sync::mpsc,
+    sync::{mpsc, Arc, Mutex},
     thread::{self},
     vec,
 };
@@ -41,25 +41,39 @@ use crate::{
     config::Config,
     fs::FsExplorer,
     handler::handle_keyboard_event,
-    media::player::{MusicPlayer, Player, RadioPlayer},
+    media::{
+        engine::{AudioEngine, Focus, FocusPolicy},
+        player::{MusicPlayer, Player, RadioPlayer},
+    },
     ui::{
         fs::draw_fs_tree,
         help::draw_help,
+        lyrics_editor::{draw_lyrics_editor, LyricsEditor},
         music_board::{draw_music_board, MusicController},
+        podcasts::{draw_podcasts, Podcasts},
         radio::{draw_radio_list, RadioExplorer},
+        station_browser::{draw_station_browser, StationBrowser},
+        station_editor::{draw_station_editor, StationEditor},
         EventType,
     },
-    util::m3u8::empty_cache,
+    util::{lyrics::LyricsMode, net},
 };
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum InputMode {
     Normal,
+    // typing into a form, `q` is text
+    Editing,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Routes {
     Main,
     Help,
+    LyricsEditor,
+    StationBrowser,
+    StationEditor,
+    Podcasts,
 }
 
 #[derive(PartialEq)]
@@ -69,13 +83,23 @@ pub enum ActiveModules {
 }
 
 pub struct App {
-    pub mode: InputMode,
+    // read by the key thread
+    mode: Arc<Mutex<InputMode>>,
     pub fs: FsExplorer,
     pub radio_fs: RadioExplorer,
     pub route_stack: Vec<Routes>,
+    // shared by the players, stops the audio when dropped
+    _engine: AudioEngine,
     pub player: MusicPlayer,
     pub radio: RadioPlayer,
+    // the source the header and the volume keys follow
+    pub focus: Focus,
     pub music_controller: MusicController,
+    pub lyrics_editor: Option<LyricsEditor>,
+    // kept while closed, so the catalogue is loaded once
+    pub station_browser: Option<StationBrowser>,
+    pub station_editor: Option<StationEditor>,
+    pub podcasts: Podcasts,
     pub active_modules: ActiveModules,
     pub config: Config,
     // terminal: Option<Terminal<B>>,
@@ -84,23 +108,50 @@ pub struct App {
 
 impl App {
     pub fn new() -> Option<Self> {
+        let config = Config::default();
+        let engine = AudioEngine::new();
+        let mut player: MusicPlayer = Player::new(&engine);
+        player.lyrics_finder.dirs = config.lyrics_dirs.clone();
+        let mut radio: RadioPlayer = Player::new(&engine);
+        radio.variant_policy = config.variant_policy;
+        radio.query_propagation = config.query_propagation;
+        radio.latency_mode = config.latency_mode;
+        radio.timeshift_capacity = config.timeshift;
+        // before anything goes online, the defaults are used on failure
+        let http = net::init(&config.http).err();
+        let radio_fs = RadioExplorer::new();
+        let (podcasts, podcasts_error) = Podcasts::load(config.podcast_refresh);
+        let msg = match (http, radio_fs.errors.as_slice(), podcasts_error) {
+            (Some(err), _, _) => format!("HTTP settings ignored: {}", err),
+            (None, [], None) => "Welcome to RustPlayer".to_string(),
+            (None, [], Some(err)) => err,
+            (None, [err], _) => err.clone(),
+            (None, [err, more @ ..], _) => format!("{} (and {} more)", err, more.len()),
+        };
         Some(Self {
-            mode: InputMode::Normal,
+            mode: Arc::new(Mutex::new(InputMode::Normal)),
             fs: FsExplorer::default(Some(|err| {
                 eprintln!("{}", err);
             }))
             .ok()?,
             // terminal: None,
             route_stack: vec![Routes::Main],
-            player: Player::new(),
-            radio: Player::new(),
-            radio_fs: RadioExplorer::new(),
+            _engine: engine,
+            player,
+            radio,
+            focus: Focus::Music,
+            radio_fs,
             music_controller: MusicController {
                 state: ListState::default(),
+                lyrics_mode: LyricsMode::Both,
             },
+            lyrics_editor: None,
+            station_browser: None,
+            station_editor: None,
+            podcasts,
             active_modules: ActiveModules::Fs,
-            msg: "Welcome to RustPlayer".to_string(),
-            config: Config::default(),
+            msg,
+            config,
         })
     }
 
@@ -125,31 +176,30 @@ impl App {
         // start event
         let (evt_sender, evt_receiver) = mpsc::sync_channel(1);
         let (exit_sender, exit_receiver) = mpsc::channel();
+        let mode = self.mode.clone();
         let evt_th = thread::spawn(move || loop {
             let evt = event::read();
             match evt {
                 Ok(evt) => {
                     if let Event::Key(key) = evt {
-                        match self.mode {
-                            InputMode::Normal => match key.code {
-                                KeyCode::Char('q') | KeyCode::Char('Q') => {
-                                    empty_cache();
-                                    drop(evt_sender);
-                                    let _ = exit_sender.send(());
-                                    return;
-                                }
-                                code => {
-                                    if key.is_press() {
-                                        match evt_sender.send(code) {
-                                            Ok(_) => {}
-                                            Err(_) => {
-                                                // send error, exit.
-                                                return;
-                                            }
+                        let editing = *mode.lock().unwrap() == InputMode::Editing;
+                        match key.code {
+                            KeyCode::Char('q') | KeyCode::Char('Q') if !editing => {
+                                drop(evt_sender);
+                                let _ = exit_sender.send(());
+                                return;
+                            }
+                            code => {
+                                if key.is_press() {
+                                    match evt_sender.send(code) {
+                                        Ok(_) => {}
+                                        Err(_) => {
+                                            // send error, exit.
+                                            return;
                                         }
                                     }
                                 }
-                            },
+                            }
                         }
                     }
                 }
@@ -177,6 +227,10 @@ impl App {
         disable_raw_mode()?;
         execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
         terminal.show_cursor()?;
+        // where the episode playing was left
+        if !self.podcasts.podcasts.is_empty() {
+            let _ = self.podcasts.save();
+        }
         let _ = evt_th.join();
         Ok(())
     }
@@ -187,10 +241,28 @@ impl App {
             EventType::Player => {
                 let player = &mut self.player;
                 player.tick();
+                if let Some(msg) = self.podcasts.tick(&mut self.player) {
+                    self.set_msg(&msg);
+                }
+                self.apply_focus();
             }
             EventType::Radio => {
                 let radio = &mut self.radio;
                 radio.tick();
+                if let Some(msg) = radio.take_message() {
+                    self.set_msg(&msg);
+                }
+                if let Some(msg) = self.radio_fs.receive() {
+                    self.set_msg(&msg);
+                }
+                let loaded = self.station_browser.as_mut().and_then(|b| b.receive());
+                if let Some(msg) = loaded {
+                    self.set_msg(&msg);
+                }
+                let tested = self.station_editor.as_mut().and_then(|e| e.receive());
+                if let Some(msg) = tested {
+                    self.set_msg(&msg);
+                }
             }
         }
     }
@@ -219,6 +291,22 @@ impl App {
                     self.draw_header(frame, chunks[0]);
                     draw_help(self, frame, chunks[1]);
                 }
+                Routes::LyricsEditor => {
+                    self.draw_header(frame, chunks[0]);
+                    draw_lyrics_editor(self, frame, chunks[1]);
+                }
+                Routes::StationBrowser => {
+                    self.draw_header(frame, chunks[0]);
+                    draw_station_browser(self, frame, chunks[1]);
+                }
+                Routes::StationEditor => {
+                    self.draw_header(frame, chunks[0]);
+                    draw_station_editor(self, frame, chunks[1]);
+                }
+                Routes::Podcasts => {
+                    self.draw_header(frame, chunks[0]);
+                    draw_podcasts(self, frame, chunks[1]);
+                }
             }
         })?;
         Ok(())
@@ -267,6 +355,18 @@ impl App {
             Routes::Help => {
                 draw_help(self, frame, area);
             }
+            Routes::LyricsEditor => {
+                draw_lyrics_editor(self, frame, area);
+            }
+            Routes::StationBrowser => {
+                draw_station_browser(self, frame, area);
+            }
+            Routes::StationEditor => {
+                draw_station_editor(self, frame, area);
+            }
+            Routes::Podcasts => {
+                draw_podcasts(self, frame, area);
+            }
         }
         Ok(())
     }
@@ -274,4 +374,48 @@ impl App {
     pub fn set_msg(&mut self, msg: &str) {
         self.msg = String::from(msg);
     }
+
+    /// `Editing` while a form takes the keys, including `q`.
+    pub fn set_mode(&self, mode: InputMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
+    /// The player of the focused source.
+    pub fn focused_player(&self) -> &dyn Player {
+        match self.focus {
+            Focus::Music => &self.player,
+            Focus::Radio => &self.radio,
+        }
+    }
+
+    pub fn focused_player_mut(&mut self) -> &mut dyn Player {
+        match self.focus {
+            Focus::Music => &mut self.player,
+            Focus::Radio => &mut self.radio,
+        }
+    }
+
+    /// Called before a source starts playing, the other one is paused or
+    /// ducked as `focus` in `config.ini` says.
+    pub fn take_focus(&mut self, focus: Focus) {
+        self.focus = focus;
+        if self.config.focus_policy == FocusPolicy::Exclusive {
+            let other: &mut dyn Player = match focus {
+                Focus::Music => &mut self.radio,
+                Focus::Radio => &mut self.player,
+            };
+            if other.is_playing() {
+                other.pause();
+            }
+        }
+        self.apply_focus();
+    }
+
+    // the other source is ducked while the focused one plays
+    fn apply_focus(&mut self) {
+        let playing = self.focused_player().is_playing();
+        let (music, radio) = self.config.focus_policy.ducked(self.focus, playing);
+        self.player.duck(music);
+        self.radio.duck(radio);
+    }
 }
@@ -15,20 +15,174 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::media::engine::FocusPolicy;
+use crate::util::hls::{LatencyMode, QueryPropagation, VariantPolicy};
+use crate::util::net::{parse_headers, HttpConfig};
 
 pub struct Config {
     pub refresh_rate: Duration,
     pub tick_gap: Duration,
     pub home_page: &'static str,
+    // searched for lyrics after the song's own folder
+    pub lyrics_dirs: Vec<PathBuf>,
+    // variant picked from HLS master playlists
+    pub variant_policy: VariantPolicy,
+    // copy the playlist's query string (CDN tokens) to segment urls
+    pub query_propagation: QueryPropagation,
+    // LL-HLS partial segments near the live edge, or whole segments
+    pub latency_mode: LatencyMode,
+    // live radio kept for pausing and rewinding
+    pub timeshift: Duration,
+    // path or url of the Radio Browser style station catalogue
+    pub catalogue: String,
+    // music and radio playing at once: pause or duck the other
+    pub focus_policy: FocusPolicy,
+    // proxy, user agent, headers and timeouts of every request
+    pub http: HttpConfig,
+    // podcast feeds fetched again this often, None only on request
+    pub podcast_refresh: Option<Duration>,
 }
 
 impl Config {
     pub fn default() -> Self {
+        let settings = Config::settings();
         Self {
             refresh_rate: Duration::from_millis(15),
             tick_gap: Duration::from_millis(100),
             home_page: "https://github.com/KetaNetwork",
+            lyrics_dirs: Config::lyrics_dirs(),
+            variant_policy: settings
+                .get("variant")
+                .and_then(|v| VariantPolicy::parse(v))
+                .unwrap_or(VariantPolicy::Highest),
+            query_propagation: settings
+                .get("propagate_query")
+                .and_then(|v| QueryPropagation::parse(v))
+                .unwrap_or(QueryPropagation::Off),
+            latency_mode: settings
+                .get("latency")
+                .and_then(|v| LatencyMode::parse(v))
+                .unwrap_or(LatencyMode::Stable),
+            timeshift: settings
+                .get("timeshift")
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|minutes| Duration::from_secs(minutes * 60))
+                .unwrap_or(Duration::from_secs(30 * 60)),
+            catalogue: settings
+                .get("catalogue")
+                .cloned()
+                .unwrap_or_else(Config::default_catalogue),
+            focus_policy: settings
+                .get("focus")
+                .and_then(|v| FocusPolicy::parse(v, Config::duck_level(&settings)))
+                .unwrap_or(FocusPolicy::Exclusive),
+            http: Config::http(&settings),
+            podcast_refresh: match settings.get("podcast_refresh").map(|v| v.parse::<u64>()) {
+                Some(Ok(0)) => None,
+                Some(Ok(minutes)) => Some(Duration::from_secs(minutes * 60)),
+                _ => Some(Duration::from_secs(60 * 60)),
+            },
+        }
+    }
+
+    /// `proxy`, `user_agent`, `headers = Name: value; Name: value` and the
+    /// `connect_timeout` and `read_timeout` in seconds.
+    fn http(settings: &HashMap<String, String>) -> HttpConfig {
+        let default = HttpConfig::default();
+        let seconds = |key: &str| {
+            settings
+                .get(key)
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs)
+        };
+        HttpConfig {
+            proxy: settings.get("proxy").filter(|v| !v.is_empty()).cloned(),
+            user_agent: settings
+                .get("user_agent")
+                .filter(|v| !v.is_empty())
+                .cloned()
+                .unwrap_or(default.user_agent),
+            headers: settings
+                .get("headers")
+                .map(|v| parse_headers(v))
+                .unwrap_or_default(),
+            connect_timeout: seconds("connect_timeout").unwrap_or(default.connect_timeout),
+            read_timeout: seconds("read_timeout").unwrap_or(default.read_timeout),
+        }
+    }
+
+    /// `duck = <percent>` of the volume kept by the source losing focus,
+    /// 20% by default.
+    fn duck_level(settings: &HashMap<String, String>) -> f32 {
+        settings
+            .get("duck")
+            .and_then(|v| FocusPolicy::parse_duck_level(v))
+            .unwrap_or(0.2)
+    }
+
+    /// `stations.json` in the config folder if there is one, otherwise the
+    /// most voted stations of the Radio Browser directory.
+    fn default_catalogue() -> String {
+        if let Some(mut config_dir) = dirs::config_dir() {
+            config_dir.push("RustPlayer");
+            config_dir.push("stations.json");
+            if config_dir.exists() {
+                return config_dir.to_string_lossy().to_string();
+            }
+        }
+        "https://all.api.radio-browser.info/json/stations/topvote/1000".to_string()
+    }
+
+    /// `key = value` lines of `config.ini` in the config folder, `#` starts
+    /// a comment.
+    fn settings() -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        if let Some(mut config_dir) = dirs::config_dir() {
+            config_dir.push("RustPlayer");
+            config_dir.push("config.ini");
+            if let Ok(f) = File::open(config_dir) {
+                for line in BufReader::new(f).lines().map_while(Result::ok) {
+                    let line = line.trim();
+                    if line.starts_with('#') {
+                        continue;
+                    }
+                    if let Some((key, value)) = line.split_once('=') {
+                        settings.insert(key.trim().to_lowercase(), value.trim().to_string());
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    /// `~/Music/Lyrics` plus one folder per line of `lyrics.ini` in the
+    /// config folder.
+    fn lyrics_dirs() -> Vec<PathBuf> {
+        let mut lyrics_dirs = vec![];
+        if let Some(mut music_dir) = dirs::audio_dir() {
+            music_dir.push("Lyrics");
+            lyrics_dirs.push(music_dir);
+        }
+        if let Some(mut config_dir) = dirs::config_dir() {
+            config_dir.push("RustPlayer");
+            config_dir.push("lyrics.ini");
+            if let Ok(f) = File::open(config_dir) {
+                for line in BufReader::new(f).lines().map_while(Result::ok) {
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        lyrics_dirs.push(PathBuf::from(line));
+                    }
+                }
+            }
         }
+        lyrics_dirs
     }
 }
@@ -25,9 +25,11 @@ use crossterm::event::KeyCode;
 use crate::{
     app::{ActiveModules, App, Routes},
     media::{
+        engine::Focus,
         media::{Media, Source},
         player::Player,
     },
+    util::playlist::PlaylistFormat,
 };
 
 fn add_media_to_player(app: &mut App, once: bool) -> bool {
@@ -60,6 +62,17 @@ fn add_media_to_player(app: &mut App, once: bool) -> bool {
         } else {
             // 文件
             let entry = &fse.files[selected - fse.dirs.len() - 1];
+            // station playlists go to the radio list
+            let name = entry.file_name().to_string_lossy().to_string();
+            if PlaylistFormat::from_location(name.as_str()).is_some() {
+                let path = entry.path().to_string_lossy().to_string();
+                let msg = match app.radio_fs.import(path.as_str()) {
+                    Ok(count) => format!("Imported {} radio stations from {}", count, name),
+                    Err(err) => format!("Import failed: {}: {}", name, err),
+                };
+                app.set_msg(&msg);
+                return true;
+            }
             let mut res = app.player.add_to_list(
                 Media {
                     src: Source::Local(entry.file_name().to_string_lossy().to_string()),
@@ -70,6 +83,10 @@ fn add_media_to_player(app: &mut App, once: bool) -> bool {
             if once {
                 for i in selected - fse.dirs.len()..fse.files.len() {
                     let entry = &fse.files[i];
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if PlaylistFormat::from_location(name.as_str()).is_some() {
+                        continue;
+                    }
                     res = app.player.add_to_list(
                         Media {
                             src: Source::Local(entry.file_name().to_string_lossy().to_string()),
@@ -84,6 +101,7 @@ fn add_media_to_player(app: &mut App, once: bool) -> bool {
             } else {
                 let msg = format!("Start playing");
                 app.set_msg(&msg);
+                app.take_focus(Focus::Music);
             }
             return res;
         }
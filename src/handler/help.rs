@@ -17,7 +17,7 @@
 
 use crossterm::event::KeyCode;
 
-use crate::app::App;
+use crate::app::{ActiveModules, App, Routes};
 
 pub fn handle_help(app: &mut App, code: KeyCode) -> bool {
     match code {
@@ -26,9 +26,12 @@ pub fn handle_help(app: &mut App, code: KeyCode) -> bool {
             return true;
         }
         KeyCode::Char('r') => {
-            let mut config_dir = dirs::config_dir().unwrap();
-            config_dir.push("RustPlayer");
-            open::that(config_dir).unwrap();
+            // stations are edited in the radio list, which works over SSH too
+            if let Some(Routes::Help) = app.route_stack.last() {
+                app.route_stack.pop();
+            }
+            app.active_modules = ActiveModules::RadioList;
+            app.set_msg("a add, e edit, d delete, K/J move stations");
             return true;
         }
         _ => {
@@ -0,0 +1,173 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fs, path::Path};
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    app::{App, Routes},
+    media::{engine::Focus, player::Player},
+    ui::lyrics_editor::LyricsEditor,
+    util::lyrics::Lyrics,
+};
+
+/// Start syncing the current song: lines come from the `.txt` beside it,
+/// or from its already loaded lyrics.
+pub fn open_lyrics_editor(app: &mut App) -> bool {
+    let song = match app.player.playing_song() {
+        Some(song) => song,
+        None => {
+            app.set_msg("Play a song before editing its lyrics");
+            return true;
+        }
+    };
+    let music_path = Path::new(song.path.as_str());
+    let music_path = music_path
+        .canonicalize()
+        .unwrap_or_else(|_| music_path.to_path_buf());
+    let lines: Vec<String> = match fs::read_to_string(music_path.with_extension("txt")) {
+        Ok(text) => text
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Err(_) => song
+            .lyrics
+            .list
+            .iter()
+            .map(|lyric| lyric.content.clone())
+            .filter(|line| !line.is_empty())
+            .collect(),
+    };
+    if lines.is_empty() {
+        let msg = format!(
+            "No lyrics text found: {}",
+            music_path.with_extension("txt").to_string_lossy()
+        );
+        app.set_msg(&msg);
+        return true;
+    }
+    app.lyrics_editor = Some(LyricsEditor::new(music_path, lines));
+    app.route_stack.push(Routes::LyricsEditor);
+    app.set_msg("Press <Space> at the start of each line");
+    true
+}
+
+fn close_lyrics_editor(app: &mut App) {
+    app.lyrics_editor = None;
+    if let Some(Routes::LyricsEditor) = app.route_stack.last() {
+        app.route_stack.pop();
+    }
+}
+
+/// Write the `.lrc` beside the audio file and load it into the playing song.
+fn save_lyrics(app: &mut App) -> bool {
+    let editor = match &app.lyrics_editor {
+        Some(editor) => editor,
+        None => return false,
+    };
+    let lyrics = editor.to_lyrics();
+    if lyrics.list.is_empty() {
+        app.set_msg("Nothing stamped yet");
+        return true;
+    }
+    let lrc_path = editor.music_path.with_extension("lrc");
+    if let Err(err) = fs::write(&lrc_path, lyrics.to_lrc()) {
+        let msg = format!("Save failed: {}", err);
+        app.set_msg(&msg);
+        return true;
+    }
+    let msg = format!(
+        "Saved {} lines to {}",
+        lyrics.list.len(),
+        lrc_path.to_string_lossy()
+    );
+    let music_path = editor.music_path.clone();
+    let position = app.player.position();
+    let player = &mut app.player;
+    player
+        .lyrics_finder
+        .invalidate(music_path.to_string_lossy().as_ref());
+    if let Some(song) = player.playing_song_mut() {
+        let song_path = Path::new(song.path.as_str());
+        let same_song = song_path.canonicalize().unwrap_or(song_path.to_path_buf()) == music_path;
+        if same_song {
+            song.lyrics = Lyrics::from_path(&lrc_path).unwrap_or(lyrics);
+            song.lyrics_index
+                .select(Some(song.lyrics.index_at(position)));
+        }
+    }
+    close_lyrics_editor(app);
+    app.set_msg(&msg);
+    true
+}
+
+pub fn handle_lyrics_editor(app: &mut App, code: KeyCode) -> bool {
+    let position = app.player.position();
+    let editor = match &mut app.lyrics_editor {
+        Some(editor) => editor,
+        None => return false,
+    };
+    match code {
+        KeyCode::Char(' ') => {
+            if !editor.stamp(position) {
+                app.set_msg("All lines stamped, press w to save");
+            }
+            true
+        }
+        KeyCode::Backspace => {
+            editor.undo();
+            true
+        }
+        KeyCode::Up => {
+            let selected = editor.index.selected().unwrap_or(0);
+            editor.index.select(Some(selected.saturating_sub(1)));
+            true
+        }
+        KeyCode::Down => {
+            let selected = editor.index.selected().unwrap_or(0);
+            let last = editor.lines.len().saturating_sub(1);
+            editor.index.select(Some((selected + 1).min(last)));
+            true
+        }
+        KeyCode::Left => {
+            editor.nudge(false);
+            true
+        }
+        KeyCode::Right => {
+            editor.nudge(true);
+            true
+        }
+        KeyCode::Char('w') | KeyCode::Char('W') => save_lyrics(app),
+        KeyCode::Esc => {
+            close_lyrics_editor(app);
+            app.set_msg("Lyrics editing discarded");
+            true
+        }
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            if app.player.is_playing() {
+                app.player.pause();
+            } else {
+                app.take_focus(Focus::Music);
+                app.player.resume();
+            }
+            true
+        }
+        _ => false,
+    }
+}
@@ -22,16 +22,24 @@ use crate::app::{ActiveModules, App, Routes};
 use self::{
     fs::handle_fs,
     help::handle_help,
+    lyrics_editor::{handle_lyrics_editor, open_lyrics_editor},
     music_controller::{handle_music_controller, handle_radio_controller},
-    player::{handle_player, handle_radio},
+    player::handle_player,
+    podcasts::{handle_podcasts, open_podcasts},
     radio::handle_radio_fs,
+    station_browser::{handle_station_browser, open_station_browser},
+    station_editor::handle_station_editor,
 };
 
 mod fs;
 mod help;
+mod lyrics_editor;
 mod music_controller;
 mod player;
+mod podcasts;
 mod radio;
+mod station_browser;
+mod station_editor;
 
 pub fn handle_active_modules(app: &mut App, key: KeyCode) -> bool {
     match key {
@@ -59,10 +67,31 @@ pub fn handle_routes(app: &mut App, key: KeyCode) -> bool {
                     Routes::Help => {
                         app.route_stack.pop();
                     }
+                    Routes::LyricsEditor
+                    | Routes::StationBrowser
+                    | Routes::StationEditor
+                    | Routes::Podcasts => {
+                        app.route_stack.push(Routes::Help);
+                    }
                 }
             }
             return true;
         }
+        KeyCode::Char('e') | KeyCode::Char('E') => {
+            if let Some(Routes::Main) = app.route_stack.last() {
+                return open_lyrics_editor(app);
+            }
+        }
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            if let Some(Routes::Main) = app.route_stack.last() {
+                return open_station_browser(app);
+            }
+        }
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            if let Some(Routes::Main) = app.route_stack.last() {
+                return open_podcasts(app);
+            }
+        }
         _ => {}
     }
     false
@@ -98,7 +127,7 @@ pub fn handle_keyboard_event(app: &mut App, key: KeyCode) {
                     if flag {
                         return;
                     }
-                    flag = handle_radio(app, key);
+                    flag = handle_player(app, key);
                     if flag {
                         return;
                     }
@@ -115,6 +144,46 @@ pub fn handle_keyboard_event(app: &mut App, key: KeyCode) {
                 return;
             }
         }
+        Routes::LyricsEditor => {
+            flag = handle_lyrics_editor(app, key);
+            if flag {
+                return;
+            }
+        }
+        Routes::StationEditor => {
+            flag = handle_station_editor(app, key);
+            if flag {
+                return;
+            }
+        }
+        Routes::StationBrowser => {
+            flag = handle_station_browser(app, key);
+            if flag {
+                return;
+            }
+            flag = handle_player(app, key);
+            if flag {
+                return;
+            }
+            flag = handle_radio_controller(app, key);
+            if flag {
+                return;
+            }
+        }
+        Routes::Podcasts => {
+            flag = handle_podcasts(app, key);
+            if flag {
+                return;
+            }
+            flag = handle_player(app, key);
+            if flag {
+                return;
+            }
+            flag = handle_music_controller(app, key);
+            if flag {
+                return;
+            }
+        }
     }
     flag = handle_routes(app, key);
     if flag {
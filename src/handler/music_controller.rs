@@ -15,26 +15,41 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use crossterm::event::KeyCode;
 
-use crate::{app::App, media::player::Player};
+use crate::{
+    app::App,
+    media::{engine::Focus, player::Player},
+    util::timeshift::format_clock,
+};
+
+// skipped by ←/→ on live radio
+const TIMESHIFT_STEP: Duration = Duration::from_secs(30);
 
 pub fn handle_music_controller(app: &mut App, code: KeyCode) -> bool {
     // if app.active_modules != ActiveModules::MusicController {
     //     return false;
     // }
-    let player = &mut app.player;
     match code {
         KeyCode::Char('s') | KeyCode::Char('S') => {
-            if player.is_playing() {
-                player.pause();
+            if app.player.is_playing() {
+                app.player.pause();
             } else {
-                player.resume();
+                app.take_focus(Focus::Music);
+                app.player.resume();
             }
             return true;
         }
         KeyCode::Char('n') | KeyCode::Char('N') => {
-            player.next();
+            app.take_focus(Focus::Music);
+            app.player.next();
+            return true;
+        }
+        KeyCode::Char('l') | KeyCode::Char('L') => {
+            let controller = &mut app.music_controller;
+            controller.lyrics_mode = controller.lyrics_mode.next();
             return true;
         }
         _ => {
@@ -47,13 +62,38 @@ pub fn handle_radio_controller(app: &mut App, code: KeyCode) -> bool {
     // if app.active_modules != ActiveModules::MusicController {
     //     return false;
     // }
-    let player = &mut app.radio;
     match code {
         KeyCode::Char('s') | KeyCode::Char('S') => {
-            if player.is_playing() {
-                player.pause();
+            if app.radio.is_playing() {
+                app.radio.pause();
             } else {
-                player.resume();
+                app.take_focus(Focus::Radio);
+                app.radio.resume();
+            }
+            return true;
+        }
+        KeyCode::Left | KeyCode::Right => {
+            let back = code == KeyCode::Left;
+            match app.radio.shift(back, TIMESHIFT_STEP) {
+                Some(behind) if behind.is_zero() => app.set_msg("Live"),
+                Some(behind) => {
+                    let msg = format!("Behind live by {}", format_clock(behind));
+                    app.set_msg(&msg);
+                }
+                None => return false,
+            }
+            return true;
+        }
+        KeyCode::Char('g') | KeyCode::Char('G') => {
+            if app.radio.go_live() {
+                app.set_msg("Live");
+            }
+            return true;
+        }
+        KeyCode::Char('v') | KeyCode::Char('V') => {
+            match app.radio.switch_variant() {
+                Some(label) => app.set_msg(format!("Switched to {}", label).as_str()),
+                None => app.set_msg("This station has a single stream"),
             }
             return true;
         }
@@ -17,40 +17,22 @@
 
 use crossterm::event::KeyCode;
 
-use crate::{app::App, media::player::Player};
+use crate::app::App;
 
+// volume of the focused source
 pub fn handle_player(app: &mut App, code: KeyCode) -> bool {
+    let player = app.focused_player_mut();
     match code {
         KeyCode::Char('-') => {
-            let volume = app.player.volume() - 0.05;
+            let volume = player.volume() - 0.05;
             let new_volume = volume.max(0.0);
-            app.player.set_volume(new_volume);
+            player.set_volume(new_volume);
             return true;
         }
         KeyCode::Char('=') | KeyCode::Char('+') => {
-            let volume = app.player.volume() + 0.05;
+            let volume = player.volume() + 0.05;
             let new_volume = volume.min(1.0);
-            app.player.set_volume(new_volume);
-            return true;
-        }
-        _ => {
-            return false;
-        }
-    }
-}
-
-pub fn handle_radio(app: &mut App, code: KeyCode) -> bool {
-    match code {
-        KeyCode::Char('-') => {
-            let volume = app.radio.volume() - 0.05;
-            let new_volume = volume.max(0.0);
-            app.radio.set_volume(new_volume);
-            return true;
-        }
-        KeyCode::Char('=') | KeyCode::Char('+') => {
-            let volume = app.radio.volume() + 0.05;
-            let new_volume = volume.min(1.0);
-            app.radio.set_volume(new_volume);
+            player.set_volume(new_volume);
             return true;
         }
         _ => {
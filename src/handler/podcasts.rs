@@ -0,0 +1,179 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    app::{App, InputMode, Routes},
+    media::engine::Focus,
+};
+
+pub fn open_podcasts(app: &mut App) -> bool {
+    app.route_stack.push(Routes::Podcasts);
+    true
+}
+
+fn close_podcasts(app: &mut App) {
+    if let Some(Routes::Podcasts) = app.route_stack.last() {
+        app.route_stack.pop();
+    }
+}
+
+fn close_input(app: &mut App) {
+    app.podcasts.input = None;
+    app.set_mode(InputMode::Normal);
+}
+
+fn handle_input(app: &mut App, code: KeyCode) -> bool {
+    let input = match &mut app.podcasts.input {
+        Some(input) => input,
+        None => return false,
+    };
+    match code {
+        KeyCode::Esc => close_input(app),
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Enter => {
+            let location = input.trim().to_string();
+            close_input(app);
+            if !location.is_empty() {
+                app.podcasts.subscribe(location.as_str());
+                app.set_msg(format!("Loading {}", location).as_str());
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+fn unsubscribe(app: &mut App) -> bool {
+    let index = match app.podcasts.podcast_index.selected() {
+        Some(index) => index,
+        None => return false,
+    };
+    // the first press only asks
+    if app.podcasts.unsubscribe_armed != Some(index) {
+        app.podcasts.unsubscribe_armed = Some(index);
+        let title = app.podcasts.podcasts[index].title().to_string();
+        app.set_msg(format!("Press d again to unsubscribe from {}", title).as_str());
+        return true;
+    }
+    app.podcasts.unsubscribe_armed = None;
+    let title = app.podcasts.podcasts[index].title().to_string();
+    let msg = match app.podcasts.unsubscribe(index) {
+        Ok(_) => format!("Unsubscribed from {}", title),
+        Err(err) => format!("Unsubscribed from {}, not saved: {}", title, err),
+    };
+    app.set_msg(&msg);
+    true
+}
+
+fn play(app: &mut App) -> bool {
+    let episode = match app.podcasts.selected_episode() {
+        Some(episode) => episode.clone(),
+        None => return false,
+    };
+    app.take_focus(Focus::Music);
+    let msg = match app.podcasts.play(&episode) {
+        Ok(msg) => msg,
+        Err(err) => format!("Cannot play {}: {}", episode.title, err),
+    };
+    app.set_msg(&msg);
+    true
+}
+
+fn download(app: &mut App) -> bool {
+    let episode = match app.podcasts.selected_episode() {
+        Some(episode) => episode.clone(),
+        None => return false,
+    };
+    let msg = match app.podcasts.download(&episode) {
+        Ok(msg) => msg,
+        Err(err) => format!("Cannot download {}: {}", episode.title, err),
+    };
+    app.set_msg(&msg);
+    true
+}
+
+fn toggle_played(app: &mut App) -> bool {
+    let episode = match app.podcasts.selected_episode() {
+        Some(episode) => episode.clone(),
+        None => return false,
+    };
+    let msg = match app.podcasts.toggle_played(episode.id.as_str()) {
+        true => format!("{} marked played", episode.title),
+        false => format!("{} marked unplayed", episode.title),
+    };
+    app.set_msg(&msg);
+    true
+}
+
+pub fn handle_podcasts(app: &mut App, code: KeyCode) -> bool {
+    if handle_input(app, code) {
+        return true;
+    }
+    if code != KeyCode::Char('d') && code != KeyCode::Char('D') {
+        app.podcasts.unsubscribe_armed = None;
+    }
+    let podcasts = &mut app.podcasts;
+    match code {
+        KeyCode::Up => {
+            podcasts.select_next(false);
+            true
+        }
+        KeyCode::Down => {
+            podcasts.select_next(true);
+            true
+        }
+        KeyCode::Left => {
+            podcasts.episodes_focused = false;
+            true
+        }
+        KeyCode::Right | KeyCode::Tab => {
+            podcasts.episodes_focused = !podcasts.episodes_focused || code == KeyCode::Right;
+            true
+        }
+        KeyCode::Char('a') | KeyCode::Char('A') => {
+            podcasts.input = Some(String::new());
+            app.set_mode(InputMode::Editing);
+            true
+        }
+        KeyCode::Char('d') | KeyCode::Char('D') => unsubscribe(app),
+        KeyCode::Char('r') | KeyCode::Char('R') => {
+            podcasts.refresh();
+            app.set_msg("Refreshing podcasts");
+            true
+        }
+        KeyCode::Enter => {
+            if podcasts.episodes_focused {
+                play(app)
+            } else {
+                podcasts.episodes_focused = true;
+                true
+            }
+        }
+        KeyCode::Char('w') | KeyCode::Char('W') => download(app),
+        KeyCode::Char('m') | KeyCode::Char('M') => toggle_played(app),
+        KeyCode::Esc => {
+            close_podcasts(app);
+            true
+        }
+        _ => false,
+    }
+}
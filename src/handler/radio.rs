@@ -3,12 +3,18 @@ use crossterm::event::KeyCode;
 use crate::{
     app::App,
     media::{
+        engine::Focus,
         media::{Media, Source},
         player::Player,
     },
 };
 
+use super::station_editor::open_station_editor;
+
 pub fn handle_radio_fs(app: &mut App, code: KeyCode) -> bool {
+    if handle_radio_edit(app, code) {
+        return true;
+    }
     let rfs = &mut app.radio_fs;
     let list = &rfs.radios;
     if list.is_empty() {
@@ -36,17 +42,70 @@ pub fn handle_radio_fs(app: &mut App, code: KeyCode) -> bool {
         KeyCode::Enter => {
             let s = rfs.index.selected();
             if let Some(selected_index) = s {
-                if app.player.is_playing() {
-                    app.player.pause();
+                let src = Source::radio(rfs.radios[selected_index].clone());
+                app.take_focus(Focus::Radio);
+                app.radio.add_to_list(Media { src }, true);
+            }
+
+            true
+        }
+        _ => false,
+    }
+}
+
+// a/e/d/K/J add, edit, delete and move stations of the radio list
+fn handle_radio_edit(app: &mut App, code: KeyCode) -> bool {
+    let rfs = &mut app.radio_fs;
+    let selected = rfs.index.selected().filter(|i| *i < rfs.radios.len());
+    // a second `d` right after the first deletes
+    let armed = rfs.delete_armed.take();
+    match code {
+        KeyCode::Char('a') | KeyCode::Char('A') => open_station_editor(app, None),
+        KeyCode::Char('e') | KeyCode::Char('E') => match selected {
+            Some(index) => open_station_editor(app, Some(index)),
+            None => false,
+        },
+        KeyCode::Char('d') | KeyCode::Char('D') => {
+            let index = match selected {
+                Some(index) => index,
+                None => return false,
+            };
+            let config = &rfs.radios[index];
+            let msg = if let Some(playlist) = &config.playlist {
+                format!(
+                    "{} comes from {}, edit the playlist instead",
+                    config.name, playlist
+                )
+            } else if armed != Some(index) {
+                rfs.delete_armed = Some(index);
+                format!("Press d again to delete {}", config.name)
+            } else {
+                match rfs.delete(index) {
+                    Ok(config) => format!("Deleted {}", config.name),
+                    Err(err) => format!("Failed to save the radio list: {}", err),
                 }
-                app.radio.add_to_list(
-                    Media {
-                        src: Source::M3u8(rfs.radios[selected_index].clone()),
-                    },
-                    true,
+            };
+            app.set_msg(&msg);
+            true
+        }
+        KeyCode::Char('K') | KeyCode::Char('J') => {
+            let index = match selected {
+                Some(index) => index,
+                None => return false,
+            };
+            if index >= rfs.own_stations() {
+                let config = &rfs.radios[index];
+                let playlist = config.playlist.clone().unwrap_or_default();
+                let msg = format!(
+                    "{} comes from {}, edit the playlist instead",
+                    config.name, playlist
                 );
+                app.set_msg(&msg);
+                return true;
+            }
+            if let Err(err) = rfs.move_station(index, code == KeyCode::Char('K')) {
+                app.set_msg(format!("Failed to save the radio list: {}", err).as_str());
             }
-
             true
         }
         _ => false,
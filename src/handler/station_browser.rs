@@ -0,0 +1,133 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    app::{App, Routes},
+    media::{
+        engine::Focus,
+        media::{Media, Source},
+        player::Player,
+    },
+    ui::{radio::RadioConfig, station_browser::StationBrowser},
+};
+
+pub fn open_station_browser(app: &mut App) -> bool {
+    if app.station_browser.is_none() {
+        let location = app.config.catalogue.clone();
+        app.station_browser = Some(StationBrowser::open(location.as_str()));
+        let msg = format!("Loading stations from {}", location);
+        app.set_msg(&msg);
+    }
+    app.route_stack.push(Routes::StationBrowser);
+    true
+}
+
+fn close_station_browser(app: &mut App) {
+    if let Some(Routes::StationBrowser) = app.route_stack.last() {
+        app.route_stack.pop();
+    }
+}
+
+/// The selected station as a radio list entry, HLS if the catalogue says so.
+fn selected_source(app: &App) -> Option<(RadioConfig, bool)> {
+    let station = app.station_browser.as_ref()?.selected()?;
+    let config = RadioConfig {
+        name: station.name.clone(),
+        url: station.url.clone(),
+        ..Default::default()
+    };
+    Some((config, station.hls))
+}
+
+fn preview(app: &mut App) -> bool {
+    let (config, hls) = match selected_source(app) {
+        Some(selected) => selected,
+        None => return false,
+    };
+    app.take_focus(Focus::Radio);
+    let msg = format!("Previewing {}", config.name);
+    let src = if hls {
+        Source::M3u8(config)
+    } else {
+        Source::radio(config)
+    };
+    app.radio.add_to_list(Media { src }, true);
+    app.set_msg(&msg);
+    true
+}
+
+fn add_station(app: &mut App) -> bool {
+    let (config, _) = match selected_source(app) {
+        Some(selected) => selected,
+        None => return false,
+    };
+    if app
+        .radio_fs
+        .radios
+        .iter()
+        .any(|radio| radio.url == config.url)
+    {
+        let msg = format!("{} is already in the radio list", config.name);
+        app.set_msg(&msg);
+        return true;
+    }
+    let name = config.name.clone();
+    let msg = match app.radio_fs.add(config) {
+        Ok(_) => format!("Added {} to the radio list", name),
+        Err(err) => format!("Failed to add {}: {}", name, err),
+    };
+    app.set_msg(&msg);
+    true
+}
+
+pub fn handle_station_browser(app: &mut App, code: KeyCode) -> bool {
+    let browser = match &mut app.station_browser {
+        Some(browser) => browser,
+        None => return false,
+    };
+    match code {
+        KeyCode::Tab => {
+            browser.next_facet();
+            true
+        }
+        KeyCode::Left => {
+            browser.cycle(false);
+            true
+        }
+        KeyCode::Right => {
+            browser.cycle(true);
+            true
+        }
+        KeyCode::Up => {
+            browser.select_next(false);
+            true
+        }
+        KeyCode::Down => {
+            browser.select_next(true);
+            true
+        }
+        KeyCode::Enter => preview(app),
+        KeyCode::Char('a') | KeyCode::Char('A') => add_station(app),
+        KeyCode::Esc => {
+            close_station_browser(app);
+            true
+        }
+        _ => false,
+    }
+}
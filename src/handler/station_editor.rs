@@ -0,0 +1,131 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    app::{App, InputMode, Routes},
+    ui::station_editor::{Field, StationEditor},
+};
+
+/// Open the form for the station at `index` of the radio list, or for a
+/// new station.
+pub fn open_station_editor(app: &mut App, index: Option<usize>) -> bool {
+    let config = index.and_then(|i| app.radio_fs.radios.get(i));
+    if let Some(playlist) = config.and_then(|config| config.playlist.as_ref()) {
+        let msg = format!(
+            "This station comes from {}, edit the playlist instead",
+            playlist
+        );
+        app.set_msg(&msg);
+        return true;
+    }
+    app.station_editor = Some(StationEditor::new(index, config));
+    app.route_stack.push(Routes::StationEditor);
+    app.set_mode(InputMode::Editing);
+    true
+}
+
+fn close_station_editor(app: &mut App) {
+    app.station_editor = None;
+    if let Some(Routes::StationEditor) = app.route_stack.last() {
+        app.route_stack.pop();
+    }
+    app.set_mode(InputMode::Normal);
+}
+
+fn save_station(app: &mut App) -> bool {
+    let editor = match &app.station_editor {
+        Some(editor) => editor,
+        None => return false,
+    };
+    let config = match editor.config() {
+        Ok(config) => config,
+        Err(err) => {
+            app.set_msg(&err);
+            return true;
+        }
+    };
+    let name = config.name.clone();
+    let index = editor.index;
+    match app.radio_fs.update(index, config) {
+        Ok(_) => {
+            close_station_editor(app);
+            app.set_msg(format!("Saved {}", name).as_str());
+        }
+        Err(err) => app.set_msg(format!("Failed to save {}: {}", name, err).as_str()),
+    }
+    true
+}
+
+pub fn handle_station_editor(app: &mut App, code: KeyCode) -> bool {
+    let editor = match &mut app.station_editor {
+        Some(editor) => editor,
+        None => return false,
+    };
+    match code {
+        KeyCode::Esc => {
+            close_station_editor(app);
+            app.set_msg("Station editing discarded");
+            true
+        }
+        KeyCode::Tab | KeyCode::Down => {
+            editor.next_field(true);
+            true
+        }
+        KeyCode::BackTab | KeyCode::Up => {
+            editor.next_field(false);
+            true
+        }
+        KeyCode::Enter => match editor.focused() {
+            Field::Favourite => {
+                editor.favourite = !editor.favourite;
+                true
+            }
+            Field::Test => {
+                match editor.test() {
+                    Ok(_) => app.set_msg("Connecting..."),
+                    Err(err) => app.set_msg(&err),
+                }
+                true
+            }
+            Field::Save => save_station(app),
+            _ => {
+                editor.next_field(true);
+                true
+            }
+        },
+        KeyCode::Backspace => {
+            if let Some(text) = editor.text_mut() {
+                text.pop();
+            }
+            true
+        }
+        KeyCode::Char(' ') if editor.focused() == Field::Favourite => {
+            editor.favourite = !editor.favourite;
+            true
+        }
+        KeyCode::Char(c) => {
+            if let Some(text) = editor.text_mut() {
+                text.push(c);
+            }
+            true
+        }
+        // nothing else leaves the form
+        _ => true,
+    }
+}
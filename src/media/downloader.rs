@@ -0,0 +1,241 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::util::{
+    hls::{retry_delay, FetchCache, SegmentQueue, SegmentRef},
+    net::http,
+};
+use bytes::Bytes;
+
+// attempts per segment before it is skipped
+const MAX_ATTEMPTS: u32 = 4;
+// downloaded audio waiting to be played, VOD playlists are not fetched whole
+const MAX_BUFFERED: Duration = Duration::from_secs(30);
+
+/// A downloaded media segment waiting to be decoded.
+#[derive(Clone)]
+pub struct HlsSegment {
+    // tuning session of the station it belongs to
+    pub session: u64,
+    pub info: SegmentRef,
+    pub data: Bytes,
+}
+
+struct Job {
+    segments: Vec<SegmentRef>,
+    window: Range<u64>,
+}
+
+/// Downloads the segments of one station on a single thread, in media
+/// sequence order. Dropping it cancels the downloads.
+pub struct SegmentDownloader {
+    jobs: Sender<Job>,
+    segments: Receiver<HlsSegment>,
+    // milliseconds of audio handed out and not played yet
+    buffered: Arc<AtomicU64>,
+    // segments given up on in a row
+    failures: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SegmentDownloader {
+    /// Download the segments of `session`, sending `headers` with every
+    /// request.
+    pub fn new(session: u64, headers: Vec<(String, String)>) -> Self {
+        let (jobs_tx, jobs_rx) = channel();
+        let (segments_tx, segments_rx) = channel();
+        let buffered = Arc::new(AtomicU64::new(0));
+        let failures = Arc::new(AtomicU32::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let shared = Shared {
+            buffered: buffered.clone(),
+            failures: failures.clone(),
+            cancelled: cancelled.clone(),
+        };
+        thread::spawn(move || {
+            Worker::new(session, jobs_rx, segments_tx, shared, headers).run();
+        });
+        Self {
+            jobs: jobs_tx,
+            segments: segments_rx,
+            buffered,
+            failures,
+            cancelled,
+        }
+    }
+
+    /// Segments of a playlist load, `window` are the media sequence numbers
+    /// the playlist still lists.
+    pub fn push(&self, segments: Vec<SegmentRef>, window: Range<u64>) {
+        let _ = self.jobs.send(Job { segments, window });
+    }
+
+    pub fn try_recv(&self) -> Option<HlsSegment> {
+        self.segments.try_recv().ok()
+    }
+
+    /// Segments skipped in a row after running out of attempts.
+    pub fn failures(&self) -> u32 {
+        self.failures.load(Ordering::SeqCst)
+    }
+
+    /// A segment handed out by `try_recv` finished playing or was dropped.
+    pub fn played(&self, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        let _ = self
+            .buffered
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |buffered| {
+                Some(buffered.saturating_sub(ms))
+            });
+    }
+}
+
+impl Drop for SegmentDownloader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+// counters the worker shares with its `SegmentDownloader`
+struct Shared {
+    buffered: Arc<AtomicU64>,
+    failures: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+}
+
+struct Worker {
+    session: u64,
+    jobs: Receiver<Job>,
+    segments: Sender<HlsSegment>,
+    buffered: Arc<AtomicU64>,
+    failures: Arc<AtomicU32>,
+    cancelled: Arc<AtomicBool>,
+    queue: SegmentQueue,
+    cache: FetchCache,
+    // sent with every request of the station
+    headers: Vec<(String, String)>,
+}
+
+impl Worker {
+    fn new(
+        session: u64,
+        jobs: Receiver<Job>,
+        segments: Sender<HlsSegment>,
+        shared: Shared,
+        headers: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            session,
+            jobs,
+            segments,
+            buffered: shared.buffered,
+            failures: shared.failures,
+            cancelled: shared.cancelled,
+            queue: SegmentQueue::default(),
+            cache: FetchCache::default(),
+            headers,
+        }
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn run(mut self) {
+        while !self.cancelled() {
+            // wait for work when there is none, otherwise just look for more
+            loop {
+                let job = if self.queue.is_empty() {
+                    self.jobs.recv().ok()
+                } else {
+                    match self.jobs.try_recv() {
+                        Ok(job) => Some(job),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => None,
+                    }
+                };
+                match job {
+                    Some(job) => {
+                        self.queue.push(job.segments, job.window);
+                    }
+                    None => return,
+                }
+            }
+            if Duration::from_millis(self.buffered.load(Ordering::SeqCst)) >= MAX_BUFFERED {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            let info = match self.queue.pop() {
+                Some(info) => info,
+                None => continue,
+            };
+            let data = match self.download(&info) {
+                Some(data) => data,
+                // given up, playback continues with the next one
+                None => {
+                    self.failures.fetch_add(1, Ordering::SeqCst);
+                    continue;
+                }
+            };
+            self.failures.store(0, Ordering::SeqCst);
+            self.buffered
+                .fetch_add(info.duration.as_millis() as u64, Ordering::SeqCst);
+            let segment = HlsSegment {
+                session: self.session,
+                info,
+                data,
+            };
+            if self.segments.send(segment).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn download(&mut self, info: &SegmentRef) -> Option<Bytes> {
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                thread::sleep(retry_delay(attempt - 1));
+            }
+            if self.cancelled() {
+                return None;
+            }
+            let headers = &self.headers;
+            let fetch = |url: &str, range| {
+                let http = http();
+                http.block_on(http.fetch_range(url, range, headers))
+                    .map_err(failure::Error::from)
+            };
+            let data = fetch(info.url.as_str(), info.byte_range)
+                .and_then(|data| info.prepare(data, &mut self.cache, fetch));
+            if let Ok(data) = data {
+                return Some(data);
+            }
+        }
+        None
+    }
+}
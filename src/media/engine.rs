@@ -0,0 +1,86 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use rodio::{cpal, OutputStream, OutputStreamBuilder, Sink};
+
+/// The output stream of the app, music and radio play as sinks on its
+/// mixer.
+pub struct AudioEngine {
+    stream: OutputStream,
+}
+
+impl AudioEngine {
+    pub fn new() -> Self {
+        for dev in cpal::available_hosts() {
+            println!("{:?}", dev);
+        }
+        let builder = OutputStreamBuilder::from_default_device().unwrap();
+        let stream = builder.open_stream_or_fallback().unwrap();
+        Self { stream }
+    }
+
+    /// A new source on the mixer.
+    pub fn sink(&self) -> Sink {
+        Sink::connect_new(self.stream.mixer())
+    }
+}
+
+/// The source the user started last.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Focus {
+    Music,
+    Radio,
+}
+
+/// What happens to the other source when one takes the focus.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FocusPolicy {
+    // paused
+    Exclusive,
+    // keeps playing at a fraction of its volume
+    Duck(f32),
+}
+
+impl FocusPolicy {
+    /// `exclusive` or `duck`, ducking to `duck_level`.
+    pub fn parse(value: &str, duck_level: f32) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "exclusive" => Some(FocusPolicy::Exclusive),
+            "duck" => Some(FocusPolicy::Duck(duck_level)),
+            _ => None,
+        }
+    }
+
+    /// `<percent>` of the volume kept while ducked, `%` optional and
+    /// capped at 100.
+    pub fn parse_duck_level(value: &str) -> Option<f32> {
+        let percent = value.trim().trim_end_matches('%').parse::<u8>().ok()?;
+        Some(percent.min(100) as f32 / 100.0)
+    }
+
+    /// Duck levels of music and radio while `focus` is `playing` or not.
+    pub fn ducked(&self, focus: Focus, playing: bool) -> (Option<f32>, Option<f32>) {
+        let level = match self {
+            FocusPolicy::Duck(level) if playing => *level,
+            _ => return (None, None),
+        };
+        match focus {
+            Focus::Music => (None, Some(level)),
+            Focus::Radio => (Some(level), None),
+        }
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{channel, sync_channel, Receiver, SyncSender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use rodio::{ChannelCount, Decoder, SampleRate, Source};
+
+use crate::util::net::BodyReader;
+
+// how often a reader ahead of the download looks again
+const WAIT: Duration = Duration::from_millis(50);
+const BLOCK: usize = 64 * 1024;
+// decoded audio is handed to the mixer in half second chunks
+const CHUNKS_PER_SECOND: usize = 2;
+// chunks decoded ahead of playback
+const AHEAD_CHUNKS: usize = 8;
+
+#[derive(Default)]
+struct Progress {
+    written: AtomicU64,
+    // Content-Length, 0 when the server does not tell
+    total: AtomicU64,
+    finished: AtomicBool,
+    failed: Mutex<Option<String>>,
+    cancelled: AtomicBool,
+}
+
+/// An episode downloading to a file on a thread of its own. Dropping it
+/// stops the download.
+pub struct EpisodeDownload {
+    pub path: PathBuf,
+    progress: Arc<Progress>,
+}
+
+impl EpisodeDownload {
+    pub fn start(url: &str, path: &Path) -> Result<Self, failure::Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path)?;
+        let progress = Arc::new(Progress::default());
+        let shared = progress.clone();
+        let url = url.to_string();
+        thread::spawn(move || {
+            if let Err(err) = download(url.as_str(), file, &shared) {
+                *shared.failed.lock().unwrap() = Some(err.to_string());
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            progress,
+        })
+    }
+
+    pub fn written(&self) -> u64 {
+        self.progress.written.load(Ordering::SeqCst)
+    }
+
+    pub fn total(&self) -> Option<u64> {
+        Some(self.progress.total.load(Ordering::SeqCst)).filter(|total| *total > 0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress.finished.load(Ordering::SeqCst)
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.progress.failed.lock().unwrap().clone()
+    }
+
+    /// A reader of the file, waiting for the bytes still downloading.
+    pub fn reader(&self) -> Result<PartialFile, failure::Error> {
+        Ok(PartialFile {
+            file: File::open(&self.path)?,
+            progress: self.progress.clone(),
+        })
+    }
+}
+
+impl Drop for EpisodeDownload {
+    fn drop(&mut self) {
+        self.progress.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+fn download(url: &str, mut file: File, progress: &Progress) -> Result<(), failure::Error> {
+    let mut body = BodyReader::open(url, &[])?;
+    let total = body
+        .header("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    progress.total.store(total, Ordering::SeqCst);
+    let mut buf = vec![0u8; BLOCK];
+    loop {
+        if progress.cancelled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let n = body.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        progress.written.fetch_add(n as u64, Ordering::SeqCst);
+    }
+    file.flush()?;
+    progress.finished.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// A downloading file read by an `EpisodeDecoder`. Reads past what is
+/// written so far wait for the download, which only happens when playback
+/// catches up with a slow connection.
+pub struct PartialFile {
+    file: File,
+    progress: Arc<Progress>,
+}
+
+impl PartialFile {
+    // wait until `len` bytes are there or the download ended
+    fn wait_for(&self, len: u64) -> std::io::Result<()> {
+        loop {
+            let progress = &self.progress;
+            if progress.written.load(Ordering::SeqCst) >= len
+                || progress.finished.load(Ordering::SeqCst)
+            {
+                return Ok(());
+            }
+            if let Some(err) = progress.failed.lock().unwrap().clone() {
+                return Err(std::io::Error::other(err));
+            }
+            if progress.cancelled.load(Ordering::SeqCst) {
+                return Err(std::io::Error::other("Download stopped"));
+            }
+            thread::sleep(WAIT);
+        }
+    }
+}
+
+impl Read for PartialFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let position = self.file.stream_position()?;
+        self.wait_for(position + 1)?;
+        let written = self.progress.written.load(Ordering::SeqCst);
+        if written <= position {
+            // finished, the end of the file
+            return self.file.read(buf);
+        }
+        let len = ((written - position) as usize).min(buf.len());
+        self.file.read(&mut buf[..len])
+    }
+}
+
+impl Seek for PartialFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            SeekFrom::End(offset) => {
+                let total = self.progress.total.load(Ordering::SeqCst);
+                if total == 0 {
+                    // the length is only known once everything is there
+                    if !self.progress.finished.load(Ordering::SeqCst) {
+                        let err = "Length unknown until downloaded";
+                        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, err));
+                    }
+                    return self.file.seek(pos);
+                }
+                let to = (total as i64 + offset).max(0) as u64;
+                self.file.seek(SeekFrom::Start(to))
+            }
+            pos => self.file.seek(pos),
+        }
+    }
+}
+
+/// An episode decoded on a thread of its own, so that waiting for the
+/// download or seeking to where it was left holds up neither the UI nor
+/// the other sources of the mixer.
+pub struct EpisodeDecoder {
+    ready: Receiver<Result<EpisodeAudio, String>>,
+}
+
+impl EpisodeDecoder {
+    /// Decode `reader` from `resume`, `hint` is the file extension and
+    /// `duration` the one of the feed, for when the decoder cannot tell.
+    pub fn start<R>(
+        reader: R,
+        byte_len: Option<u64>,
+        hint: Option<String>,
+        duration: Option<Duration>,
+        resume: Duration,
+    ) -> Self
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let (chunks_tx, chunks) = sync_channel(AHEAD_CHUNKS);
+            match open(reader, byte_len, hint, duration, resume) {
+                Ok((decoder, duration, start)) => {
+                    let audio = EpisodeAudio {
+                        duration,
+                        start,
+                        channels: decoder.channels(),
+                        sample_rate: decoder.sample_rate(),
+                        chunks,
+                        chunk: vec![].into_iter(),
+                        silence: 0,
+                    };
+                    if tx.send(Ok(audio)).is_ok() {
+                        decode(decoder, &chunks_tx);
+                    }
+                }
+                Err(err) => {
+                    let _ = tx.send(Err(err));
+                }
+            }
+        });
+        Self { ready: rx }
+    }
+
+    /// A decoder that failed before it started.
+    pub fn failed(err: String) -> Self {
+        let (tx, rx) = channel();
+        let _ = tx.send(Err(err));
+        Self { ready: rx }
+    }
+
+    /// The audio once decoding started, None until then.
+    pub fn try_ready(&self) -> Option<Result<EpisodeAudio, String>> {
+        match self.ready.try_recv() {
+            Ok(ready) => Some(ready),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("Decoding stopped".to_string())),
+        }
+    }
+}
+
+// the decoder at `resume`, with the duration and where it starts
+fn open<R>(
+    reader: R,
+    byte_len: Option<u64>,
+    hint: Option<String>,
+    duration: Option<Duration>,
+    resume: Duration,
+) -> Result<(Decoder<R>, Duration, Duration), String>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    let mut builder = Decoder::builder().with_data(reader).with_seekable(true);
+    if let Some(byte_len) = byte_len {
+        builder = builder.with_byte_len(byte_len);
+    }
+    if let Some(hint) = hint {
+        builder = builder.with_hint(hint.as_str());
+    }
+    let mut decoder = builder.build().map_err(|err| err.to_string())?;
+    if decoder.channels() == 0 || decoder.sample_rate() == 0 {
+        return Err("Unsupported audio format".to_string());
+    }
+    let duration = decoder
+        .total_duration()
+        .or(duration)
+        .ok_or_else(|| "Unknown duration".to_string())?;
+    let start = if !resume.is_zero() && decoder.try_seek(resume).is_ok() {
+        resume
+    } else {
+        Duration::ZERO
+    };
+    Ok((decoder, duration, start))
+}
+
+fn decode<R>(decoder: Decoder<R>, chunks: &SyncSender<Vec<f32>>)
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    let chunk_len =
+        decoder.sample_rate() as usize * decoder.channels() as usize / CHUNKS_PER_SECOND;
+    let mut chunk = Vec::with_capacity(chunk_len);
+    for sample in decoder {
+        chunk.push(sample);
+        if chunk.len() < chunk_len {
+            continue;
+        }
+        let full = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_len));
+        // the episode was stopped
+        if chunks.send(full).is_err() {
+            return;
+        }
+    }
+    if !chunk.is_empty() {
+        let _ = chunks.send(chunk);
+    }
+}
+
+/// The decoded audio of an `EpisodeDecoder` for the mixer. When the
+/// decoder falls behind it plays silence rather than waiting. Dropping it
+/// stops the decoder.
+pub struct EpisodeAudio {
+    pub duration: Duration,
+    // the resume position, or zero when it could not be sought to
+    pub start: Duration,
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+    chunks: Receiver<Vec<f32>>,
+    chunk: std::vec::IntoIter<f32>,
+    // samples of silence left, whole frames so the channels stay in order
+    silence: usize,
+}
+
+impl Iterator for EpisodeAudio {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(0.0);
+        }
+        loop {
+            if let Some(sample) = self.chunk.next() {
+                return Some(sample);
+            }
+            match self.chunks.try_recv() {
+                Ok(chunk) => self.chunk = chunk.into_iter(),
+                Err(TryRecvError::Empty) => {
+                    self.silence = self.channels as usize - 1;
+                    return Some(0.0);
+                }
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Source for EpisodeAudio {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.duration.saturating_sub(self.start))
+    }
+}
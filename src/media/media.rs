@@ -16,12 +16,33 @@
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::ui::radio::RadioConfig;
+use crate::util::playlist::{detect_kind, StreamKind};
 
 pub enum Source {
     M3u8(RadioConfig),
+    // MPEG-DASH manifest
+    Dash(RadioConfig),
+    // progressive HTTP radio, e.g. Icecast or Shoutcast
+    Stream(RadioConfig),
     Local(String),
 }
 
+impl Source {
+    /// HLS for `.m3u8` urls, DASH for `.mpd` urls, a progressive stream
+    /// otherwise.
+    pub fn radio(config: RadioConfig) -> Self {
+        Source::of_kind(detect_kind(config.url.as_str(), None), config)
+    }
+
+    pub fn of_kind(kind: StreamKind, config: RadioConfig) -> Self {
+        match kind {
+            StreamKind::Hls => Source::M3u8(config),
+            StreamKind::Dash => Source::Dash(config),
+            StreamKind::Progressive => Source::Stream(config),
+        }
+    }
+}
+
 pub struct Media {
     pub src: Source,
 }
@@ -15,5 +15,9 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod downloader;
+pub mod engine;
+pub mod episode;
 pub mod media;
 pub mod player;
+pub mod stream;
@@ -15,30 +15,42 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::cmp::max;
-
-use std::sync::mpsc::{Receiver, Sender};
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, TryRecvError};
 use std::{
     fs::File,
-    io::{BufReader, Write},
+    io::BufReader,
     ops::Add,
     path::Path,
     sync::mpsc::channel,
     thread,
-    time::{Duration, Instant, SystemTime},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use m3u8_rs::{MediaPlaylist, Playlist};
+use m3u8_rs::{MediaPlaylist, Playlist, VariantStream};
 
-use rodio::cpal;
-use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink, Source};
+use rodio::{Decoder, Sink, Source};
 use tui::widgets::ListState;
 
-use crate::util::lyrics::Lyrics;
-use crate::util::m3u8::empty_cache;
-use crate::{m3u8::download_m3u8_playlist, util::net::download_as_bytes};
+use crate::m3u8::download_m3u8_playlist;
+use crate::util::dash::{Manifest, TimelineNumbers};
+use crate::util::hls::{
+    blocking_reload_url, low_latency_segments, new_segments, next_cursor, next_variant,
+    playlist_duration, select_variant, variant_label, LatencyMode, LowLatency, PartCursor,
+    QueryPropagation, Reconnects, ReloadScheduler, UriResolver, VariantPolicy,
+};
+use crate::util::id3::{segment_id3, Id3Tag, TimedId3};
+use crate::util::lyrics::{Lyrics, LyricsFinder};
+use crate::util::net::fetch_text;
+use crate::util::timeshift::{format_clock, Timeshift};
 
+use super::downloader::{HlsSegment, SegmentDownloader};
+use super::engine::AudioEngine;
+use super::episode::EpisodeAudio;
 use super::media::Media;
+use super::stream::{StreamEvent, StreamSession, CHUNK_DURATION};
+use crate::ui::radio::RadioConfig;
+use crate::util::history;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum PlayStatus {
@@ -63,8 +75,10 @@ pub struct PlayList {
 }
 
 pub trait Player {
-    // 初始化
-    fn new() -> Self;
+    // 初始化，在音频引擎上播放
+    fn new(engine: &AudioEngine) -> Self
+    where
+        Self: Sized;
 
     // 添加歌曲
     fn add_to_list(&mut self, media: Media, once: bool) -> bool;
@@ -99,11 +113,17 @@ pub trait Player {
     // 有歌词
     fn has_lyrics(&self) -> bool;
 
+    // 正在播放的节目
+    fn now_playing(&self) -> Option<NowPlaying>;
+
     // 音量
     fn volume(&self) -> f32;
 
     // 设置音量
     fn set_volume(&mut self, new_volume: f32) -> bool;
+
+    // 让出焦点时压低音量，None 恢复
+    fn duck(&mut self, level: Option<f32>);
 }
 
 pub struct MusicPlayer {
@@ -112,30 +132,29 @@ pub struct MusicPlayer {
     pub total_time: Duration,
     pub play_list: PlayList,
     // media: Media,
-    // stream
-    stream: OutputStream,
     sink: Sink,
+    // set by the user, before ducking
+    volume: f32,
+    ducked: Option<f32>,
     current_lyric: Option<String>,
     initialized: bool,
+    pub lyrics_finder: LyricsFinder,
 }
 
 impl Player for MusicPlayer {
-    fn new() -> Self {
-        for dev in cpal::available_hosts() {
-            println!("{:?}", dev);
-        }
-        let builder = OutputStreamBuilder::from_default_device().unwrap();
-        let stream = builder.open_stream_or_fallback().unwrap();
-        let sink = Sink::connect_new(stream.mixer());
+    fn new(engine: &AudioEngine) -> Self {
+        let sink = engine.sink();
         Self {
             current_time: Duration::from_secs(0),
             total_time: Duration::from_secs(0),
             play_list: PlayList { lists: vec![] },
             // media: f,
-            stream,
             sink,
+            volume: 1.0,
+            ducked: None,
             current_lyric: None,
             initialized: false,
+            lyrics_finder: LyricsFinder::new(vec![]),
         }
     }
 
@@ -144,7 +163,9 @@ impl Player for MusicPlayer {
             super::media::Source::Local(path) => {
                 return self.play_with_file(path, once);
             }
-            super::media::Source::M3u8(_path) => false,
+            super::media::Source::M3u8(_)
+            | super::media::Source::Dash(_)
+            | super::media::Source::Stream(_) => false,
         }
     }
 
@@ -262,7 +283,7 @@ impl Player for MusicPlayer {
                         self.total_time = song.duration.clone();
                         // add lyrics
                         let selected_index = song.lyrics_index.selected().unwrap();
-                        if selected_index + 1 < song.lyrics.list.len() {
+                        if song.lyrics.synced && selected_index + 1 < song.lyrics.list.len() {
                             let next_lyric = &song.lyrics.list[selected_index + 1];
                             if self.current_time > next_lyric.time {
                                 song.lyrics_index.select(Some(selected_index + 1));
@@ -296,14 +317,27 @@ impl Player for MusicPlayer {
             && !self.play_list.lists.first().unwrap().lyrics.list.is_empty()
     }
 
+    fn now_playing(&self) -> Option<NowPlaying> {
+        None
+    }
+
     fn volume(&self) -> f32 {
-        return self.sink.volume();
+        return self.volume;
     }
 
     fn set_volume(&mut self, new_volume: f32) -> bool {
-        self.sink.set_volume(new_volume);
+        self.volume = new_volume;
+        self.sink
+            .set_volume(new_volume * self.ducked.unwrap_or(1.0));
         true
     }
+
+    fn duck(&mut self, level: Option<f32>) {
+        if self.ducked != level {
+            self.ducked = level;
+            self.set_volume(self.volume);
+        }
+    }
 }
 
 impl MusicPlayer {
@@ -311,6 +345,43 @@ impl MusicPlayer {
         return self.play_list.lists.first();
     }
 
+    pub fn playing_song_mut(&mut self) -> Option<&mut PlayListItem> {
+        self.play_list.lists.first_mut()
+    }
+
+    /// Exact playback position of the current song, `current_time` only
+    /// follows it on every tick.
+    pub fn position(&self) -> Duration {
+        match self.playing_song().map(|song| &song.status) {
+            Some(PlayStatus::Playing(instant, duration)) => duration.add(instant.elapsed()),
+            Some(PlayStatus::Stopped(duration)) => *duration,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Play a podcast episode decoded by an `EpisodeDecoder`, from where it
+    /// started.
+    pub fn play_episode(&mut self, name: &str, path: &str, audio: EpisodeAudio) {
+        let (duration, start) = (audio.duration, audio.start);
+        self.stop();
+        self.sink.append(audio);
+        self.play_list.lists.clear();
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.play_list.lists.push(PlayListItem {
+            name: name.to_string(),
+            duration,
+            current_pos: start,
+            status: PlayStatus::Stopped(start),
+            path: path.to_string(),
+            lyrics: self.lyrics_finder.find(path),
+            lyrics_index: state,
+        });
+        self.initialized = true;
+        self.play();
+        self.tick();
+    }
+
     fn play_with_file(&mut self, path: String, once: bool) -> bool {
         let duration: Duration;
         if path.ends_with(".mp3") {
@@ -344,7 +415,7 @@ impl MusicPlayer {
             }
         }
         // find lyrics
-        let lyrics = Lyrics::from_music_path(path.as_str());
+        let lyrics = self.lyrics_finder.find(path.as_str());
         // open
         match File::open(path.as_str()) {
             Ok(f) => {
@@ -392,82 +463,308 @@ impl Drop for MusicPlayer {
 }
 
 pub struct RadioItem {
+    name: String,
+    list: MediaPlaylist,
+    low_latency: LowLatency,
+    // media playlist in use, and where it was served from after redirects
+    url: String,
+    base_url: String,
+    // final url of the station's master playlist, if it has one
+    master_url: Option<String>,
+    variants: Vec<VariantStream>,
+    variant: Option<usize>,
+    // a DASH manifest, reloaded instead of a media playlist
+    dash: bool,
+    // numbers of its live timeline segments by time, for the next reload
+    numbers: TimelineNumbers,
+}
+
+/// Media playlist fetched from a station url, with the variant it was
+/// resolved through if the url points at a master playlist.
+struct ResolvedPlaylist {
     list: MediaPlaylist,
+    low_latency: LowLatency,
     url: String,
+    base_url: String,
+    master_url: Option<String>,
+    variants: Vec<VariantStream>,
+    variant: Option<usize>,
+    numbers: TimelineNumbers,
+}
+
+fn resolve_media_playlist(
+    url: &str,
+    policy: VariantPolicy,
+    query: QueryPropagation,
+    headers: &[(String, String)],
+    timeout: Option<Duration>,
+) -> Result<ResolvedPlaylist, failure::Error> {
+    let fetched = download_m3u8_playlist(url.to_string(), headers.to_vec(), timeout)?;
+    match fetched.playlist {
+        Playlist::MediaPlaylist(list) => Ok(ResolvedPlaylist {
+            list,
+            low_latency: fetched.low_latency,
+            url: url.to_string(),
+            base_url: fetched.url,
+            master_url: None,
+            variants: vec![],
+            variant: None,
+            numbers: TimelineNumbers::default(),
+        }),
+        Playlist::MasterPlaylist(master) => {
+            let index = select_variant(&master, policy)
+                .ok_or_else(|| failure::format_err!("No playable variant"))?;
+            let resolver = UriResolver::new(fetched.url.as_str(), query);
+            let media_url = resolver.resolve(master.variants[index].uri.as_str());
+            let media = download_m3u8_playlist(media_url.clone(), headers.to_vec(), None)?;
+            match media.playlist {
+                Playlist::MediaPlaylist(list) => Ok(ResolvedPlaylist {
+                    list,
+                    low_latency: media.low_latency,
+                    url: media_url,
+                    base_url: media.url,
+                    master_url: Some(fetched.url),
+                    variants: master.variants,
+                    variant: Some(index),
+                    numbers: TimelineNumbers::default(),
+                }),
+                Playlist::MasterPlaylist(_) => Err(failure::format_err!("Nested master playlist")),
+            }
+        }
+    }
+}
+
+/// A representation of a DASH manifest as a media playlist, the audio
+/// representations as variants. `url` is the manifest's, with `#<id>` of
+/// the representation to keep once one was picked. `numbers` are those of
+/// the previous load.
+fn resolve_dash_manifest(
+    url: &str,
+    policy: VariantPolicy,
+    headers: &[(String, String)],
+    mut numbers: TimelineNumbers,
+) -> Result<ResolvedPlaylist, failure::Error> {
+    let (manifest_url, pinned) = match url.split_once('#') {
+        Some((manifest_url, id)) => (manifest_url, Some(format!("#{}", id))),
+        None => (url, None),
+    };
+    let (text, base_url) = fetch_text(manifest_url, headers)?;
+    let manifest = Manifest::parse(text.as_str(), base_url.as_str())?;
+    let master = manifest.variants();
+    let index = pinned
+        .and_then(|uri| master.variants.iter().position(|v| v.uri == uri))
+        .or_else(|| select_variant(&master, policy))
+        .ok_or_else(|| failure::format_err!("No playable representation"))?;
+    let uri = master.variants[index].uri.as_str();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    Ok(ResolvedPlaylist {
+        list: manifest.media_playlist(&uri[1..], now, &mut numbers),
+        low_latency: LowLatency::default(),
+        url: format!("{}{}", manifest_url, uri),
+        base_url,
+        master_url: Some(manifest_url.to_string()),
+        variants: master.variants,
+        variant: Some(index),
+        numbers,
+    })
+}
+
+fn resolve_playlist(
+    url: &str,
+    dash: bool,
+    policy: VariantPolicy,
+    query: QueryPropagation,
+    headers: &[(String, String)],
+    timeout: Option<Duration>,
+    numbers: TimelineNumbers,
+) -> Result<ResolvedPlaylist, failure::Error> {
+    if dash {
+        resolve_dash_manifest(url, policy, headers, numbers)
+    } else {
+        resolve_media_playlist(url, policy, query, headers, timeout)
+    }
+}
+
+// half second chunks of a progressive station queued in the sink
+const STREAM_AHEAD_CHUNKS: usize = 4;
+// HLS segments in the sink, the playing one included
+const FEED_AHEAD_SEGMENTS: usize = 2;
+// segments skipped in a row before reconnecting
+const SEGMENT_FAILURES: u32 = 3;
+// moving forward closer than this to the live edge goes live
+const LIVE_TOLERANCE: Duration = Duration::from_secs(10);
+
+/// Connection state of the playing station.
+pub struct StreamHealth {
+    // downloaded audio ahead of playback
+    pub buffered: Duration,
+    pub bitrate: Option<u64>,
+    // since the playlist was last loaded, HLS only
+    pub refreshed: Option<Duration>,
+    pub error: Option<String>,
+    // attempt of the reconnect waiting
+    pub reconnecting: Option<u32>,
+}
+
+/// A segment in the sink.
+struct QueuedSegment {
+    duration: Duration,
+    date_time: Option<i64>,
+    // tags not reached yet
+    id3: Vec<TimedId3>,
+}
+
+/// What a radio station is playing, from timed metadata.
+#[derive(Clone, Default, PartialEq)]
+pub struct NowPlaying {
+    pub song: Option<String>,
+    pub programme: Option<String>,
+    // broadcast time of the audio playing, from EXT-X-PROGRAM-DATE-TIME
+    pub wall_clock: Option<SystemTime>,
+}
+
+impl NowPlaying {
+    fn apply(&mut self, tag: Id3Tag) {
+        let song = match (tag.artist, tag.title) {
+            (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+            (None, Some(title)) => Some(title),
+            (Some(artist), None) => Some(artist),
+            (None, None) => None,
+        };
+        if song.is_some() {
+            self.song = song;
+        }
+        if tag.programme.is_some() {
+            self.programme = tag.programme;
+        }
+    }
+}
+
+/// A station being tuned in the background.
+struct Tuning {
+    session: u64,
+    name: String,
+    dash: bool,
+    result: Receiver<Result<ResolvedPlaylist, failure::Error>>,
 }
 
 #[allow(dead_code)]
 pub struct RadioPlayer {
     pub item: Option<RadioItem>,
     pub list: Vec<PlayListItem>,
-    stream: OutputStream,
     sink: Sink,
+    // set by the user, before ducking
+    volume: f32,
+    ducked: Option<f32>,
     is_playing: bool,
-    last_playing_id: i32,
-    data_tx: Sender<bytes::Bytes>,
-    data_rx: Receiver<bytes::Bytes>,
+    // media sequence number and part of the next chunk to download
+    cursor: Option<PartCursor>,
+    downloader: Option<SegmentDownloader>,
+    // bumped on every tune, segments of older sessions are discarded
+    session: u64,
+    tuning: Option<Tuning>,
+    // progressive HTTP station, instead of `item`
+    icy: Option<StreamSession>,
+    // the station picked by the user and which of its urls is playing
+    station: Option<RadioConfig>,
+    attempt: usize,
+    // for the status line, taken by the app
+    message: Option<String>,
+    // last playlist load
     elasped: SystemTime,
-    gap: Duration,
+    reload: ReloadScheduler,
+    // playlist reload running in the background
+    reloading: Option<Receiver<Result<ResolvedPlaylist, failure::Error>>>,
+    reload_failed: bool,
+    // durations of the segments in the sink, to follow VOD progress
+    queued: VecDeque<QueuedSegment>,
+    played: Duration,
+    // downloaded segments, fed to the sink a couple at a time
+    timeshift: Timeshift<HlsSegment>,
+    // start into the next segment fed, after seeking
+    skip: Option<Duration>,
+    // how far into the playing segment it was started
+    skipped: Duration,
+    // paused or moved back from the live edge
+    shifted: bool,
+    pub timeshift_capacity: Duration,
+    now_playing: NowPlaying,
+    // reconnects in a row, and when the next one is due
+    reconnects: Reconnects,
+    reconnect_at: Option<Instant>,
+    // the station played before, so failures are worth reconnecting
+    was_connected: bool,
+    // last failure, until audio arrives again
+    error: Option<String>,
+    // last successful playlist load
+    refreshed: Option<Instant>,
+    // kbps of the last segment, or announced by the stream
+    bitrate: Option<u64>,
+    pub variant_policy: VariantPolicy,
+    pub query_propagation: QueryPropagation,
+    pub latency_mode: LatencyMode,
 }
 
 impl Player for RadioPlayer {
-    fn new() -> Self {
-        let builder = OutputStreamBuilder::from_default_device().unwrap();
-        let stream = builder.open_stream_or_fallback().unwrap();
-        let sink = Sink::connect_new(stream.mixer());
-        let (tx, rx) = channel();
-        empty_cache();
+    fn new(engine: &AudioEngine) -> Self {
+        let sink = engine.sink();
         RadioPlayer {
             item: None,
             list: vec![],
-            stream,
             sink,
+            volume: 1.0,
+            ducked: None,
             is_playing: false,
-            last_playing_id: -1,
-            data_rx: rx,
-            data_tx: tx,
+            cursor: None,
+            downloader: None,
+            session: 0,
+            tuning: None,
+            icy: None,
+            station: None,
+            attempt: 0,
+            message: None,
             elasped: SystemTime::now(),
-            gap: Duration::from_secs(5),
+            reload: ReloadScheduler::new(),
+            reloading: None,
+            reload_failed: false,
+            queued: VecDeque::new(),
+            played: Duration::ZERO,
+            timeshift: Timeshift::new(Duration::ZERO),
+            skip: None,
+            skipped: Duration::ZERO,
+            shifted: false,
+            timeshift_capacity: Duration::from_secs(30 * 60),
+            now_playing: NowPlaying::default(),
+            reconnects: Reconnects::default(),
+            reconnect_at: None,
+            was_connected: false,
+            error: None,
+            refreshed: None,
+            bitrate: None,
+            variant_policy: VariantPolicy::Highest,
+            query_propagation: QueryPropagation::Off,
+            latency_mode: LatencyMode::Stable,
         }
     }
 
     fn add_to_list(&mut self, media: Media, _: bool) -> bool {
-        self.last_playing_id = -1;
-        let src = media.src;
-        match src {
-            super::media::Source::M3u8(url) => {
-                let (tx, rx) = channel();
-                let m3u8_url = url.url.clone();
-                thread::spawn(move || {
-                    let playlist = download_m3u8_playlist(m3u8_url);
-                    tx.send(playlist).unwrap();
-                });
-                match rx.recv_timeout(Duration::from_secs(5)) {
-                    Ok(list) => {
-                        if let Ok(playlist) = list {
-                            match playlist {
-                                Playlist::MasterPlaylist(_) => {}
-                                Playlist::MediaPlaylist(pl) => {
-                                    let item = RadioItem {
-                                        list: pl,
-                                        url: url.url.clone(),
-                                    };
-                                    self.item = Some(item);
-                                    self.download_and_push();
-                                    self.play();
-                                }
-                            }
-                            return true;
-                        }
-                    }
-                    Err(_err) => {
-                        // ignore
-                    }
-                }
-                false
-            }
-            super::media::Source::Local(_) => false,
-        }
+        self.station = match &media.src {
+            super::media::Source::M3u8(config)
+            | super::media::Source::Dash(config)
+            | super::media::Source::Stream(config) => Some(config.clone()),
+            super::media::Source::Local(_) => None,
+        };
+        self.attempt = 0;
+        self.reconnects = Reconnects::default();
+        self.reconnect_at = None;
+        self.was_connected = false;
+        self.error = None;
+        self.refreshed = None;
+        self.bitrate = None;
+        self.tune(media.src)
     }
 
     fn play(&mut self) -> bool {
@@ -488,6 +785,10 @@ impl Player for RadioPlayer {
     fn pause(&mut self) -> bool {
         self.sink.pause();
         self.is_playing = false;
+        // live radio keeps downloading into the timeshift buffer
+        if self.is_live() {
+            self.shifted = true;
+        }
         true
     }
 
@@ -497,8 +798,18 @@ impl Player for RadioPlayer {
         true
     }
 
+    /// (played, total) seconds of a VOD playlist, zeros for live radio.
     fn get_progress(&self) -> (f32, f32) {
-        return (0.0, 0.0);
+        match &self.item {
+            Some(radio) if radio.list.end_list => {
+                let current = self.played + self.sink.get_pos();
+                (
+                    current.as_secs_f32(),
+                    playlist_duration(&radio.list).as_secs_f32(),
+                )
+            }
+            _ => (0.0, 0.0),
+        }
     }
 
     fn is_playing(&self) -> bool {
@@ -506,39 +817,54 @@ impl Player for RadioPlayer {
     }
 
     fn tick(&mut self) {
-        match self.data_rx.try_recv() {
-            Ok(data) => {
-                // let f = File::open("D:\\audio.wav").unwrap();
-                let mut cache_dir = dirs::cache_dir().unwrap();
-                cache_dir.push("RustPlayer");
-                std::fs::create_dir_all(cache_dir.clone()).unwrap();
-                let timestamp = SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap();
-                let fname = timestamp.as_nanos().to_string();
-                cache_dir.push(fname.as_str());
-                let mut f = File::create(cache_dir.clone()).unwrap();
-                f.write_all(data.as_ref()).unwrap();
-                let decoder = ffmpeg_decoder::Decoder::open(cache_dir);
-                // let buffer = BufReader::new(f);
-                // let decoder = rodio::Decoder::new_mp4(buffer, rodio::decoder::Mp4Type::M4a);
-                // Decoder::
-                match decoder {
-                    Ok(dec) => {
-                        self.sink.append(dec);
-                    }
-                    Err(err) => {
-                        eprintln!("{:?}", err);
-                    }
+        if self.reconnect_at.is_some_and(|at| Instant::now() >= at) {
+            self.reconnect();
+        }
+        self.receive_tuning();
+        self.receive_stream();
+        let live = self.is_live();
+        while let Some(segment) = self.downloader.as_ref().and_then(|d| d.try_recv()) {
+            if segment.session != self.session {
+                continue;
+            }
+            let duration = segment.info.duration;
+            if !duration.is_zero() {
+                let bits = segment.data.len() as u64 * 8;
+                self.bitrate = Some(bits / duration.as_millis().max(1) as u64);
+            }
+            self.connected();
+            self.timeshift.push(duration, segment);
+            // live segments wait in the timeshift buffer, not in the download
+            // budget, so downloading goes on while paused
+            if live {
+                if let Some(downloader) = &self.downloader {
+                    downloader.played(duration);
                 }
             }
-            Err(_) => {}
         }
-        // check length
+        // segments finished playing
+        while self.queued.len() > self.sink.len() {
+            if let Some(queued) = self.queued.pop_front() {
+                self.played += queued.duration;
+                self.skipped = Duration::ZERO;
+                if let (false, Some(downloader)) = (live, &self.downloader) {
+                    downloader.played(queued.duration);
+                }
+            }
+        }
+        self.feed();
+        self.update_now_playing();
+        let failures = self.downloader.as_ref().map_or(0, |d| d.failures());
+        if failures >= SEGMENT_FAILURES && self.reconnect_at.is_none() {
+            self.connection_lost(format!("{} segments failed", failures));
+        }
+        self.receive_reload();
+        // VOD playlists are complete, live ones are reloaded on schedule,
+        // reconnecting takes over after a failure
         if let Ok(elapsed) = self.elasped.elapsed() {
-            // 过了gap时间重新拉取m3u8
-            if self.is_playing() && elapsed.as_secs() > self.gap.as_secs() {
-                self.download_and_push();
+            let idle = self.reloading.is_none() && self.reconnect_at.is_none();
+            if live && idle && elapsed >= self.reload_delay() {
+                self.reload_playlist();
             }
         }
     }
@@ -551,84 +877,620 @@ impl Player for RadioPlayer {
         false
     }
 
+    /// Song and programme of the HLS station from timed ID3, or the ICY
+    /// stream title.
+    fn now_playing(&self) -> Option<NowPlaying> {
+        if let Some(icy) = &self.icy {
+            return Some(NowPlaying {
+                song: icy.title.clone(),
+                ..NowPlaying::default()
+            });
+        }
+        self.item.as_ref()?;
+        Some(self.now_playing.clone())
+    }
+
     fn volume(&self) -> f32 {
-        self.sink.volume()
+        self.volume
+    }
+
+    fn duck(&mut self, level: Option<f32>) {
+        if self.ducked != level {
+            self.ducked = level;
+            self.set_volume(self.volume);
+        }
     }
 
     fn set_volume(&mut self, new_volume: f32) -> bool {
-        self.sink.set_volume(new_volume);
+        self.volume = new_volume;
+        self.sink
+            .set_volume(new_volume * self.ducked.unwrap_or(1.0));
         true
     }
 }
 
 impl RadioPlayer {
-    /// 触发下载
-    fn download_and_push(&mut self) {
-        self.elasped = SystemTime::now();
-        // 第一次下载，直接全部下载
-        if self.last_playing_id == -1 {
-            // 直接全部下载
-            let item = &self.item;
-            if let Some(radio) = item {
-                let index = radio.url.clone().rfind("/").unwrap();
-                let base_url = radio.url.as_str()[0..index + 1].to_string();
-                let tx_clone = self.data_tx.clone();
-                let urls: Vec<String> = radio
-                    .list
-                    .segments
-                    .iter()
-                    .map(|e| e.uri.clone())
-                    .map(|uri| base_url.clone() + &uri)
-                    .collect();
-                self.last_playing_id =
-                    radio.list.media_sequence + max((radio.list.segments.len() - 1) as i32, 0);
+    fn tune(&mut self, src: super::media::Source) -> bool {
+        self.cursor = None;
+        self.reload = ReloadScheduler::new();
+        self.reloading = None;
+        self.reload_failed = false;
+        self.queued.clear();
+        self.played = Duration::ZERO;
+        self.timeshift = Timeshift::new(self.timeshift_capacity);
+        self.skip = None;
+        self.skipped = Duration::ZERO;
+        self.shifted = false;
+        self.now_playing = NowPlaying::default();
+        // drop everything of the previous station, its downloader stops and
+        // whatever it still delivers carries an old session
+        self.session += 1;
+        self.tuning = None;
+        self.item = None;
+        self.icy = None;
+        self.downloader = None;
+        self.sink.clear();
+        let dash = matches!(src, super::media::Source::Dash(_));
+        match src {
+            super::media::Source::M3u8(url) | super::media::Source::Dash(url) => {
+                let (tx, rx) = channel();
+                let m3u8_url = url.url.clone();
+                let policy = url.variant.unwrap_or(self.variant_policy);
+                let query = self.query_propagation;
+                let headers = url.request_headers();
+                // a master playlist takes two round trips, `tick` finishes tuning
                 thread::spawn(move || {
-                    for url in urls {
-                        match download_as_bytes(url.as_str(), &tx_clone) {
-                            _ => {
-                                // println!("{:?} downloaded",url);
-                            }
-                        }
-                    }
+                    let playlist = resolve_playlist(
+                        m3u8_url.as_str(),
+                        dash,
+                        policy,
+                        query,
+                        &headers,
+                        None,
+                        TimelineNumbers::default(),
+                    );
+                    let _ = tx.send(playlist);
+                });
+                self.tuning = Some(Tuning {
+                    session: self.session,
+                    name: url.name.clone(),
+                    dash,
+                    result: rx,
+                });
+                true
+            }
+            super::media::Source::Stream(config) => {
+                let headers = config.request_headers();
+                let icy = StreamSession::start(self.session, &config.name, &config.url, headers);
+                self.icy = Some(icy);
+                true
+            }
+            super::media::Source::Local(_) => false,
+        }
+    }
+
+    /// Tune the next url of the station after `err`, false when there is
+    /// none left.
+    fn try_fallback(&mut self, err: &str) -> bool {
+        let station = match &self.station {
+            Some(station) => station,
+            None => return false,
+        };
+        let url = match station.urls().get(self.attempt + 1) {
+            Some(url) => url.clone(),
+            None => return false,
+        };
+        self.attempt += 1;
+        self.message = Some(format!("{}: {}, trying {}", station.name, err, url));
+        let config = RadioConfig {
+            url,
+            fallbacks: vec![],
+            ..station.clone()
+        };
+        self.tune(super::media::Source::radio(config))
+    }
+
+    fn receive_tuning(&mut self) {
+        let resolved = match self.tuning.as_ref().map(|tuning| tuning.result.try_recv()) {
+            None | Some(Err(TryRecvError::Empty)) => return,
+            Some(resolved) => resolved,
+        };
+        let tuning = match self.tuning.take() {
+            Some(tuning) if tuning.session == self.session => tuning,
+            _ => return,
+        };
+        match resolved {
+            Ok(Ok(resolved)) => {
+                self.item = Some(RadioItem {
+                    name: tuning.name,
+                    list: resolved.list,
+                    low_latency: resolved.low_latency,
+                    url: resolved.url,
+                    base_url: resolved.base_url,
+                    master_url: resolved.master_url,
+                    variants: resolved.variants,
+                    variant: resolved.variant,
+                    dash: tuning.dash,
+                    numbers: resolved.numbers,
                 });
+                self.downloader = Some(SegmentDownloader::new(self.session, self.headers()));
+                self.refreshed = Some(Instant::now());
+                self.download_and_push();
+                self.play();
+            }
+            Ok(Err(err)) => {
+                if !self.try_fallback(err.to_string().as_str()) {
+                    self.tuning_failed(tuning.name.as_str(), err.to_string());
+                }
+            }
+            Err(_) => {
+                if !self.try_fallback("tuning stopped") {
+                    self.tuning_failed(tuning.name.as_str(), "tuning stopped".to_string());
+                }
+            }
+        }
+    }
+
+    /// Move decoded audio of a progressive station into the sink, keeping
+    /// only a couple of seconds ahead so pausing stops the download.
+    fn receive_stream(&mut self) {
+        while self.sink.len() < STREAM_AHEAD_CHUNKS {
+            let icy = match &mut self.icy {
+                Some(icy) if icy.session == self.session => icy,
+                _ => return,
+            };
+            match icy.try_recv() {
+                Some(StreamEvent::Audio(audio)) => {
+                    self.sink.append(audio);
+                    if !icy.connected {
+                        icy.connected = true;
+                        self.sink.play();
+                        self.is_playing = true;
+                    }
+                    self.connected();
+                }
+                Some(StreamEvent::Bitrate(bitrate)) => {
+                    self.bitrate = Some(bitrate);
+                }
+                Some(StreamEvent::Title(title)) => {
+                    history::record(icy.name.as_str(), title.as_str());
+                    icy.title = Some(title);
+                }
+                Some(StreamEvent::Playlist(url, kind)) => {
+                    let config = RadioConfig {
+                        name: icy.name.clone(),
+                        url,
+                        fallbacks: vec![],
+                        ..self.station.clone().unwrap_or_default()
+                    };
+                    self.tune(super::media::Source::of_kind(kind, config));
+                }
+                Some(StreamEvent::Failed(err)) => {
+                    let name = icy.name.clone();
+                    self.icy = None;
+                    if !self.try_fallback(err.as_str()) {
+                        self.tuning_failed(name.as_str(), err);
+                    }
+                }
+                None => return,
             }
+        }
+    }
+
+    /// Audio arrived, the connection is fine again.
+    fn connected(&mut self) {
+        if self.reconnects.connected() {
+            self.message = Some("Reconnected".to_string());
+        }
+        self.reconnect_at = None;
+        self.was_connected = true;
+        self.error = None;
+    }
+
+    /// Every url of the station failed: reconnect if it played before,
+    /// otherwise it is probably not a working station.
+    fn tuning_failed(&mut self, name: &str, err: String) {
+        if self.was_connected {
+            self.connection_lost(err);
         } else {
-            // 更新playlist列表
-            if let Some(radio) = &self.item {
-                let index = radio.url.clone().rfind("/").unwrap();
-                let base_url = radio.url.as_str()[0..index + 1].to_string();
-                match download_m3u8_playlist(radio.url.clone()) {
-                    Ok(playlist) => match playlist {
-                        Playlist::MasterPlaylist(_) => todo!(),
-                        Playlist::MediaPlaylist(media_playlist) => {
-                            let seq = media_playlist.media_sequence;
-                            let skip_num = max(self.last_playing_id - seq + 1, 0) as usize;
-                            let segs = &media_playlist.segments[skip_num..];
-                            // println!("add {:?}",segs);
-                            let urls: Vec<String> = segs
-                                .iter()
-                                .map(|e| e.uri.clone())
-                                .map(|uri| base_url.clone() + &uri)
-                                .collect();
-                            self.last_playing_id =
-                                seq + max((media_playlist.segments.len() - 1) as i32, 0);
-                            let tx_clone = self.data_tx.clone();
-                            thread::spawn(move || {
-                                for url in urls {
-                                    match download_as_bytes(url.as_str(), &tx_clone) {
-                                        _ => {
-                                            // println!("update {:?}",url);
-                                        }
-                                    }
-                                }
-                            });
-                        }
-                    },
-                    Err(_) => {
-                        // ignore
+            self.message = Some(format!("Failed to tune {}: {}", name, err));
+            self.error = Some(err);
+        }
+    }
+
+    /// Schedule a reconnect with exponential back-off, giving up after
+    /// `MAX_RECONNECTS` in a row.
+    fn connection_lost(&mut self, err: String) {
+        let (attempt, delay) = match self.reconnects.lost() {
+            Some(next) => next,
+            None => {
+                self.gave_up(err);
+                return;
+            }
+        };
+        self.reconnect_at = Some(Instant::now() + delay);
+        self.message = Some(format!("Reconnecting (attempt {})", attempt));
+        self.error = Some(err);
+    }
+
+    fn gave_up(&mut self, err: String) {
+        let name = self.station.as_ref().map(|s| s.name.as_str());
+        self.message = Some(format!(
+            "Disconnected from {}: {}",
+            name.unwrap_or_default(),
+            err
+        ));
+        self.error = Some(err);
+        self.reconnect_at = None;
+        self.is_playing = false;
+    }
+
+    fn reconnect(&mut self) {
+        self.reconnect_at = None;
+        if self.item.is_some() {
+            // a fresh downloader continues from the cursor
+            let failures = self.downloader.as_ref().map_or(0, |d| d.failures());
+            if failures >= SEGMENT_FAILURES {
+                self.downloader = Some(SegmentDownloader::new(self.session, self.headers()));
+            }
+            self.reload_playlist();
+            return;
+        }
+        // tuning or the progressive stream failed, start over with the
+        // first url of the station
+        let station = match &self.station {
+            Some(station) => station.clone(),
+            None => return,
+        };
+        self.attempt = 0;
+        self.tune(super::media::Source::radio(station));
+    }
+
+    /// Buffer, bitrate and errors of the station, None when nothing is
+    /// tuned.
+    pub fn health(&self) -> Option<StreamHealth> {
+        let tuned = self.item.is_some() || self.icy.is_some() || self.tuning.is_some();
+        if !tuned && self.error.is_none() {
+            return None;
+        }
+        let buffered = match &self.icy {
+            Some(_) => CHUNK_DURATION * self.sink.len() as u32,
+            None => self.timeshift.behind(self.timeshift_position()),
+        };
+        Some(StreamHealth {
+            buffered,
+            bitrate: self.bitrate,
+            refreshed: self.refreshed.map(|at| at.elapsed()),
+            error: self.error.clone(),
+            reconnecting: self.reconnect_at.map(|_| self.reconnects.attempts()),
+        })
+    }
+
+    // request headers of the tuned station
+    fn headers(&self) -> Vec<(String, String)> {
+        self.station
+            .as_ref()
+            .map(|station| station.request_headers())
+            .unwrap_or_default()
+    }
+
+    // variant policy of the tuned station, or the one of `config.ini`
+    fn variant(&self) -> VariantPolicy {
+        self.station
+            .as_ref()
+            .and_then(|station| station.variant)
+            .unwrap_or(self.variant_policy)
+    }
+
+    /// Status message for the app to show, once.
+    pub fn take_message(&mut self) -> Option<String> {
+        self.message.take()
+    }
+
+    /// Fetch the playlist again in the background, `tick` picks it up.
+    /// Replaces a reload still running, e.g. for the previous variant.
+    fn reload_playlist(&mut self) {
+        let radio = match &self.item {
+            Some(radio) => radio,
+            None => return,
+        };
+        let (url, timeout) = match self.cursor {
+            Some(cursor) if self.blocking_reload() => {
+                let target = Duration::from_secs_f32(radio.list.target_duration.max(0.0));
+                (
+                    blocking_reload_url(radio.url.as_str(), cursor),
+                    Some(radio.low_latency.blocking_timeout(target)),
+                )
+            }
+            _ => (radio.url.clone(), None),
+        };
+        let (tx, rx) = channel();
+        let dash = radio.dash;
+        let policy = self.variant();
+        let query = self.query_propagation;
+        let headers = self.headers();
+        let numbers = radio.numbers.clone();
+        thread::spawn(move || {
+            let _ = tx.send(resolve_playlist(
+                url.as_str(),
+                dash,
+                policy,
+                query,
+                &headers,
+                timeout,
+                numbers,
+            ));
+        });
+        self.reloading = Some(rx);
+    }
+
+    fn receive_reload(&mut self) {
+        let resolved = match self.reloading.as_ref().map(|rx| rx.try_recv()) {
+            None | Some(Err(TryRecvError::Empty)) => return,
+            Some(resolved) => resolved,
+        };
+        self.reloading = None;
+        self.elasped = SystemTime::now();
+        let radio = match &mut self.item {
+            Some(radio) => radio,
+            None => return,
+        };
+        match resolved {
+            Ok(Ok(resolved)) => {
+                self.refreshed = Some(Instant::now());
+                // the media playlist url answered with a master playlist
+                if resolved.variant.is_some() {
+                    radio.master_url = resolved.master_url;
+                    radio.variants = resolved.variants;
+                    radio.variant = resolved.variant;
+                    radio.url = resolved.url;
+                }
+                radio.base_url = resolved.base_url;
+                radio.list = resolved.list;
+                radio.low_latency = resolved.low_latency;
+                radio.numbers = resolved.numbers;
+                self.reload_failed = false;
+                self.download_and_push();
+            }
+            Ok(Err(err)) => {
+                self.reload_failed = true;
+                self.connection_lost(err.to_string());
+            }
+            Err(_) => {
+                self.reload_failed = true;
+                self.connection_lost("playlist reload stopped".to_string());
+            }
+        }
+    }
+
+    fn low_latency(&self) -> bool {
+        self.latency_mode == LatencyMode::Low
+            && self
+                .item
+                .as_ref()
+                .is_some_and(|radio| radio.low_latency.has_parts())
+    }
+
+    // the server holds the request until the next part exists
+    fn blocking_reload(&self) -> bool {
+        self.low_latency()
+            && !self.reload_failed
+            && self
+                .item
+                .as_ref()
+                .is_some_and(|radio| radio.low_latency.can_block_reload)
+    }
+
+    fn reload_delay(&self) -> Duration {
+        if self.blocking_reload() {
+            return Duration::ZERO;
+        }
+        match &self.item {
+            Some(radio) if self.low_latency() => radio
+                .low_latency
+                .part_target
+                .unwrap_or_else(|| self.reload.delay()),
+            _ => self.reload.delay(),
+        }
+    }
+
+    /// 触发下载
+    fn download_and_push(&mut self) {
+        self.elasped = SystemTime::now();
+        let radio = match &self.item {
+            Some(radio) => radio,
+            None => return,
+        };
+        self.reload.loaded(&radio.list);
+        let resolver = UriResolver::new(radio.base_url.as_str(), self.query_propagation);
+        let segments = match self.latency_mode {
+            LatencyMode::Low => {
+                low_latency_segments(&radio.list, &radio.low_latency, &resolver, self.cursor)
+            }
+            LatencyMode::Stable => new_segments(
+                &radio.list,
+                &resolver,
+                self.cursor.map(|(sequence, _)| sequence),
+            ),
+        };
+        match segments.last() {
+            Some(last) => {
+                self.cursor = Some(next_cursor(&radio.list, &radio.low_latency, last));
+            }
+            None if self.cursor.is_none() => {
+                let first = radio.list.media_sequence.max(0) as u64;
+                self.cursor = Some((first + radio.list.segments.len() as u64, 0));
+            }
+            None => {}
+        }
+        let first = radio.list.media_sequence.max(0) as u64;
+        let window = first..first + radio.list.segments.len() as u64;
+        if let Some(downloader) = &self.downloader {
+            downloader.push(segments, window);
+        }
+    }
+
+    /// Switch to the next variant of the master playlist while playing,
+    /// segments continue from the current media sequence number once its
+    /// playlist is loaded.
+    pub fn switch_variant(&mut self) -> Option<String> {
+        let radio = self.item.as_mut()?;
+        let master_url = radio.master_url.clone()?;
+        let next = next_variant(&radio.variants, radio.variant?)?;
+        radio.variant = Some(next);
+        let resolver = UriResolver::new(master_url.as_str(), self.query_propagation);
+        radio.url = resolver.resolve(radio.variants[next].uri.as_str());
+        let label = variant_label(&radio.variants[next]);
+        self.reload_playlist();
+        Some(label)
+    }
+
+    fn is_live(&self) -> bool {
+        self.item.as_ref().is_some_and(|radio| !radio.list.end_list)
+    }
+
+    /// Keep a couple of segments of the timeshift buffer in the sink, so
+    /// seeking only has to drop those.
+    fn feed(&mut self) {
+        while self.sink.len() < FEED_AHEAD_SEGMENTS {
+            let (duration, segment) = match self.timeshift.next_segment() {
+                Some(segment) => segment,
+                None => return,
+            };
+            // read now, applied once playback gets there
+            let id3 = segment_id3(&segment.data);
+            // every segment gets a fresh decoder reading it from memory, so a
+            // discontinuity (new encoder settings or timestamps) needs no
+            // reset here, and fMP4 fragments carry their init section in front
+            match ffmpeg_decoder::Decoder::from_bytes(segment.data) {
+                Ok(dec) => {
+                    match self.skip.take() {
+                        Some(skip) => self.sink.append(dec.skip_duration(skip)),
+                        None => self.sink.append(dec),
+                    }
+                    self.queued.push_back(QueuedSegment {
+                        duration,
+                        date_time: segment.info.date_time,
+                        id3,
+                    });
+                }
+                Err(err) => {
+                    eprintln!("{:?}", err);
+                    if let (false, Some(downloader)) = (self.is_live(), &self.downloader) {
+                        downloader.played(duration);
                     }
                 }
             }
         }
     }
+
+    /// Apply the timed metadata of the playing segment that playback has
+    /// reached.
+    fn update_now_playing(&mut self) {
+        let elapsed = self.skipped + self.sink.get_pos();
+        let playing = match self.queued.front_mut() {
+            Some(playing) => playing,
+            None => return,
+        };
+        while playing.id3.first().is_some_and(|id3| id3.offset <= elapsed) {
+            let id3 = playing.id3.remove(0);
+            self.now_playing.apply(id3.tag);
+        }
+        self.now_playing.wall_clock = playing
+            .date_time
+            .map(|start| UNIX_EPOCH + Duration::from_millis(start.max(0) as u64) + elapsed);
+    }
+
+    /// Playback position in the timeshift buffer.
+    fn timeshift_position(&self) -> Duration {
+        let elapsed = self.skipped + self.sink.get_pos();
+        self.timeshift.position(self.queued.len(), elapsed)
+    }
+
+    fn seek(&mut self, to: Option<Duration>) {
+        self.sink.clear();
+        self.queued.clear();
+        match to {
+            Some(to) => self.skipped = self.timeshift.seek(to),
+            None => {
+                self.timeshift.seek_live();
+                self.skipped = Duration::ZERO;
+            }
+        }
+        self.skip = Some(self.skipped).filter(|skip| !skip.is_zero());
+        self.feed();
+        if self.is_playing {
+            self.sink.play();
+        }
+    }
+
+    /// Move live playback `by` back or forward within the timeshift buffer,
+    /// returns how far it is behind live then.
+    pub fn shift(&mut self, back: bool, by: Duration) -> Option<Duration> {
+        if !self.is_live() {
+            return None;
+        }
+        let position = self.timeshift_position();
+        let to = if back {
+            position.saturating_sub(by)
+        } else {
+            position + by
+        };
+        if self.timeshift.behind(to) < LIVE_TOLERANCE {
+            self.go_live();
+            return Some(Duration::ZERO);
+        }
+        self.seek(Some(to));
+        self.shifted = true;
+        Some(self.timeshift.behind(to))
+    }
+
+    /// Jump back to the newest buffered segment.
+    pub fn go_live(&mut self) -> bool {
+        if !self.is_live() {
+            return false;
+        }
+        self.seek(None);
+        self.shifted = false;
+        true
+    }
+
+    /// How far playback is behind live, after pausing or moving back.
+    pub fn behind_live(&self) -> Option<Duration> {
+        if !self.shifted || !self.is_live() {
+            return None;
+        }
+        Some(self.timeshift.behind(self.timeshift_position()))
+    }
+
+    /// Station name, with the variant in use for master playlists.
+    pub fn playing_station(&self) -> Option<String> {
+        if let Some(tuning) = &self.tuning {
+            return Some(format!("Connecting… {}", tuning.name));
+        }
+        if let Some(icy) = &self.icy {
+            return Some(match (&icy.title, icy.connected) {
+                (_, false) => format!("Connecting… {}", icy.name),
+                (Some(title), true) => format!("{} - {}", icy.name, title),
+                (None, true) => icy.name.clone(),
+            });
+        }
+        let radio = self.item.as_ref()?;
+        let name = match radio.variant {
+            Some(variant) => format!(
+                "{} ({})",
+                radio.name,
+                variant_label(&radio.variants[variant])
+            ),
+            None => radio.name.clone(),
+        };
+        match self.behind_live() {
+            Some(behind) => Some(format!(
+                "{} - behind live by {}",
+                name,
+                format_clock(behind)
+            )),
+            None => Some(name),
+        }
+    }
 }
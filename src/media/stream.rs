@@ -0,0 +1,156 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, SyncSender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use rodio::{buffer::SamplesBuffer, Source};
+
+use crate::util::{
+    icy::{IcyReader, ICY_METADATA, ICY_METAINT},
+    net::BodyReader,
+    playlist::{detect_kind, StreamKind},
+};
+
+// decoded audio is handed over in half second chunks
+const CHUNKS_PER_SECOND: usize = 2;
+pub const CHUNK_DURATION: Duration = Duration::from_millis(1000 / CHUNKS_PER_SECOND as u64);
+// kbps announced by Icecast/Shoutcast
+const ICY_BITRATE: &str = "icy-br";
+// chunks waiting in the channel, the stream is not read further ahead
+const CHANNEL_CHUNKS: usize = 8;
+
+pub enum StreamEvent {
+    Audio(SamplesBuffer),
+    // ICY StreamTitle, sent in order with the audio it belongs to
+    Title(String),
+    // kbps from the response headers
+    Bitrate(u64),
+    // the url serves an HLS playlist or DASH manifest, not audio
+    Playlist(String, StreamKind),
+    Failed(String),
+}
+
+/// A progressive HTTP (Icecast/Shoutcast) station decoded on a thread of its
+/// own. Dropping it stops the thread.
+pub struct StreamSession {
+    pub session: u64,
+    pub name: String,
+    pub title: Option<String>,
+    pub connected: bool,
+    events: Receiver<StreamEvent>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl StreamSession {
+    /// Connect to `url` sending the station's `headers` as well.
+    pub fn start(session: u64, name: &str, url: &str, headers: Vec<(String, String)>) -> Self {
+        let (tx, rx) = sync_channel(CHANNEL_CHUNKS);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let url = url.to_string();
+        thread::spawn(move || {
+            if let Err(err) = decode_stream(url.as_str(), &headers, &tx, &flag) {
+                let _ = tx.send(StreamEvent::Failed(err.to_string()));
+            }
+        });
+        Self {
+            session,
+            name: name.to_string(),
+            title: None,
+            connected: false,
+            events: rx,
+            cancelled,
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<StreamEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for StreamSession {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+fn decode_stream(
+    url: &str,
+    headers: &[(String, String)],
+    events: &SyncSender<StreamEvent>,
+    cancelled: &AtomicBool,
+) -> Result<(), failure::Error> {
+    let mut request: Vec<(&str, &str)> = vec![(ICY_METADATA, "1")];
+    request.extend(headers.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    let body = BodyReader::open(url, &request)?;
+    let content_type = body.header("content-type");
+    let kind = detect_kind(url, content_type.as_deref());
+    if kind != StreamKind::Progressive {
+        let _ = events.send(StreamEvent::Playlist(url.to_string(), kind));
+        return Ok(());
+    }
+    // some servers list several values, e.g. `128,128`
+    let bitrate = body
+        .header(ICY_BITRATE)
+        .and_then(|br| br.split(',').next().and_then(|br| br.trim().parse().ok()));
+    if let Some(bitrate) = bitrate {
+        let _ = events.send(StreamEvent::Bitrate(bitrate));
+    }
+    let metaint = body
+        .header(ICY_METAINT)
+        .and_then(|metaint| metaint.parse().ok());
+    let (titles_tx, titles) = channel();
+    let reader = IcyReader::new(body, metaint, titles_tx);
+    let decoder = ffmpeg_decoder::Decoder::from_stream(reader)
+        .map_err(|err| failure::format_err!("{}", err))?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    if channels == 0 || sample_rate == 0 {
+        return Err(failure::format_err!("Unsupported stream format"));
+    }
+    let chunk_len = sample_rate as usize * channels as usize / CHUNKS_PER_SECOND;
+    let mut samples = Vec::with_capacity(chunk_len);
+    for sample in decoder {
+        samples.push(sample);
+        if samples.len() < chunk_len {
+            continue;
+        }
+        if cancelled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut samples, Vec::with_capacity(chunk_len));
+        let audio = SamplesBuffer::new(channels, sample_rate, chunk);
+        let sent = titles
+            .try_iter()
+            .map(StreamEvent::Title)
+            .chain(std::iter::once(StreamEvent::Audio(audio)))
+            .all(|event| events.send(event).is_ok());
+        // the station was switched
+        if !sent {
+            return Ok(());
+        }
+    }
+    Err(failure::format_err!("Stream ended"))
+}
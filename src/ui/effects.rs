@@ -25,11 +25,16 @@ use tui::{
     backend::Backend,
     layout::{Alignment, Rect},
     style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
     widgets::{BarChart, Block, BorderType, Borders, List, ListItem},
     Frame,
 };
 
-use crate::{app::App, media::player::Player};
+use crate::{
+    app::App,
+    media::player::Player,
+    util::lyrics::{Lyric, LyricsMode},
+};
 use lazy_static::lazy_static;
 
 pub struct WaveEffectCache {
@@ -48,28 +53,47 @@ where
 {
     let player = &mut app.player;
     let radio = &app.radio;
+    let mode = app.music_controller.lyrics_mode;
+    let current_time = player.current_time;
     match player.has_lyrics() {
         true => {
             let mut lyrics = vec![];
             if let Some(item) = player.play_list.lists.first_mut() {
                 for ele in &item.lyrics.list {
-                    lyrics.push(ListItem::new(ele.content.as_str()));
+                    lyrics.push(lyric_item(ele, mode));
                 }
-                let list = List::new(lyrics)
-                    .highlight_symbol("*")
-                    .highlight_style(
+                let title = if item.lyrics.synced {
+                    format!("Lyrics ({})", mode.name())
+                } else {
+                    format!("Lyrics ({}, unsynced)", mode.name())
+                };
+                let list = List::new(lyrics).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .title(title)
+                        .title_alignment(Alignment::Center),
+                );
+                if item.lyrics.synced {
+                    let active = item
+                        .lyrics_index
+                        .selected()
+                        .and_then(|index| item.lyrics.list.get(index))
+                        .is_some_and(|lyric| lyric.is_active(current_time));
+                    // keep the position but drop the highlight between cues
+                    let style = if active {
                         Style::default()
                             .add_modifier(Modifier::BOLD)
-                            .bg(Color::Cyan),
-                    )
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .border_type(BorderType::Rounded)
-                            .title("Lyrics")
-                            .title_alignment(Alignment::Center),
-                    );
-                frame.render_stateful_widget(list, area, &mut item.lyrics_index);
+                            .bg(Color::Cyan)
+                    } else {
+                        Style::default()
+                    };
+                    let list = list.highlight_symbol("*").highlight_style(style);
+                    frame.render_stateful_widget(list, area, &mut item.lyrics_index);
+                } else {
+                    // no position to follow, show the text from the top
+                    frame.render_widget(list, area);
+                }
             }
         }
         false => {
@@ -126,3 +150,21 @@ where
         }
     }
 }
+
+fn lyric_item(lyric: &Lyric, mode: LyricsMode) -> ListItem<'_> {
+    let mut lines = vec![];
+    let (original, translation) = lyric.lines(mode);
+    if let Some(original) = original {
+        lines.push(Spans::from(original));
+    }
+    if let Some(translation) = translation {
+        // translation beneath the original is dimmed
+        let style = if original.is_some() {
+            Style::default().fg(Color::Gray).add_modifier(Modifier::DIM)
+        } else {
+            Style::default()
+        };
+        lines.push(Spans::from(Span::styled(translation, style)));
+    }
+    ListItem::new(Text::from(lines))
+}
@@ -62,7 +62,7 @@ impl FsExplorer {
             dirs: vec![],
             index: list_state,
             on_error_msg_callback: callback,
-            accept_suffix: vec!["mp3", "wav", "flac", "ts"],
+            accept_suffix: vec!["mp3", "wav", "flac", "ts", "pls", "m3u", "xspf"],
         };
         let (dirs, files) = exp.visit_dir(path_str)?;
         exp.files = files;
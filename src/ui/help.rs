@@ -49,7 +49,20 @@ where
             "Tab",
             "switch highlight block. (Audio Explorer/Radio Explorer)",
         ]),
-        Row::new(["r", "open radio config list."]),
+        Row::new(["r", "go to the radio list to edit stations."]),
+        Row::new([
+            "a/e/d",
+            "add, edit or delete (press twice) a radio station.",
+        ]),
+        Row::new(["K/J", "move the selected radio station up/down."]),
+        Row::new([
+            "b",
+            "browse the station catalogue, Enter previews and a adds a station.",
+        ]),
+        Row::new([
+            "p",
+            "podcasts: a subscribes to a feed or OPML, Enter plays, w downloads, m marks played.",
+        ]),
         Row::new(["->", "add audio to play list."]),
         Row::new([
             "Enter",
@@ -58,6 +71,14 @@ where
         Row::new(["-/+", "decrease/increase volume."]),
         Row::new(["s", "pause/resume audio playback."]),
         Row::new(["n", "play the next audio."]),
+        Row::new(["v", "switch radio stream quality (master playlists)."]),
+        Row::new(["←/→", "skip live radio back/forward by 30s."]),
+        Row::new(["g", "go back to the live edge of the radio."]),
+        Row::new(["l", "show original/translated/both lyrics."]),
+        Row::new([
+            "e",
+            "sync lyrics of the playing audio (tap-to-sync editor).",
+        ]),
         Row::new(["q", "quit RustPlayer."]),
         Row::new(["↑/↓", "change selected index."]),
     ])
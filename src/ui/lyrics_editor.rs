@@ -0,0 +1,201 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{path::PathBuf, time::Duration};
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    util::lyrics::{format_lrc_time, Lyric, Lyrics},
+};
+
+// step used by nudging a stamped line
+pub const NUDGE_STEP: Duration = Duration::from_millis(100);
+
+/// Tap-to-sync editor: plain lines get stamped with the playback position
+/// one by one, then saved as `.lrc` beside the audio file.
+pub struct LyricsEditor {
+    pub music_path: PathBuf,
+    pub lines: Vec<String>,
+    pub stamps: Vec<Option<Duration>>,
+    // indexes in stamping order, for undo
+    pub history: Vec<usize>,
+    // next line to stamp
+    pub cursor: usize,
+    pub index: ListState,
+}
+
+impl LyricsEditor {
+    pub fn new(music_path: PathBuf, lines: Vec<String>) -> Self {
+        let mut index = ListState::default();
+        index.select(Some(0));
+        Self {
+            music_path,
+            stamps: vec![None; lines.len()],
+            lines,
+            history: vec![],
+            cursor: 0,
+            index,
+        }
+    }
+
+    /// Stamp the next line, returns false when every line is stamped.
+    pub fn stamp(&mut self, at: Duration) -> bool {
+        if self.cursor >= self.lines.len() {
+            return false;
+        }
+        self.stamps[self.cursor] = Some(at);
+        self.history.push(self.cursor);
+        self.index.select(Some(self.cursor));
+        self.cursor += 1;
+        true
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(line) => {
+                self.stamps[line] = None;
+                self.cursor = line;
+                self.index.select(Some(line));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the selected line's stamp earlier or later.
+    pub fn nudge(&mut self, later: bool) -> bool {
+        let selected = match self.index.selected() {
+            Some(selected) => selected,
+            None => return false,
+        };
+        match self.stamps.get_mut(selected) {
+            Some(Some(stamp)) => {
+                *stamp = if later {
+                    stamp.saturating_add(NUDGE_STEP)
+                } else {
+                    stamp.saturating_sub(NUDGE_STEP)
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn stamped(&self) -> usize {
+        self.stamps.iter().filter(|stamp| stamp.is_some()).count()
+    }
+
+    /// Stamped lines in playback order, unstamped ones are left out.
+    pub fn to_lyrics(&self) -> Lyrics {
+        let mut list: Vec<Lyric> = self
+            .lines
+            .iter()
+            .zip(self.stamps.iter())
+            .filter_map(|(line, stamp)| {
+                stamp.map(|time| Lyric {
+                    time,
+                    end: None,
+                    content: line.clone(),
+                    translation: None,
+                })
+            })
+            .collect();
+        list.sort_by_key(|lyric| lyric.time);
+        Lyrics { list, synced: true }
+    }
+}
+
+fn format_stamp(stamp: Option<Duration>) -> String {
+    match stamp {
+        Some(stamp) => format_lrc_time(stamp),
+        None => "[--:--.---]".to_string(),
+    }
+}
+
+pub fn draw_lyrics_editor<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Percentage(100)])
+        .split(area);
+    let editor = match &mut app.lyrics_editor {
+        Some(editor) => editor,
+        None => return,
+    };
+    let tips = Paragraph::new(vec![
+        Spans::from("Space: stamp next line  Backspace: undo last stamp  s: pause/resume"),
+        Spans::from("↑/↓: select line  ←/→: nudge selected line  w: save .lrc  Esc: discard"),
+    ])
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(tips, chunks[0]);
+
+    let mut items = vec![];
+    for (i, (line, stamp)) in editor.lines.iter().zip(editor.stamps.iter()).enumerate() {
+        let marker = if i == editor.cursor { "▶ " } else { "  " };
+        let stamp_style = if stamp.is_some() {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        items.push(ListItem::new(Spans::from(vec![
+            Span::raw(marker),
+            Span::styled(format_stamp(*stamp), stamp_style),
+            Span::raw(" "),
+            Span::raw(line.as_str()),
+        ])));
+    }
+    let title = format!(
+        "Lyrics Editor - {} ({}/{})",
+        editor
+            .music_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        editor.stamped(),
+        editor.lines.len()
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::DarkGray),
+        );
+    frame.render_stateful_widget(list, chunks[1], &mut editor.index);
+}
@@ -18,10 +18,15 @@
 pub mod effects;
 pub mod fs;
 pub mod help;
+pub mod lyrics_editor;
 pub mod music_board;
+pub mod now_playing;
 pub mod play_list;
+pub mod podcasts;
 pub mod progress;
 pub mod radio;
+pub mod station_browser;
+pub mod station_editor;
 
 pub enum EventType {
     Player,
@@ -25,12 +25,20 @@ use tui::{
     Frame,
 };
 
-use crate::{app::App, media::player::Player};
+use crate::{
+    app::{ActiveModules, App},
+    media::engine::Focus,
+    util::lyrics::LyricsMode,
+};
 
-use super::{effects::draw_bar_charts_effect, play_list::draw_play_list, progress::draw_progress};
+use super::{
+    effects::draw_bar_charts_effect, now_playing::draw_now_playing, play_list::draw_play_list,
+    progress::draw_progress,
+};
 
 pub struct MusicController {
     pub state: ListState,
+    pub lyrics_mode: LyricsMode,
 }
 
 pub fn draw_music_board<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
@@ -54,7 +62,10 @@ where
         .split(main_layout_chunks[1]);
 
     draw_bar_charts_effect(app, frame, mid_layout_chunks[0]);
-    draw_play_list(app, frame, mid_layout_chunks[1]);
+    match app.active_modules {
+        ActiveModules::RadioList => draw_now_playing(app, frame, mid_layout_chunks[1]),
+        _ => draw_play_list(app, frame, mid_layout_chunks[1]),
+    }
     draw_progress(app, frame, main_layout_chunks[2]);
 }
 
@@ -62,14 +73,20 @@ pub fn draw_header<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
 where
     B: Backend,
 {
-    let player = &app.player;
+    let player = app.focused_player();
     let main_layout_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
         .split(area);
 
     let playing_text;
-    if let Some(item) = player.playing_song() {
+    let station = match app.focus {
+        Focus::Radio => app.radio.playing_station(),
+        Focus::Music => None,
+    };
+    if let Some(station) = station {
+        playing_text = station;
+    } else if let Some(item) = app.player.playing_song() {
         playing_text = String::from(item.name.as_str());
     } else {
         playing_text = String::from("None");
@@ -89,7 +106,7 @@ where
         .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
         .split(main_layout_chunks[0]);
 
-    let sound_volume_percent = app.player.volume();
+    let sound_volume_percent = player.volume();
     let bar = LineGauge::default()
         .ratio(sound_volume_percent.into())
         .label("VOL")
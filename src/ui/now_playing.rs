@@ -0,0 +1,86 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::UNIX_EPOCH;
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{app::App, media::player::Player, util::history::format_utc};
+
+/// Song, programme and broadcast time of the playing radio station,
+/// followed by the health of its stream.
+pub fn draw_now_playing<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let label = Style::default().fg(Color::Cyan);
+    let mut lines = vec![];
+    if let Some(now_playing) = app.radio.now_playing() {
+        let mut rows = vec![
+            ("Song", now_playing.song),
+            ("Programme", now_playing.programme),
+        ];
+        let time = now_playing
+            .wall_clock
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| format!("{} UTC", format_utc(since.as_secs())));
+        rows.push(("On air", time));
+        for (name, value) in rows {
+            if let Some(value) = value {
+                lines.push(Spans::from(Span::styled(name, label)));
+                lines.push(Spans::from(value));
+            }
+        }
+    }
+    if let Some(health) = app.radio.health() {
+        lines.push(Spans::from(Span::styled("Stream", label)));
+        let mut stream = format!("Buffer {}s", health.buffered.as_secs());
+        if let Some(bitrate) = health.bitrate {
+            stream.push_str(&format!(", {} kbps", bitrate));
+        }
+        lines.push(Spans::from(stream));
+        if let Some(refreshed) = health.refreshed {
+            lines.push(Spans::from(format!(
+                "Refreshed {}s ago",
+                refreshed.as_secs()
+            )));
+        }
+        let error = Style::default().fg(Color::Red);
+        if let Some(attempt) = health.reconnecting {
+            let text = format!("Reconnecting (attempt {})", attempt);
+            lines.push(Spans::from(Span::styled(text, error)));
+        }
+        if let Some(err) = health.error {
+            lines.push(Spans::from(Span::styled(err, error)));
+        }
+    }
+    let text = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("On Air")
+            .border_type(BorderType::Rounded)
+            .title_alignment(Alignment::Center),
+    );
+    frame.render_widget(text, area);
+}
@@ -0,0 +1,802 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    media::{
+        episode::{EpisodeDecoder, EpisodeDownload},
+        player::MusicPlayer,
+    },
+    util::{
+        net::fetch_text,
+        podcast::{
+            file_name, format_date, is_opml, parse_feed, parse_opml, resume_offset, safe_file_name,
+            Episode, EpisodeState, Feed, PodcastsFile, Subscription,
+        },
+        timeshift::format_clock,
+    },
+};
+
+// `podcasts.toml` is written at most this often while an episode plays
+const SAVE_EVERY: Duration = Duration::from_secs(15);
+// downloaded beyond the resume position before playback starts
+const START_BYTES: u64 = 256 * 1024;
+
+const HEADER: &str = "\
+# RustPlayer podcasts, rewritten by the podcasts page (press p).
+# [[podcast]] entries are the subscribed feeds, [episode.\"<id>\"] the
+# played state, resume position and download of an episode.
+
+";
+
+/// A subscribed feed, with its episodes once loaded.
+pub struct Podcast {
+    pub subscription: Subscription,
+    pub feed: Option<Feed>,
+    // of the last refresh
+    pub error: Option<String>,
+}
+
+impl Podcast {
+    pub fn title(&self) -> &str {
+        match &self.feed {
+            Some(feed) if !feed.title.is_empty() => feed.title.as_str(),
+            _ if !self.subscription.title.is_empty() => self.subscription.title.as_str(),
+            _ => self.subscription.url.as_str(),
+        }
+    }
+
+    pub fn episodes(&self) -> &[Episode] {
+        self.feed
+            .as_ref()
+            .map_or(&[], |feed| feed.episodes.as_slice())
+    }
+}
+
+enum Loaded {
+    Feed(String, Feed),
+    Opml(Vec<Subscription>),
+}
+
+// waiting for enough of the episode to start playing
+struct Starting {
+    id: String,
+    title: String,
+    duration: Option<Duration>,
+    resume: Duration,
+    // the file being opened and sought to the resume position
+    decoder: Option<(String, EpisodeDecoder)>,
+}
+
+// the episode in the music player
+struct Playing {
+    id: String,
+    path: String,
+    duration: Duration,
+}
+
+/// Subscribed podcasts, their episodes and what was played of them.
+pub struct Podcasts {
+    pub podcasts: Vec<Podcast>,
+    pub states: BTreeMap<String, EpisodeState>,
+    pub podcast_index: ListState,
+    pub episode_index: ListState,
+    // ←/→ move between the podcasts and their episodes
+    pub episodes_focused: bool,
+    // text of the subscribe prompt while it is open
+    pub input: Option<String>,
+    pub unsubscribe_armed: Option<usize>,
+    // `podcasts.toml` could not be read, it is not overwritten
+    broken: bool,
+    // None refreshes only on request
+    refresh_every: Option<Duration>,
+    refreshed: Option<Instant>,
+    // feeds arriving one by one with the url they were fetched from
+    refreshing: Option<Receiver<(String, Result<Feed, failure::Error>)>>,
+    adding: Option<Receiver<Result<Loaded, failure::Error>>>,
+    // kept episodes downloading, by episode id
+    pub downloads: BTreeMap<String, EpisodeDownload>,
+    // an episode played without keeping it
+    stream: Option<(String, EpisodeDownload)>,
+    starting: Option<Starting>,
+    playing: Option<Playing>,
+    saved: Instant,
+}
+
+fn podcasts_toml() -> PathBuf {
+    let mut config_dir = dirs::config_dir().unwrap();
+    config_dir.push("RustPlayer");
+    config_dir.push("podcasts.toml");
+    config_dir
+}
+
+// `~/Music/Podcasts/<podcast>`
+fn download_dir(podcast: &str) -> PathBuf {
+    let mut dir = dirs::audio_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default();
+    dir.push("Podcasts");
+    dir.push(safe_file_name(podcast, "podcast"));
+    dir
+}
+
+fn stream_dir() -> PathBuf {
+    let mut dir = dirs::cache_dir().unwrap_or_else(std::env::temp_dir);
+    dir.push("RustPlayer");
+    dir.push("podcasts");
+    dir
+}
+
+// the extension of `path` for the decoder
+fn hint(path: &str) -> Option<String> {
+    let ext = Path::new(path).extension()?;
+    Some(ext.to_string_lossy().to_string())
+}
+
+fn load(location: &str) -> Result<Loaded, failure::Error> {
+    let (text, url) = if location.contains("://") {
+        fetch_text(location, &[])?
+    } else {
+        (std::fs::read_to_string(location)?, location.to_string())
+    };
+    if is_opml(text.as_str()) {
+        return Ok(Loaded::Opml(parse_opml(text.as_str())?));
+    }
+    // subscribed by the url given, not the one after redirects
+    let feed = parse_feed(text.as_str(), url.as_str())?;
+    Ok(Loaded::Feed(location.to_string(), feed))
+}
+
+impl Podcasts {
+    /// Read `podcasts.toml`, feeds are loaded by the first `tick`. The
+    /// error is for the status line.
+    pub fn load(refresh_every: Option<Duration>) -> (Self, Option<String>) {
+        let (file, error) = match std::fs::read_to_string(podcasts_toml()) {
+            Ok(text) => match PodcastsFile::parse(text.as_str()) {
+                Ok(file) => (file, None),
+                Err(err) => (PodcastsFile::default(), Some(err)),
+            },
+            Err(_) => (PodcastsFile::default(), None),
+        };
+        let podcasts = Self {
+            podcasts: file
+                .podcast
+                .into_iter()
+                .map(|subscription| Podcast {
+                    subscription,
+                    feed: None,
+                    error: None,
+                })
+                .collect(),
+            states: file.episode,
+            podcast_index: ListState::default(),
+            episode_index: ListState::default(),
+            episodes_focused: false,
+            input: None,
+            unsubscribe_armed: None,
+            broken: error.is_some(),
+            refresh_every,
+            refreshed: None,
+            refreshing: None,
+            adding: None,
+            downloads: BTreeMap::new(),
+            stream: None,
+            starting: None,
+            playing: None,
+            saved: Instant::now(),
+        };
+        let error = error.map(|err| format!("podcasts.toml: {}", err));
+        (podcasts, error)
+    }
+
+    pub fn save(&mut self) -> Result<(), failure::Error> {
+        if self.broken {
+            return Err(failure::format_err!(
+                "podcasts.toml has errors, fix it first"
+            ));
+        }
+        let file = PodcastsFile {
+            podcast: self
+                .podcasts
+                .iter()
+                .map(|podcast| Subscription {
+                    url: podcast.subscription.url.clone(),
+                    title: podcast.title().to_string(),
+                })
+                .collect(),
+            // untouched episodes need no entry
+            episode: self
+                .states
+                .iter()
+                .filter(|(_, state)| **state != EpisodeState::default())
+                .map(|(id, state)| (id.clone(), state.clone()))
+                .collect(),
+        };
+        let text = file
+            .to_toml()
+            .map_err(|err| failure::format_err!("{}", err))?;
+        let path = podcasts_toml();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, format!("{}{}", HEADER, text))?;
+        self.saved = Instant::now();
+        Ok(())
+    }
+
+    /// Fetch every feed again in the background.
+    pub fn refresh(&mut self) {
+        let urls: Vec<String> = self
+            .podcasts
+            .iter()
+            .map(|podcast| podcast.subscription.url.clone())
+            .collect();
+        self.refreshed = Some(Instant::now());
+        self.refreshing = Some(fetch_feeds(urls));
+    }
+
+    pub fn is_refreshing(&self) -> bool {
+        self.refreshing.is_some() || self.adding.is_some()
+    }
+
+    /// Subscribe to the feed or the OPML list at a path or url.
+    pub fn subscribe(&mut self, location: &str) {
+        let (tx, rx) = channel();
+        let location = location.trim().to_string();
+        thread::spawn(move || {
+            let _ = tx.send(load(location.as_str()));
+        });
+        self.adding = Some(rx);
+    }
+
+    pub fn unsubscribe(&mut self, index: usize) -> Result<Podcast, failure::Error> {
+        let podcast = self.podcasts.remove(index);
+        self.select_podcast(index.min(self.podcasts.len().saturating_sub(1)));
+        self.save()?;
+        Ok(podcast)
+    }
+
+    /// Refresh when due, take in loaded feeds, start an episode once enough
+    /// of it is there and follow the one playing. A message for the status
+    /// line when something happened.
+    pub fn tick(&mut self, player: &mut MusicPlayer) -> Option<String> {
+        let due = match (self.refresh_every, self.refreshed) {
+            (_, None) => true,
+            (Some(every), Some(at)) => at.elapsed() >= every,
+            (None, Some(_)) => false,
+        };
+        if due && !self.is_refreshing() && !self.podcasts.is_empty() {
+            self.refresh();
+        }
+        let mut msg = self.receive_feeds();
+        msg = self.receive_added().or(msg);
+        msg = self.finish_downloads().or(msg);
+        msg = self.start_playing(player).or(msg);
+        self.follow(player);
+        msg
+    }
+
+    fn receive_feeds(&mut self) -> Option<String> {
+        let rx = self.refreshing.as_ref()?;
+        let mut failed = vec![];
+        loop {
+            match rx.try_recv() {
+                Ok((url, result)) => {
+                    if let Some(podcast) = self
+                        .podcasts
+                        .iter_mut()
+                        .find(|podcast| podcast.subscription.url == url)
+                    {
+                        match result {
+                            Ok(feed) => {
+                                podcast.feed = Some(feed);
+                                podcast.error = None;
+                            }
+                            Err(err) => {
+                                failed.push(podcast.title().to_string());
+                                podcast.error = Some(err.to_string());
+                            }
+                        }
+                    }
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.refreshing = None;
+                    break;
+                }
+            }
+        }
+        self.clamp_selection();
+        match failed.as_slice() {
+            [] => None,
+            [title] => Some(format!("Refreshing {} failed", title)),
+            [title, more @ ..] => Some(format!(
+                "Refreshing {} (and {} more) failed",
+                title,
+                more.len()
+            )),
+        }
+    }
+
+    fn receive_added(&mut self) -> Option<String> {
+        let result = self.adding.as_ref()?.try_recv().ok()?;
+        self.adding = None;
+        let msg = match result {
+            Ok(Loaded::Feed(url, feed)) => {
+                if self.is_subscribed(url.as_str()) {
+                    return Some(format!("Already subscribed to {}", url));
+                }
+                let msg = format!("Subscribed to {}", feed.title);
+                self.podcasts.push(Podcast {
+                    subscription: Subscription {
+                        url,
+                        title: feed.title.clone(),
+                    },
+                    feed: Some(feed),
+                    error: None,
+                });
+                self.select_podcast(self.podcasts.len() - 1);
+                msg
+            }
+            Ok(Loaded::Opml(subscriptions)) => {
+                let new: Vec<Subscription> = subscriptions
+                    .into_iter()
+                    .filter(|sub| !self.is_subscribed(sub.url.as_str()))
+                    .collect();
+                let count = new.len();
+                self.podcasts
+                    .extend(new.into_iter().map(|subscription| Podcast {
+                        subscription,
+                        feed: None,
+                        error: None,
+                    }));
+                self.refresh();
+                self.clamp_selection();
+                format!("Imported {} podcasts", count)
+            }
+            Err(err) => return Some(format!("Subscribing failed: {}", err)),
+        };
+        match self.save() {
+            Ok(_) => Some(msg),
+            Err(err) => Some(format!("{}, not saved: {}", msg, err)),
+        }
+    }
+
+    fn is_subscribed(&self, url: &str) -> bool {
+        self.podcasts
+            .iter()
+            .any(|podcast| podcast.subscription.url == url)
+    }
+
+    // finished downloads are remembered, failed ones reported
+    fn finish_downloads(&mut self) -> Option<String> {
+        let (id, done) = self
+            .downloads
+            .iter()
+            .find(|(_, download)| download.is_finished() || download.error().is_some())
+            .map(|(id, download)| (id.clone(), download.error().is_none()))?;
+        let download = self.downloads.remove(&id)?;
+        let name = download
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !done {
+            let err = download.error().unwrap_or_default();
+            return Some(format!("Downloading {} failed: {}", name, err));
+        }
+        let state = self.states.entry(id).or_default();
+        state.file = Some(download.path.to_string_lossy().to_string());
+        let _ = self.save();
+        Some(format!("Downloaded {}", name))
+    }
+
+    /// Download `episode` of the selected podcast to keep it.
+    pub fn download(&mut self, episode: &Episode) -> Result<String, failure::Error> {
+        if self.downloaded(episode.id.as_str()).is_some() {
+            return Ok(format!("{} is already downloaded", episode.title));
+        }
+        if self.downloads.contains_key(&episode.id) {
+            return Ok(format!("{} is downloading", episode.title));
+        }
+        let podcast = self.selected_podcast().map(|p| p.title().to_string());
+        let mut path = download_dir(podcast.unwrap_or_default().as_str());
+        path.push(file_name(episode));
+        let download = EpisodeDownload::start(episode.url.as_str(), &path)?;
+        self.downloads.insert(episode.id.clone(), download);
+        Ok(format!("Downloading {}", episode.title))
+    }
+
+    // the finished download of an episode, if it is still there
+    fn downloaded(&self, id: &str) -> Option<&str> {
+        let file = self.states.get(id)?.file.as_deref()?;
+        Some(file).filter(|file| Path::new(file).exists())
+    }
+
+    /// Play `episode` where it was left, from its download if there is one,
+    /// otherwise streamed. Playback starts with a later `tick`.
+    pub fn play(&mut self, episode: &Episode) -> Result<String, failure::Error> {
+        let id = episode.id.clone();
+        let resume = self
+            .states
+            .get(&id)
+            .map_or(Duration::ZERO, |state| state.resume());
+        let local = self.downloaded(id.as_str()).is_some() || self.downloads.contains_key(&id);
+        let streaming = self
+            .stream
+            .as_ref()
+            .is_some_and(|(stream, _)| *stream == id);
+        if !local && !streaming {
+            let mut path = stream_dir();
+            path.push(file_name(episode));
+            // the previous one is not needed any more
+            if let Some((_, previous)) = self.stream.take() {
+                let _ = std::fs::remove_file(&previous.path);
+            }
+            self.stream = Some((
+                id.clone(),
+                EpisodeDownload::start(episode.url.as_str(), &path)?,
+            ));
+        }
+        self.starting = Some(Starting {
+            id,
+            title: episode.title.clone(),
+            duration: episode.duration,
+            resume,
+            decoder: None,
+        });
+        Ok(match resume.is_zero() {
+            true => format!("Loading {}", episode.title),
+            false => format!("Resuming {} at {}", episode.title, format_clock(resume)),
+        })
+    }
+
+    // the download of an episode, kept or streamed
+    fn download_of(&self, id: &str) -> Option<&EpisodeDownload> {
+        self.downloads.get(id).or(match &self.stream {
+            Some((stream, download)) if stream == id => Some(download),
+            _ => None,
+        })
+    }
+
+    fn start_playing(&mut self, player: &mut MusicPlayer) -> Option<String> {
+        let starting = self.starting.as_ref()?;
+        let ready = match &starting.decoder {
+            Some((_, decoder)) => decoder.try_ready()?,
+            None => {
+                let decoder = self.open_starting()?;
+                self.starting.as_mut()?.decoder = Some(decoder);
+                return None;
+            }
+        };
+        let starting = self.starting.take()?;
+        let (path, _) = starting.decoder?;
+        let audio = match ready {
+            Ok(audio) => audio,
+            Err(err) => return Some(format!("Cannot play {}: {}", starting.title, err)),
+        };
+        let duration = audio.duration;
+        player.play_episode(starting.title.as_str(), path.as_str(), audio);
+        self.playing = Some(Playing {
+            id: starting.id,
+            path,
+            duration,
+        });
+        Some(format!("Playing {}", starting.title))
+    }
+
+    // start decoding the starting episode once enough of it is there
+    fn open_starting(&mut self) -> Option<(String, EpisodeDecoder)> {
+        let starting = self.starting.as_ref()?;
+        let (duration, resume) = (starting.duration, starting.resume);
+        if let Some(file) = self.downloaded(starting.id.as_str()) {
+            let path = file.to_string();
+            let len = std::fs::metadata(&path).ok().map(|meta| meta.len());
+            let decoder = match std::fs::File::open(&path) {
+                Ok(file) => EpisodeDecoder::start(file, len, hint(&path), duration, resume),
+                Err(err) => EpisodeDecoder::failed(err.to_string()),
+            };
+            return Some((path, decoder));
+        }
+        let download = self.download_of(starting.id.as_str())?;
+        if let Some(err) = download.error() {
+            return Some((String::new(), EpisodeDecoder::failed(err)));
+        }
+        let needed = resume_offset(download.total(), duration, resume) + START_BYTES;
+        let needed = download.total().map_or(needed, |total| needed.min(total));
+        if download.written() < needed && !download.is_finished() {
+            return None;
+        }
+        let path = download.path.to_string_lossy().to_string();
+        let total = download.total();
+        let decoder = match download.reader() {
+            Ok(reader) => EpisodeDecoder::start(reader, total, hint(&path), duration, resume),
+            Err(err) => EpisodeDecoder::failed(err.to_string()),
+        };
+        Some((path, decoder))
+    }
+
+    // remember where the playing episode is, it was played once it ends
+    // close to the end
+    fn follow(&mut self, player: &MusicPlayer) {
+        let playing = match &self.playing {
+            Some(playing) => playing,
+            None => return,
+        };
+        let state = self.states.entry(playing.id.clone()).or_default();
+        match player.playing_song() {
+            Some(song) if song.path == playing.path => {
+                state.position = player.position().as_secs();
+                if self.saved.elapsed() >= SAVE_EVERY {
+                    let _ = self.save();
+                }
+            }
+            // finished, or something else is played now
+            _ => {
+                state.stopped(playing.duration);
+                self.playing = None;
+                let _ = self.save();
+            }
+        }
+    }
+
+    /// Mark an episode played, or unplayed again.
+    pub fn toggle_played(&mut self, id: &str) -> bool {
+        let state = self.states.entry(id.to_string()).or_default();
+        state.played = !state.played;
+        state.position = 0;
+        let played = state.played;
+        let _ = self.save();
+        played
+    }
+
+    pub fn selected_podcast(&self) -> Option<&Podcast> {
+        self.podcasts.get(self.podcast_index.selected()?)
+    }
+
+    pub fn selected_episode(&self) -> Option<&Episode> {
+        self.selected_podcast()?
+            .episodes()
+            .get(self.episode_index.selected()?)
+    }
+
+    fn select_podcast(&mut self, index: usize) {
+        self.podcast_index
+            .select(Some(index).filter(|_| !self.podcasts.is_empty()));
+        self.episode_index.select(None);
+        self.clamp_selection();
+    }
+
+    // keep the selections within the lists, which change with refreshes
+    fn clamp_selection(&mut self) {
+        if self.podcasts.is_empty() {
+            self.podcast_index.select(None);
+        } else if self.podcast_index.selected().is_none() {
+            self.podcast_index.select(Some(0));
+        }
+        let episodes = self.selected_podcast().map_or(0, |p| p.episodes().len());
+        match self.episode_index.selected() {
+            _ if episodes == 0 => self.episode_index.select(None),
+            Some(i) if i >= episodes => self.episode_index.select(Some(episodes - 1)),
+            None => self.episode_index.select(Some(0)),
+            Some(_) => {}
+        }
+    }
+
+    pub fn select_next(&mut self, down: bool) {
+        let (len, selected) = if self.episodes_focused {
+            let len = self.selected_podcast().map_or(0, |p| p.episodes().len());
+            (len, self.episode_index.selected())
+        } else {
+            (self.podcasts.len(), self.podcast_index.selected())
+        };
+        if len == 0 {
+            return;
+        }
+        let selected = selected.unwrap_or(0);
+        let next = match down {
+            true => (selected + 1) % len,
+            false => (selected + len - 1) % len,
+        };
+        if self.episodes_focused {
+            self.episode_index.select(Some(next));
+        } else {
+            self.select_podcast(next);
+        }
+    }
+
+    // `✓`, `12:30`, `↓ 45%`... of an episode
+    fn status(&self, episode: &Episode) -> String {
+        let mut status = vec![];
+        let state = self.states.get(&episode.id);
+        if let Some(download) = self.downloads.get(&episode.id) {
+            match download.total() {
+                Some(total) => status.push(format!("↓ {}%", download.written() * 100 / total)),
+                None => status.push("↓".to_string()),
+            }
+        } else if self.downloaded(episode.id.as_str()).is_some() {
+            status.push("saved".to_string());
+        }
+        match state {
+            Some(state) if state.played => status.push("✓".to_string()),
+            Some(state) if state.position > 0 => status.push(format!(
+                "at {}",
+                format_clock(Duration::from_secs(state.position))
+            )),
+            _ => status.push("new".to_string()),
+        }
+        status.join(" ")
+    }
+}
+
+fn fetch_feeds(urls: Vec<String>) -> Receiver<(String, Result<Feed, failure::Error>)> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for url in urls {
+            let feed = fetch_text(url.as_str(), &[])
+                .map_err(failure::Error::from)
+                .and_then(|(text, final_url)| parse_feed(text.as_str(), final_url.as_str()));
+            if tx.send((url, feed)).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+pub fn draw_podcasts<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(100), Constraint::Length(3)])
+        .split(area);
+    let lists = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(chunks[0]);
+    let podcasts = &mut app.podcasts;
+    let focused = |on: bool| Style::default().fg(if on { Color::Cyan } else { Color::White });
+    let highlight = Style::default()
+        .add_modifier(Modifier::BOLD)
+        .bg(Color::DarkGray);
+
+    let items: Vec<ListItem> = podcasts
+        .podcasts
+        .iter()
+        .map(|podcast| {
+            let mut spans = vec![Span::raw(podcast.title().to_string())];
+            let new = podcast
+                .episodes()
+                .iter()
+                .filter(|e| !podcasts.states.get(&e.id).is_some_and(|s| s.played))
+                .count();
+            if new > 0 {
+                spans.push(Span::styled(
+                    format!("  ({})", new),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            if podcast.error.is_some() {
+                spans.push(Span::styled("  failed", Style::default().fg(Color::Red)));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let title = if podcasts.is_refreshing() {
+        "Podcasts - refreshing".to_string()
+    } else {
+        format!("Podcasts ({})", podcasts.podcasts.len())
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(focused(!podcasts.episodes_focused)),
+        )
+        .highlight_style(highlight)
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, lists[0], &mut podcasts.podcast_index);
+
+    let (title, items) = match podcasts.selected_podcast() {
+        Some(podcast) => {
+            let items: Vec<ListItem> = podcast
+                .episodes()
+                .iter()
+                .map(|episode| {
+                    let played = podcasts.states.get(&episode.id).is_some_and(|s| s.played);
+                    let title_style = match played {
+                        true => Style::default().fg(Color::DarkGray),
+                        false => Style::default(),
+                    };
+                    let mut details = vec![];
+                    if let Some(published) = episode.published {
+                        details.push(format_date(published));
+                    }
+                    if let Some(duration) = episode.duration {
+                        details.push(format_clock(duration));
+                    }
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(episode.title.clone(), title_style),
+                        Span::styled(
+                            format!("  {}", details.join("  ")),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                        Span::styled(
+                            format!("  {}", podcasts.status(episode)),
+                            Style::default().fg(Color::Cyan),
+                        ),
+                    ]))
+                })
+                .collect();
+            let title = match &podcast.error {
+                Some(err) => format!("{} - {}", podcast.title(), err),
+                None => podcast.title().to_string(),
+            };
+            (title, items)
+        }
+        None => ("Episodes".to_string(), vec![]),
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(focused(podcasts.episodes_focused)),
+        )
+        .highlight_style(highlight)
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, lists[1], &mut podcasts.episode_index);
+
+    let bottom = match &podcasts.input {
+        Some(input) => Paragraph::new(Spans::from(vec![
+            Span::styled("Feed or OPML url/path: ", Style::default().fg(Color::Cyan)),
+            Span::raw(input.as_str()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ])),
+        None => Paragraph::new(
+            "←/→: podcasts/episodes  Enter: play  w: download  m: played  a: subscribe  d: unsubscribe  r: refresh  s: pause  Esc: close",
+        ),
+    };
+    let bottom = bottom.wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(bottom, chunks[1]);
+}
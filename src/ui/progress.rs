@@ -15,6 +15,8 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use tui::{
     backend::Backend,
     layout::{Constraint, Layout, Rect},
@@ -24,7 +26,10 @@ use tui::{
     Frame,
 };
 
-use crate::{app::App, media::player::Player};
+use crate::{
+    app::{ActiveModules, App},
+    media::player::Player,
+};
 
 pub fn draw_progress<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
 where
@@ -32,8 +37,18 @@ where
 {
     let player = &app.player;
 
-    let current_time = player.current_time;
-    let total_time = player.total_time;
+    let mut current_time = player.current_time;
+    let mut total_time = player.total_time;
+    let mut is_playing = player.is_playing();
+    // VOD radio playlists have a known length
+    if app.active_modules == ActiveModules::RadioList {
+        let (current, total) = app.radio.get_progress();
+        if total > 0.0 {
+            current_time = Duration::from_secs_f32(current);
+            total_time = Duration::from_secs_f32(total);
+            is_playing = app.radio.is_playing();
+        }
+    }
 
     let minute_mins = current_time.as_secs() / 60;
     let minute_secs = current_time.as_secs() % 60;
@@ -42,13 +57,13 @@ where
     let total_secs = total_time.as_secs() % 60;
     let mut percent = 0.0;
     if total_time.as_secs() != 0 {
-        percent = if player.is_playing() {
+        percent = if is_playing {
             current_time.as_secs_f64() / total_time.as_secs_f64()
         } else {
             0.0
         };
     }
-    let s = if player.is_playing() {
+    let s = if is_playing {
         format!(
             "{:0>2}:{:0>2} / {:0>2}:{:0>2}",
             minute_mins, minute_secs, total_mins, total_secs
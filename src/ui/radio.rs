@@ -1,7 +1,7 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    vec,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    thread, vec,
 };
 
 use dirs;
@@ -13,51 +13,410 @@ use tui::{
     Frame,
 };
 
-use crate::app::App;
+use crate::{
+    app::App,
+    util::{
+        hls::VariantPolicy,
+        net::fetch_text,
+        playlist::parse_playlist,
+        stations::{migrate_radio_ini, parse_stations, Station, StationsDocument},
+    },
+};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct RadioConfig {
     pub name: String,
     pub url: String,
+    // tried in order when `url` cannot be played
+    pub fallbacks: Vec<String>,
+    pub group: Option<String>,
+    pub favourite: bool,
+    // sent with every request of the station
+    pub headers: Vec<(String, String)>,
+    pub user_agent: Option<String>,
+    // instead of `variant` of `config.ini`
+    pub variant: Option<VariantPolicy>,
+    // the `[[playlist]]` it was imported from, edited there
+    pub playlist: Option<String>,
 }
 
+impl RadioConfig {
+    /// `url` followed by the fallbacks.
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![self.url.clone()];
+        urls.extend(self.fallbacks.iter().cloned());
+        urls
+    }
+
+    /// `headers` plus the user agent.
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if let Some(user_agent) = &self.user_agent {
+            headers.push(("User-Agent".to_string(), user_agent.clone()));
+        }
+        headers
+    }
+}
+
+const HEADER: &str = "\
+# RustPlayer radio stations
+#
+# [[station]]
+# name = \"经济之声\"
+# url = \"http://ngcdn002.cnr.cn/live/jjzs/index.m3u8\"  # or urls = [...] with fallbacks
+# group = \"News\"
+# favourite = true
+# variant = \"lowest\"  # highest, lowest or kbps
+# user_agent = \"RustPlayer\"
+# headers = { Referer = \"http://www.cnr.cn\" }
+#
+# [[playlist]]
+# location = \"/path/or/url/of/stations.pls\"
+
+";
+
+// a playlist location with its stations
+type LoadedPlaylist = (String, Result<Vec<RadioConfig>, failure::Error>);
+
+/// The radio list, stations of `stations.toml` first, then the ones of its
+/// playlists.
 pub struct RadioExplorer {
     pub radios: Vec<RadioConfig>,
     pub index: ListState,
+    // `[[playlist]]` locations of `stations.toml`
+    pub playlists: Vec<String>,
+    // station to delete on the second press of `d`
+    pub delete_armed: Option<usize>,
+    // invalid entries of `stations.toml`, for the status line
+    pub errors: Vec<String>,
+    // edited in place on save, None when it could not be parsed
+    document: Option<StationsDocument>,
+    // `[[station]]` table of each station of `stations.toml` in the list
+    entries: Vec<usize>,
+    // stations of the playlists, arriving one playlist at a time
+    loading: Option<Receiver<LoadedPlaylist>>,
 }
 
 impl RadioExplorer {
     pub fn new() -> Self {
-        let mut config_dir = dirs::config_dir().unwrap();
-        let mut configs = vec![];
-        config_dir.push("RustPlayer");
-        std::fs::create_dir_all(config_dir.clone()).unwrap();
-        config_dir.push("radio.ini");
-        let f: File;
-        if !config_dir.as_path().exists() {
-            File::create(config_dir.clone()).unwrap();
+        let config_path = stations_toml();
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        let mut errors = vec![];
+        if !config_path.exists() {
+            if let Err(err) = migrate(config_path.as_path()) {
+                errors.push(format!("Migrating radio.ini failed: {}", err));
+            }
         }
-        f = File::open(config_dir).unwrap();
-        let reader = BufReader::new(f);
-        let mut lines = reader.lines().map(|i| i.unwrap());
-        while let Some(line) = lines.next() {
-            if line.is_empty() {
-                continue;
+        let mut text = std::fs::read_to_string(config_path).unwrap_or_default();
+        if text.trim().is_empty() {
+            text = HEADER.to_string();
+        }
+        let mut configs = vec![];
+        let mut entries = vec![];
+        let mut playlists = vec![];
+        let mut document = None;
+        match parse_stations(text.as_str()) {
+            Ok(stations) => {
+                for station in stations.stations {
+                    let entry = station.entry;
+                    match to_config(station) {
+                        Ok(config) => {
+                            configs.push(config);
+                            entries.push(entry);
+                        }
+                        Err(err) => errors.push(err),
+                    }
+                }
+                playlists = stations.playlists;
+                let invalid = stations.errors.into_iter();
+                errors.extend(invalid.map(|err| format!("stations.toml {}", err)));
+                // saving a file that could not be read would lose what is in it
+                match StationsDocument::parse(text.as_str()) {
+                    Ok(parsed) => document = Some(parsed),
+                    Err(err) => errors.push(format!("stations.toml: {}", err)),
+                }
+            }
+            // the first line says where, the rest quotes the file
+            Err(err) => {
+                let err = err.lines().next().unwrap_or_default().to_string();
+                errors.push(format!("stations.toml: {}", err));
             }
-            let radio_bean: Vec<_> = line.split(' ').collect();
-            let config = RadioConfig {
-                name: radio_bean[0].to_string(),
-                url: radio_bean[1].to_string(),
-            };
-            configs.push(config);
         }
         let mut state = ListState::default();
         state.select(Some(0));
+        // urls may take a while, the list fills in when they arrive
+        let loading = load_playlists(playlists.clone());
         Self {
             radios: configs,
             index: state,
+            playlists,
+            delete_armed: None,
+            errors,
+            document,
+            entries,
+            loading: Some(loading),
         }
     }
+
+    /// Take in the stations of playlists loaded since, a message for the
+    /// status line when one failed.
+    pub fn receive(&mut self) -> Option<String> {
+        let mut msg = None;
+        while let Some(received) = self.loading.as_ref().map(|rx| rx.try_recv()) {
+            let (location, result) = match received {
+                Ok(received) => received,
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.loading = None;
+                    break;
+                }
+            };
+            match result {
+                Ok(stations) => self.insert_playlist(location.as_str(), stations),
+                Err(err) => msg = Some(format!("Playlist {}: {}", location, err)),
+            }
+        }
+        msg
+    }
+
+    // after the stations of the playlists listed before it
+    fn insert_playlist(&mut self, location: &str, stations: Vec<RadioConfig>) {
+        let order = |playlist: &str| self.playlists.iter().position(|p| p == playlist);
+        let this = order(location);
+        let at = self
+            .radios
+            .iter()
+            .position(|radio| match &radio.playlist {
+                Some(playlist) => order(playlist.as_str()) > this,
+                None => false,
+            })
+            .unwrap_or(self.radios.len());
+        let count = stations.len();
+        self.radios.splice(at..at, stations);
+        match self.index.selected() {
+            Some(selected) if selected >= at && selected < self.radios.len() - count => {
+                self.index.select(Some(selected + count))
+            }
+            Some(_) => {}
+            None => self.index.select(Some(0)),
+        }
+    }
+
+    /// Add a station to the list and to `stations.toml`.
+    pub fn add(&mut self, config: RadioConfig) -> Result<(), failure::Error> {
+        self.update(None, config)
+    }
+
+    /// Replace the station at `index`, or add one after the other stations
+    /// of `stations.toml` when None, and save.
+    pub fn update(
+        &mut self,
+        index: Option<usize>,
+        config: RadioConfig,
+    ) -> Result<(), failure::Error> {
+        let station = to_station(&config);
+        let entry = index.and_then(|index| self.entries.get(index).copied());
+        let entry = self.document()?.set_station(entry, &station);
+        let at = match index {
+            Some(index) => {
+                self.radios[index] = config;
+                index
+            }
+            None => {
+                let at = self.own_stations();
+                self.radios.insert(at, config);
+                self.entries.push(entry);
+                at
+            }
+        };
+        self.index.select(Some(at));
+        self.save()
+    }
+
+    /// Remove the station at `index` and save.
+    pub fn delete(&mut self, index: usize) -> Result<RadioConfig, failure::Error> {
+        self.document()?;
+        if let Some(entry) = self.entries.get(index).copied() {
+            self.document()?.remove_station(entry);
+            self.entries.remove(index);
+            for later in self.entries.iter_mut().filter(|later| **later > entry) {
+                *later -= 1;
+            }
+        }
+        let config = self.radios.remove(index);
+        if self.radios.is_empty() {
+            self.index.select(None);
+        } else {
+            self.index.select(Some(index.min(self.radios.len() - 1)));
+        }
+        self.save()?;
+        Ok(config)
+    }
+
+    /// Swap the station at `index` with the one above or below and save,
+    /// false at the ends of the stations of `stations.toml`.
+    pub fn move_station(&mut self, index: usize, up: bool) -> Result<bool, failure::Error> {
+        let other = match up {
+            true if index > 0 => index - 1,
+            false if index + 1 < self.own_stations() => index + 1,
+            _ => return Ok(false),
+        };
+        let (entry, other_entry) = (self.entries[index], self.entries[other]);
+        self.document()?.swap_stations(entry, other_entry);
+        self.radios.swap(index, other);
+        self.index.select(Some(other));
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Stations at the top of the list that are not imported from a
+    /// playlist.
+    pub fn own_stations(&self) -> usize {
+        self.radios
+            .iter()
+            .take_while(|radio| radio.playlist.is_none())
+            .count()
+    }
+
+    /// Write the edited `stations.toml` back.
+    pub fn save(&self) -> Result<(), failure::Error> {
+        let document = match &self.document {
+            Some(document) => document,
+            None => return Err(broken()),
+        };
+        std::fs::write(stations_toml(), document.to_string())?;
+        Ok(())
+    }
+
+    fn document(&mut self) -> Result<&mut StationsDocument, failure::Error> {
+        self.document.as_mut().ok_or_else(broken)
+    }
+
+    /// Add the stations of a PLS, M3U or XSPF playlist at a path or url and
+    /// remember the playlist in `stations.toml`. Returns the number of
+    /// stations.
+    pub fn import(&mut self, location: &str) -> Result<usize, failure::Error> {
+        let location = match Path::new(location).canonicalize() {
+            Ok(path) if !location.contains("://") => path.to_string_lossy().to_string(),
+            _ => location.to_string(),
+        };
+        self.document()?;
+        let stations = load_playlist(location.as_str())?;
+        if stations.is_empty() {
+            return Err(failure::format_err!("no radio stations found"));
+        }
+        self.document()?.add_playlist(location.as_str());
+        let count = stations.len();
+        self.radios.extend(stations);
+        if self.index.selected().is_none() {
+            self.index.select(Some(0));
+        }
+        self.playlists.push(location);
+        self.save()?;
+        Ok(count)
+    }
+}
+
+fn broken() -> failure::Error {
+    failure::format_err!("stations.toml has errors, fix it first")
+}
+
+/// ~/.config/RustPlayer/stations.toml
+fn stations_toml() -> PathBuf {
+    let mut config_dir = dirs::config_dir().unwrap();
+    config_dir.push("RustPlayer");
+    config_dir.push("stations.toml");
+    config_dir
+}
+
+/// Create `stations.toml` from the `radio.ini` of older versions, which is
+/// kept as `radio.ini.bak`.
+fn migrate(path: &Path) -> Result<(), failure::Error> {
+    let radio_ini = path.with_file_name("radio.ini");
+    let stations = match std::fs::read_to_string(&radio_ini) {
+        Ok(text) => migrate_radio_ini(text.as_str()),
+        Err(_) => String::new(),
+    };
+    std::fs::write(path, format!("{}{}", HEADER, stations))?;
+    if radio_ini.exists() {
+        std::fs::rename(&radio_ini, path.with_file_name("radio.ini.bak"))?;
+    }
+    Ok(())
+}
+
+fn to_station(config: &RadioConfig) -> Station {
+    Station {
+        name: config.name.clone(),
+        urls: config.urls(),
+        group: config.group.clone(),
+        favourite: config.favourite,
+        headers: config.headers.clone(),
+        user_agent: config.user_agent.clone(),
+        variant: config.variant.map(|variant| variant.setting()),
+        line: 0,
+        entry: 0,
+    }
+}
+
+fn to_config(station: Station) -> Result<RadioConfig, String> {
+    let variant = match &station.variant {
+        Some(variant) => match VariantPolicy::parse(variant) {
+            Some(policy) => Some(policy),
+            None => {
+                let err = format!("unknown variant {}", variant);
+                let line = station.line;
+                return Err(format!(
+                    "stations.toml line {}: {}: {}",
+                    line, station.name, err
+                ));
+            }
+        },
+        None => None,
+    };
+    Ok(RadioConfig {
+        name: station.name,
+        url: station.urls[0].clone(),
+        fallbacks: station.urls[1..].to_vec(),
+        group: station.group,
+        favourite: station.favourite,
+        headers: station.headers,
+        user_agent: station.user_agent,
+        variant,
+        playlist: None,
+    })
+}
+
+fn load_playlists(locations: Vec<String>) -> Receiver<LoadedPlaylist> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for location in locations {
+            let stations = load_playlist(location.as_str());
+            if tx.send((location, stations)).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+fn load_playlist(location: &str) -> Result<Vec<RadioConfig>, failure::Error> {
+    // relative entries resolve against the url after redirects
+    let (text, base) = if location.contains("://") {
+        fetch_text(location, &[])?
+    } else {
+        (std::fs::read_to_string(location)?, location.to_string())
+    };
+    let stations = parse_playlist(base.as_str(), text.as_str())
+        .into_iter()
+        .map(|station| RadioConfig {
+            name: station.name,
+            url: station.urls[0].clone(),
+            fallbacks: station.urls[1..].to_vec(),
+            playlist: Some(location.to_string()),
+            ..Default::default()
+        })
+        .collect();
+    Ok(stations)
 }
 
 pub fn draw_radio_list<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
@@ -67,7 +426,14 @@ where
     let fs = &mut app.radio_fs;
     let mut item_vec = vec![];
     for radio in &fs.radios {
-        item_vec.push(ListItem::new(radio.name.as_str()));
+        let mut name = radio.name.clone();
+        if radio.favourite {
+            name = format!("★ {}", name);
+        }
+        if let Some(group) = &radio.group {
+            name = format!("{} [{}]", name, group);
+        }
+        item_vec.push(ListItem::new(name));
     }
     let list = List::new(item_vec)
         .block(
@@ -0,0 +1,275 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    util::{
+        catalogue::{Catalogue, CatalogueStation, Facet, Filter},
+        net::fetch_text,
+    },
+};
+
+/// Browses a Radio Browser style catalogue, filtered by country, language,
+/// tag and codec.
+pub struct StationBrowser {
+    pub location: String,
+    pub catalogue: Option<Catalogue>,
+    loading: Option<Receiver<Result<Catalogue, failure::Error>>>,
+    pub filter: Filter,
+    // index in `Facet::ALL` changed by ←/→
+    pub facet: usize,
+    // indexes of the stations passing the filter
+    pub matches: Vec<usize>,
+    pub index: ListState,
+}
+
+impl StationBrowser {
+    /// Start loading the catalogue at a path or url in the background.
+    pub fn open(location: &str) -> Self {
+        let (tx, rx) = channel();
+        let path = location.to_string();
+        thread::spawn(move || {
+            let _ = tx.send(load_catalogue(path.as_str()));
+        });
+        Self {
+            location: location.to_string(),
+            catalogue: None,
+            loading: Some(rx),
+            filter: Filter::default(),
+            facet: 0,
+            matches: vec![],
+            index: ListState::default(),
+        }
+    }
+
+    /// A message for the status line once loading finished.
+    pub fn receive(&mut self) -> Option<String> {
+        let result = self.loading.as_ref()?.try_recv().ok()?;
+        self.loading = None;
+        match result {
+            Ok(catalogue) => {
+                let msg = format!("{} stations in {}", catalogue.stations.len(), self.location);
+                self.catalogue = Some(catalogue);
+                self.refilter();
+                Some(msg)
+            }
+            Err(err) => Some(format!("Catalogue failed: {}", err)),
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    pub fn focused_facet(&self) -> Facet {
+        Facet::ALL[self.facet]
+    }
+
+    pub fn next_facet(&mut self) {
+        self.facet = (self.facet + 1) % Facet::ALL.len();
+    }
+
+    /// Step the focused facet through "any" and its values, most common
+    /// first.
+    pub fn cycle(&mut self, forward: bool) {
+        let catalogue = match &self.catalogue {
+            Some(catalogue) => catalogue,
+            None => return,
+        };
+        let facet = self.focused_facet();
+        let values = catalogue.values(facet, &self.filter);
+        // 0 is "any"
+        let current = self
+            .filter
+            .values
+            .get(&facet)
+            .and_then(|value| values.iter().position(|(v, _)| v == value))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let len = values.len() + 1;
+        let next = if forward {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+        match next {
+            0 => self.filter.values.remove(&facet),
+            i => self.filter.values.insert(facet, values[i - 1].0.clone()),
+        };
+        self.refilter();
+    }
+
+    fn refilter(&mut self) {
+        self.matches = match &self.catalogue {
+            Some(catalogue) => catalogue.filter(&self.filter),
+            None => vec![],
+        };
+        self.index.select(if self.matches.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    pub fn select_next(&mut self, down: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() - 1;
+        let selected = self.index.selected().unwrap_or(0);
+        let next = match (down, selected) {
+            (true, s) if s >= last => 0,
+            (true, s) => s + 1,
+            (false, 0) => last,
+            (false, s) => s - 1,
+        };
+        self.index.select(Some(next));
+    }
+
+    pub fn selected(&self) -> Option<&CatalogueStation> {
+        let catalogue = self.catalogue.as_ref()?;
+        let i = *self.matches.get(self.index.selected()?)?;
+        catalogue.stations.get(i)
+    }
+}
+
+fn load_catalogue(location: &str) -> Result<Catalogue, failure::Error> {
+    let json = if location.contains("://") {
+        fetch_text(location, &[])?.0
+    } else {
+        std::fs::read_to_string(location)?
+    };
+    Catalogue::parse(json.as_str())
+}
+
+pub fn draw_station_browser<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Percentage(100),
+            Constraint::Length(3),
+        ])
+        .split(area);
+    let browser = match &mut app.station_browser {
+        Some(browser) => browser,
+        None => return,
+    };
+    let mut filters = vec![];
+    for (i, facet) in Facet::ALL.iter().enumerate() {
+        let value = browser
+            .filter
+            .values
+            .get(facet)
+            .map(|value| value.as_str())
+            .unwrap_or("any");
+        let style = if i == browser.facet {
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        filters.push(Span::styled(
+            format!(" {}: {} ", facet.label(), value),
+            style,
+        ));
+        filters.push(Span::raw("  "));
+    }
+    let filters = Paragraph::new(Spans::from(filters)).block(
+        Block::default()
+            .title("Filters")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(filters, chunks[0]);
+
+    let mut items = vec![];
+    if let Some(catalogue) = &browser.catalogue {
+        for i in browser.matches.iter() {
+            let station = &catalogue.stations[*i];
+            let mut details = station.codec.clone();
+            if station.bitrate > 0 {
+                details = format!("{} {}kbps", details, station.bitrate);
+            }
+            if station.hls {
+                details = format!("{} HLS", details);
+            }
+            items.push(ListItem::new(Spans::from(vec![
+                Span::raw(station.name.as_str()),
+                Span::styled(
+                    format!("  [{}]", details.trim()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("  {}", station.country),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    format!("  {}", station.tags.join(", ")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])));
+        }
+    }
+    let title = if browser.is_loading() {
+        format!("Station Browser - loading {}", browser.location)
+    } else {
+        format!("Station Browser ({})", browser.matches.len())
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::DarkGray),
+        )
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, chunks[1], &mut browser.index);
+
+    let tips = Paragraph::new(
+        "Tab: next filter  ←/→: change filter  ↑/↓: select  Enter: preview  a: add to radio list  Esc: close",
+    )
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(tips, chunks[2]);
+}
@@ -0,0 +1,307 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    util::{hls::VariantPolicy, net::BodyReader},
+};
+
+use super::radio::RadioConfig;
+
+/// A row of the station form.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Name,
+    Urls,
+    Group,
+    Variant,
+    UserAgent,
+    Favourite,
+    Test,
+    Save,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::Name,
+        Field::Urls,
+        Field::Group,
+        Field::Variant,
+        Field::UserAgent,
+        Field::Favourite,
+        Field::Test,
+        Field::Save,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Field::Name => "Name",
+            Field::Urls => "URLs",
+            Field::Group => "Group",
+            Field::Variant => "Variant",
+            Field::UserAgent => "User agent",
+            Field::Favourite => "Favourite",
+            Field::Test => "Test connection",
+            Field::Save => "Save",
+        }
+    }
+}
+
+/// Form adding a station to the radio list or editing one of it.
+pub struct StationEditor {
+    // in the radio list, None for a new station
+    pub index: Option<usize>,
+    pub name: String,
+    // space separated, the first one is played
+    pub urls: String,
+    pub group: String,
+    pub variant: String,
+    pub user_agent: String,
+    pub favourite: bool,
+    // not in the form, kept as they are
+    headers: Vec<(String, String)>,
+    // index in `Field::ALL`
+    pub field: usize,
+    testing: Option<Receiver<Result<String, failure::Error>>>,
+    // outcome of the last connection test
+    pub tested: Option<Result<String, String>>,
+}
+
+impl StationEditor {
+    pub fn new(index: Option<usize>, config: Option<&RadioConfig>) -> Self {
+        let config = config.cloned().unwrap_or_default();
+        Self {
+            index,
+            name: config.name.clone(),
+            urls: config.urls().join(" ").trim().to_string(),
+            group: config.group.clone().unwrap_or_default(),
+            variant: config.variant.map(|v| v.setting()).unwrap_or_default(),
+            user_agent: config.user_agent.clone().unwrap_or_default(),
+            favourite: config.favourite,
+            headers: config.headers,
+            field: 0,
+            testing: None,
+            tested: None,
+        }
+    }
+
+    pub fn focused(&self) -> Field {
+        Field::ALL[self.field]
+    }
+
+    pub fn next_field(&mut self, forward: bool) {
+        let count = Field::ALL.len();
+        self.field = match forward {
+            true => (self.field + 1) % count,
+            false => (self.field + count - 1) % count,
+        };
+    }
+
+    /// The text of the focused field, None on the buttons.
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self.focused() {
+            Field::Name => Some(&mut self.name),
+            Field::Urls => Some(&mut self.urls),
+            Field::Group => Some(&mut self.group),
+            Field::Variant => Some(&mut self.variant),
+            Field::UserAgent => Some(&mut self.user_agent),
+            Field::Favourite | Field::Test | Field::Save => None,
+        }
+    }
+
+    /// The station described by the form.
+    pub fn config(&self) -> Result<RadioConfig, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("The station needs a name".to_string());
+        }
+        let urls: Vec<String> = self.urls.split_whitespace().map(String::from).collect();
+        if urls.is_empty() {
+            return Err("The station needs a url".to_string());
+        }
+        if let Some(url) = urls.iter().find(|url| !url.contains("://")) {
+            return Err(format!("{} is not a url", url));
+        }
+        let variant = match self.variant.trim() {
+            "" => None,
+            variant => match VariantPolicy::parse(variant) {
+                Some(policy) => Some(policy),
+                None => return Err(format!("Unknown variant {}", variant)),
+            },
+        };
+        let optional = |text: &str| Some(text.trim().to_string()).filter(|t| !t.is_empty());
+        Ok(RadioConfig {
+            name: name.to_string(),
+            url: urls[0].clone(),
+            fallbacks: urls[1..].to_vec(),
+            group: optional(self.group.as_str()),
+            favourite: self.favourite,
+            headers: self.headers.clone(),
+            user_agent: optional(self.user_agent.as_str()),
+            variant,
+            playlist: None,
+        })
+    }
+
+    /// Connect to the first url in the background, `receive` tells how it
+    /// went.
+    pub fn test(&mut self) -> Result<(), String> {
+        let config = self.config()?;
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let _ = tx.send(test_connection(&config));
+        });
+        self.testing = Some(rx);
+        self.tested = None;
+        Ok(())
+    }
+
+    pub fn is_testing(&self) -> bool {
+        self.testing.is_some()
+    }
+
+    /// A message for the status line once the test finished.
+    pub fn receive(&mut self) -> Option<String> {
+        let result = self.testing.as_ref()?.try_recv().ok()?;
+        self.testing = None;
+        let msg = match &result {
+            Ok(answer) => format!("Connected: {}", answer),
+            Err(err) => format!("Connection failed: {}", err),
+        };
+        self.tested = Some(result.map_err(|err| err.to_string()));
+        Some(msg)
+    }
+}
+
+// content type and bitrate the station answers with
+fn test_connection(config: &RadioConfig) -> Result<String, failure::Error> {
+    let headers = config.request_headers();
+    let headers: Vec<(&str, &str)> = headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let body = BodyReader::open(config.url.as_str(), &headers)?;
+    let mut answer = body
+        .header("content-type")
+        .unwrap_or_else(|| "unknown content type".to_string());
+    if let Some(bitrate) = body.header("icy-br") {
+        answer = format!("{}, {} kbps", answer, bitrate);
+    }
+    Ok(answer)
+}
+
+pub fn draw_station_editor<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let editor = match &app.station_editor {
+        Some(editor) => editor,
+        None => return,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(100), Constraint::Length(3)])
+        .split(area);
+    let label = Style::default().fg(Color::Cyan);
+    let focused = Style::default().fg(Color::Black).bg(Color::Cyan);
+    let mut lines = vec![];
+    for (i, field) in Field::ALL.iter().enumerate() {
+        let is_focused = i == editor.field;
+        let value = match field {
+            Field::Name => editor.name.clone(),
+            Field::Urls => editor.urls.clone(),
+            Field::Group => editor.group.clone(),
+            Field::Variant => editor.variant.clone(),
+            Field::UserAgent => editor.user_agent.clone(),
+            Field::Favourite => {
+                let mark = if editor.favourite { "[x]" } else { "[ ]" };
+                lines.push(Spans::from(Span::styled(
+                    format!("{} {}", mark, field.label()),
+                    if is_focused { focused } else { label },
+                )));
+                continue;
+            }
+            Field::Test | Field::Save => {
+                lines.push(Spans::from(""));
+                let style = match is_focused {
+                    true => focused.add_modifier(Modifier::BOLD),
+                    false => label,
+                };
+                lines.push(Spans::from(Span::styled(
+                    format!("[ {} ]", field.label()),
+                    style,
+                )));
+                continue;
+            }
+        };
+        lines.push(Spans::from(Span::styled(
+            field.label(),
+            if is_focused { focused } else { label },
+        )));
+        // the cursor is at the end of the focused field
+        let cursor = if is_focused { "▏" } else { "" };
+        lines.push(Spans::from(format!("  {}{}", value, cursor)));
+    }
+    let title = match editor.index {
+        Some(_) => "Edit Station",
+        None => "Add Station",
+    };
+    let form = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    frame.render_widget(form, chunks[0]);
+
+    let status = match (&editor.tested, editor.is_testing()) {
+        (_, true) => Span::raw("Connecting..."),
+        (Some(Ok(answer)), _) => Span::styled(
+            format!("Connected: {}", answer),
+            Style::default().fg(Color::Green),
+        ),
+        (Some(Err(err)), _) => Span::styled(
+            format!("Connection failed: {}", err),
+            Style::default().fg(Color::Red),
+        ),
+        (None, false) => Span::raw(
+            "Tab/↑/↓ move, Enter selects, Space toggles favourite, Esc cancels. \
+             URLs are space separated, variant is highest, lowest or kbps.",
+        ),
+    };
+    let help = Paragraph::new(Spans::from(status)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(help, chunks[1]);
+}
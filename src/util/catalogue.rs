@@ -0,0 +1,197 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+/// A station of a Radio Browser style JSON catalogue, such as the dump of
+/// `/json/stations`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatalogueStation {
+    pub uuid: String,
+    pub name: String,
+    pub url: String,
+    pub homepage: String,
+    pub country: String,
+    pub languages: Vec<String>,
+    pub tags: Vec<String>,
+    pub codec: String,
+    // kbps, 0 if unknown
+    pub bitrate: u64,
+    pub hls: bool,
+    pub votes: u64,
+}
+
+/// What stations can be filtered by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Facet {
+    Country,
+    Language,
+    Tag,
+    Codec,
+}
+
+impl Facet {
+    pub const ALL: [Facet; 4] = [Facet::Country, Facet::Language, Facet::Tag, Facet::Codec];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Facet::Country => "Country",
+            Facet::Language => "Language",
+            Facet::Tag => "Tag",
+            Facet::Codec => "Codec",
+        }
+    }
+}
+
+impl CatalogueStation {
+    fn from_json(value: &Value) -> Option<Self> {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        // numbers in the API, strings in some dumps
+        let number = |key: &str| match value.get(key) {
+            Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+            Some(Value::String(s)) => s.trim().parse().unwrap_or(0),
+            Some(Value::Bool(b)) => *b as u64,
+            _ => 0,
+        };
+        let list = |key: &str| {
+            text(key)
+                .split(',')
+                .map(|item| item.trim().to_lowercase())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+        };
+        // stations the directory found broken on its last check
+        if value.get("lastcheckok").is_some() && number("lastcheckok") == 0 {
+            return None;
+        }
+        let url = match text("url_resolved") {
+            url if url.is_empty() => text("url"),
+            url => url,
+        };
+        let name = text("name");
+        if url.is_empty() || name.is_empty() {
+            return None;
+        }
+        Some(Self {
+            uuid: text("stationuuid"),
+            name,
+            url,
+            homepage: text("homepage"),
+            country: text("country"),
+            languages: list("language"),
+            tags: list("tags"),
+            codec: text("codec").to_uppercase(),
+            bitrate: number("bitrate"),
+            hls: number("hls") != 0,
+            votes: number("votes"),
+        })
+    }
+
+    /// Values of `facet`, lower case except for countries and codecs.
+    pub fn values(&self, facet: Facet) -> Vec<&str> {
+        match facet {
+            Facet::Country => vec![self.country.as_str()],
+            Facet::Language => self.languages.iter().map(|s| s.as_str()).collect(),
+            Facet::Tag => self.tags.iter().map(|s| s.as_str()).collect(),
+            Facet::Codec => vec![self.codec.as_str()],
+        }
+    }
+}
+
+/// Chosen value per facet, stations have to match all of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub values: HashMap<Facet, String>,
+}
+
+impl Filter {
+    pub fn matches(&self, station: &CatalogueStation) -> bool {
+        self.values.iter().all(|(facet, value)| {
+            station
+                .values(*facet)
+                .iter()
+                .any(|v| v.eq_ignore_ascii_case(value))
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Catalogue {
+    pub stations: Vec<CatalogueStation>,
+}
+
+impl Catalogue {
+    /// Parse a JSON array of stations, skipping broken and duplicate ones.
+    /// Stations are ordered by votes.
+    pub fn parse(json: &str) -> Result<Self, failure::Error> {
+        let value: Value = serde_json::from_str(json)?;
+        let list = value
+            .as_array()
+            .ok_or_else(|| failure::format_err!("not a list of stations"))?;
+        let mut stations: Vec<CatalogueStation> = vec![];
+        let mut uuids = HashSet::new();
+        let mut urls = HashSet::new();
+        for station in list.iter().filter_map(CatalogueStation::from_json) {
+            let duplicate = (!station.uuid.is_empty() && uuids.contains(&station.uuid))
+                || urls.contains(&station.url);
+            if !duplicate {
+                uuids.insert(station.uuid.clone());
+                urls.insert(station.url.clone());
+                stations.push(station);
+            }
+        }
+        stations.sort_by_key(|station| std::cmp::Reverse(station.votes));
+        Ok(Self { stations })
+    }
+
+    /// Values of `facet` among the stations matching `filter` without that
+    /// facet, most common first.
+    pub fn values(&self, facet: Facet, filter: &Filter) -> Vec<(String, usize)> {
+        let mut others = filter.clone();
+        others.values.remove(&facet);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for station in self.stations.iter().filter(|s| others.matches(s)) {
+            for value in station.values(facet) {
+                if !value.is_empty() {
+                    *counts.entry(value).or_default() += 1;
+                }
+            }
+        }
+        let mut values: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(value, count)| (value.to_string(), count))
+            .collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        values
+    }
+
+    /// Indexes of the stations matching `filter`.
+    pub fn filter(&self, filter: &Filter) -> Vec<usize> {
+        (0..self.stations.len())
+            .filter(|i| filter.matches(&self.stations[*i]))
+            .collect()
+    }
+}
@@ -0,0 +1,586 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use failure::format_err;
+use m3u8_rs::{Map, MasterPlaylist, MediaPlaylist, MediaSegment, VariantStream};
+use roxmltree::{Document, Node};
+
+use super::hls::{format_date_time, parse_date_time, resolve_uri};
+
+// segments of a live manifest offered for playback, older ones are left out
+const LIVE_WINDOW: Duration = Duration::from_secs(120);
+
+/// An audio representation of a DASH manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Representation {
+    pub id: String,
+    // bits per second
+    pub bandwidth: u64,
+    pub codecs: Option<String>,
+    // BaseURL of the manifest, period, adaptation set and representation
+    base_url: String,
+    template: SegmentTemplate,
+}
+
+/// SegmentTemplate attributes, the ones missing on a representation are
+/// inherited from its adaptation set and period.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SegmentTemplate {
+    media: Option<String>,
+    initialization: Option<String>,
+    timescale: Option<u64>,
+    duration: Option<u64>,
+    start_number: Option<u64>,
+    presentation_time_offset: Option<u64>,
+    timeline: Option<Vec<TimelineEntry>>,
+}
+
+/// `<S t d r>` of a SegmentTimeline, `r = -1` repeats up to the next entry
+/// or the end of the period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TimelineEntry {
+    t: Option<u64>,
+    d: u64,
+    r: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Period {
+    // from the start of the presentation
+    start: Duration,
+    duration: Option<Duration>,
+    pub representations: Vec<Representation>,
+}
+
+/// The audio of an MPD manifest, static (on demand) or dynamic (live).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub dynamic: bool,
+    // unix milliseconds, live manifests only
+    availability_start: Option<i64>,
+    time_shift_buffer_depth: Option<Duration>,
+    pub periods: Vec<Period>,
+}
+
+// a segment of a representation: number, start time and duration in
+// timescale units
+struct Segment {
+    number: u64,
+    time: u64,
+    duration: u64,
+}
+
+/// Numbers given to the segments of live SegmentTimelines by their period
+/// and `S@t`, kept from one reload of a manifest to the next so a segment
+/// keeps its number while the window slides.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimelineNumbers {
+    numbers: BTreeMap<(Duration, u64), u64>,
+}
+
+impl TimelineNumbers {
+    // the number of the first of `segments`, from a segment numbered before
+    // or the numbers of an earlier window of the timeline
+    fn first(&self, period: Duration, segments: &[Segment], usual: u64) -> Option<u64> {
+        let known = segments.iter().enumerate().find_map(|(i, segment)| {
+            let number = self.numbers.get(&(period, segment.time))?;
+            number.checked_sub(i as u64)
+        });
+        if known.is_some() {
+            return known;
+        }
+        let first = segments.first()?;
+        let (&(_, time), &number) = self
+            .numbers
+            .range((period, 0)..=(period, u64::MAX))
+            .next_back()?;
+        // the window moved past the last numbered segment, skip the ones
+        // that fell between them
+        let gap = first.time.checked_sub(time)? as f64 / usual as f64;
+        Some(number + (gap.round() as u64).max(1))
+    }
+}
+
+impl SegmentTemplate {
+    fn parse(node: Node) -> Self {
+        let number = |name: &str| node.attribute(name).and_then(|v| v.trim().parse().ok());
+        let timeline = node
+            .children()
+            .find(|child| child.has_tag_name("SegmentTimeline"))
+            .map(|timeline| {
+                timeline
+                    .children()
+                    .filter(|s| s.has_tag_name("S"))
+                    .map(|s| TimelineEntry {
+                        t: s.attribute("t").and_then(|v| v.trim().parse().ok()),
+                        d: s.attribute("d")
+                            .and_then(|v| v.trim().parse().ok())
+                            .unwrap_or(0),
+                        r: s.attribute("r")
+                            .and_then(|v| v.trim().parse().ok())
+                            .unwrap_or(0),
+                    })
+                    .collect()
+            });
+        Self {
+            media: node.attribute("media").map(str::to_string),
+            initialization: node.attribute("initialization").map(str::to_string),
+            timescale: number("timescale"),
+            duration: number("duration"),
+            start_number: number("startNumber"),
+            presentation_time_offset: number("presentationTimeOffset"),
+            timeline,
+        }
+    }
+
+    fn inherit(self, parent: &SegmentTemplate) -> Self {
+        Self {
+            media: self.media.or_else(|| parent.media.clone()),
+            initialization: self
+                .initialization
+                .or_else(|| parent.initialization.clone()),
+            timescale: self.timescale.or(parent.timescale),
+            duration: self.duration.or(parent.duration),
+            start_number: self.start_number.or(parent.start_number),
+            presentation_time_offset: self
+                .presentation_time_offset
+                .or(parent.presentation_time_offset),
+            timeline: self.timeline.or_else(|| parent.timeline.clone()),
+        }
+    }
+
+    // the template of `node` on top of `parent`
+    fn within(node: Node, parent: &SegmentTemplate) -> Self {
+        match child(node, "SegmentTemplate") {
+            Some(template) => SegmentTemplate::parse(template).inherit(parent),
+            None => parent.clone(),
+        }
+    }
+
+    fn timescale(&self) -> u64 {
+        self.timescale.filter(|t| *t > 0).unwrap_or(1)
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+// BaseURL of `node` resolved against `base`
+fn base_url(node: Node, base: &str) -> String {
+    match child(node, "BaseURL").and_then(|url| url.text()) {
+        Some(url) => resolve_uri(base, url),
+        None => base.to_string(),
+    }
+}
+
+fn is_audio(node: Node) -> bool {
+    node.attribute("contentType") == Some("audio")
+        || node
+            .attribute("mimeType")
+            .is_some_and(|mime| mime.starts_with("audio/"))
+}
+
+/// ISO 8601 durations such as `PT1H2M3.5S` or `P1DT12H`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim().strip_prefix('P')?;
+    let (date, time) = s.split_once('T').unwrap_or((s, ""));
+    let mut seconds = 0.0;
+    for (part, units) in [
+        (date, &[('D', 86400.0)][..]),
+        (time, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)][..]),
+    ] {
+        let mut rest = part;
+        for (unit, scale) in units {
+            if let Some((value, after)) = rest.split_once(*unit) {
+                seconds += value.parse::<f64>().ok()? * scale;
+                rest = after;
+            }
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// `$RepresentationID$`, `$Number$`, `$Time$` and `$Bandwidth$` of a
+/// template, the numbers with an optional `%0<width>d` format.
+fn fill(template: &str, rep: &Representation, number: u64, time: u64) -> String {
+    let mut url = String::new();
+    // identifiers are between every other pair of `$`
+    for (i, part) in template.split('$').enumerate() {
+        if i % 2 == 0 {
+            url.push_str(part);
+            continue;
+        }
+        let (name, format) = part.split_once('%').unwrap_or((part, ""));
+        let value = match name {
+            "" => {
+                url.push('$');
+                continue;
+            }
+            "RepresentationID" => {
+                url.push_str(rep.id.as_str());
+                continue;
+            }
+            "Number" => number,
+            "Time" => time,
+            "Bandwidth" => rep.bandwidth,
+            _ => {
+                url.push_str(format!("${}$", part).as_str());
+                continue;
+            }
+        };
+        let width = format
+            .trim_end_matches('d')
+            .trim_start_matches('0')
+            .parse::<usize>()
+            .unwrap_or(0);
+        url.push_str(format!("{:0width$}", value, width = width).as_str());
+    }
+    url
+}
+
+// the most frequent segment duration, the longest of equally frequent ones
+fn usual_duration(segments: &[Segment]) -> Option<u64> {
+    let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+    for segment in segments {
+        *counts.entry(segment.duration).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(duration, count)| (*count, *duration))
+        .map(|(duration, _)| duration)
+}
+
+impl Manifest {
+    /// Parse the MPD served from `url`, keeping the first audio adaptation
+    /// set of each period (the `main` one if there are several).
+    /// Representations without a SegmentTemplate or with DRM are skipped.
+    pub fn parse(text: &str, url: &str) -> Result<Self, failure::Error> {
+        let doc = Document::parse(text).map_err(|err| format_err!("Bad MPD: {}", err))?;
+        let mpd = doc.root_element();
+        if !mpd.has_tag_name("MPD") {
+            return Err(format_err!("Not an MPD manifest"));
+        }
+        let seconds = |name: &str| mpd.attribute(name).and_then(parse_duration);
+        let presentation = seconds("mediaPresentationDuration");
+        let mpd_base = base_url(mpd, url);
+        let period_nodes: Vec<Node> = mpd
+            .children()
+            .filter(|child| child.has_tag_name("Period"))
+            .collect();
+        let mut periods: Vec<Period> = vec![];
+        for (i, node) in period_nodes.iter().enumerate() {
+            let start = node
+                .attribute("start")
+                .and_then(parse_duration)
+                .or_else(|| {
+                    periods
+                        .last()
+                        .and_then(|prev| Some(prev.start + prev.duration?))
+                })
+                .unwrap_or(Duration::ZERO);
+            let next_start = period_nodes
+                .get(i + 1)
+                .and_then(|next| next.attribute("start"))
+                .and_then(parse_duration);
+            let duration = node
+                .attribute("duration")
+                .and_then(parse_duration)
+                .or_else(|| next_start.map(|next| next.saturating_sub(start)))
+                .or_else(|| presentation.map(|total| total.saturating_sub(start)));
+            periods.push(Period {
+                start,
+                duration,
+                representations: Manifest::audio(*node, mpd_base.as_str()),
+            });
+        }
+        if periods
+            .iter()
+            .all(|period| period.representations.is_empty())
+        {
+            return Err(format_err!("No unencrypted audio with a SegmentTemplate"));
+        }
+        Ok(Self {
+            dynamic: mpd.attribute("type") == Some("dynamic"),
+            availability_start: mpd
+                .attribute("availabilityStartTime")
+                .and_then(parse_date_time),
+            time_shift_buffer_depth: seconds("timeShiftBufferDepth"),
+            periods,
+        })
+    }
+
+    fn audio(period: Node, base: &str) -> Vec<Representation> {
+        let base = base_url(period, base);
+        let template = SegmentTemplate::within(period, &SegmentTemplate::default());
+        let sets: Vec<Node> = period
+            .children()
+            .filter(|set| set.has_tag_name("AdaptationSet"))
+            .filter(|set| child(*set, "ContentProtection").is_none())
+            .filter(|set| {
+                is_audio(*set)
+                    || set
+                        .children()
+                        .any(|rep| rep.has_tag_name("Representation") && is_audio(rep))
+            })
+            .collect();
+        let main = sets.iter().find(|set| {
+            set.children()
+                .any(|role| role.has_tag_name("Role") && role.attribute("value") == Some("main"))
+        });
+        let set = match main.or(sets.first()) {
+            Some(set) => *set,
+            None => return vec![],
+        };
+        let set_base = base_url(set, base.as_str());
+        let set_template = SegmentTemplate::within(set, &template);
+        set.children()
+            .filter(|rep| rep.has_tag_name("Representation"))
+            .filter(|rep| child(*rep, "ContentProtection").is_none())
+            .filter_map(|rep| {
+                let template = SegmentTemplate::within(rep, &set_template);
+                template.media.as_ref()?;
+                Some(Representation {
+                    id: rep.attribute("id").unwrap_or_default().to_string(),
+                    bandwidth: rep
+                        .attribute("bandwidth")
+                        .and_then(|v| v.trim().parse().ok())
+                        .unwrap_or(0),
+                    codecs: rep
+                        .attribute("codecs")
+                        .or_else(|| set.attribute("codecs"))
+                        .map(str::to_string),
+                    base_url: base_url(rep, set_base.as_str()),
+                    template,
+                })
+            })
+            .collect()
+    }
+
+    // periods to play: all of an on demand manifest, the last one live
+    fn playing(&self) -> &[Period] {
+        if self.dynamic {
+            &self.periods[self.periods.len().saturating_sub(1)..]
+        } else {
+            &self.periods
+        }
+    }
+
+    /// The audio representations of the first period played, as variants
+    /// whose uri is `#<id>`.
+    pub fn variants(&self) -> MasterPlaylist {
+        let representations = self
+            .playing()
+            .iter()
+            .map(|period| &period.representations)
+            .find(|reps| !reps.is_empty());
+        MasterPlaylist {
+            variants: representations
+                .into_iter()
+                .flatten()
+                .map(|rep| VariantStream {
+                    uri: format!("#{}", rep.id),
+                    bandwidth: rep.bandwidth.to_string(),
+                    codecs: rep.codecs.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The segments of representation `id` as a media playlist, `now` in
+    /// unix milliseconds. Periods without it play the representation of the
+    /// closest bitrate.
+    /// `numbers` are those of the previous load of a live manifest, updated
+    /// to the segments listed now.
+    pub fn media_playlist(
+        &self,
+        id: &str,
+        now: i64,
+        numbers: &mut TimelineNumbers,
+    ) -> MediaPlaylist {
+        let bandwidth = self
+            .periods
+            .iter()
+            .flat_map(|period| &period.representations)
+            .find(|rep| rep.id == id)
+            .map_or(0, |rep| rep.bandwidth);
+        let mut list = MediaPlaylist {
+            version: 7,
+            end_list: !self.dynamic,
+            ..Default::default()
+        };
+        let mut numbered = TimelineNumbers::default();
+        for period in self.playing() {
+            let rep = period
+                .representations
+                .iter()
+                .find(|rep| rep.id == id)
+                .or_else(|| {
+                    period
+                        .representations
+                        .iter()
+                        .min_by_key(|rep| rep.bandwidth.abs_diff(bandwidth))
+                });
+            let rep = match rep {
+                Some(rep) => rep,
+                None => continue,
+            };
+            let segments = self.segments(period, rep, now, numbers);
+            if rep.template.timeline.is_some() {
+                numbered.numbers.extend(
+                    segments
+                        .iter()
+                        .map(|segment| ((period.start, segment.time), segment.number)),
+                );
+            }
+            if list.segments.is_empty() {
+                let first = segments.first().map_or(0, |segment| segment.number);
+                list.media_sequence = first.min(i32::MAX as u64) as i32;
+            }
+            let timescale = rep.template.timescale() as f64;
+            let offset = rep.template.presentation_time_offset.unwrap_or(0);
+            let init = rep.template.initialization.as_ref().map(|init| Map {
+                uri: resolve_uri(rep.base_url.as_str(), fill(init, rep, 0, 0).as_str()),
+                byte_range: None,
+            });
+            let media = rep.template.media.as_deref().unwrap_or_default();
+            for (i, segment) in segments.iter().enumerate() {
+                let duration = segment.duration as f64 / timescale;
+                list.target_duration = list.target_duration.max(duration.ceil() as f32);
+                let date_time = self.availability_start.map(|start| {
+                    let since = segment.time.saturating_sub(offset) as f64 / timescale;
+                    start + period.start.as_millis() as i64 + (since * 1000.0) as i64
+                });
+                list.segments.push(MediaSegment {
+                    uri: resolve_uri(
+                        rep.base_url.as_str(),
+                        fill(media, rep, segment.number, segment.time).as_str(),
+                    ),
+                    duration: duration as f32,
+                    // a new period may come with new encoder settings
+                    discontinuity: i == 0 && !list.segments.is_empty(),
+                    map: init.clone(),
+                    program_date_time: date_time.map(format_date_time),
+                    ..Default::default()
+                });
+            }
+        }
+        if self.dynamic {
+            *numbers = numbered;
+        }
+        list
+    }
+
+    fn segments(
+        &self,
+        period: &Period,
+        rep: &Representation,
+        now: i64,
+        numbers: &TimelineNumbers,
+    ) -> Vec<Segment> {
+        let template = &rep.template;
+        let timescale = template.timescale();
+        let start_number = template.start_number.unwrap_or(1);
+        let offset = template.presentation_time_offset.unwrap_or(0);
+        // how far into the period segments exist: all of it on demand, up
+        // to now live
+        let available = match (self.dynamic, self.availability_start) {
+            (true, Some(start)) => {
+                let elapsed = now - start - period.start.as_millis() as i64;
+                let elapsed = Duration::from_millis(elapsed.max(0) as u64);
+                Some(period.duration.map_or(elapsed, |d| d.min(elapsed)))
+            }
+            _ => period.duration,
+        };
+        let end = available.map(|end| offset + (end.as_secs_f64() * timescale as f64) as u64);
+        let mut segments = vec![];
+        if let Some(timeline) = &template.timeline {
+            let mut time = 0;
+            for (i, entry) in timeline.iter().enumerate() {
+                time = entry.t.unwrap_or(time);
+                if entry.d == 0 {
+                    continue;
+                }
+                let repeats = match entry.r {
+                    r if r >= 0 => r as u64,
+                    _ => {
+                        let until = timeline.get(i + 1).and_then(|next| next.t).or(end);
+                        until.map_or(0, |until| {
+                            (until.saturating_sub(time) / entry.d).saturating_sub(1)
+                        })
+                    }
+                };
+                for _ in 0..=repeats {
+                    segments.push(Segment {
+                        number: start_number + segments.len() as u64,
+                        time,
+                        duration: entry.d,
+                    });
+                    time += entry.d;
+                }
+            }
+            // the window of a live timeline slides, counted from its first
+            // segment the numbers would restart on every reload. Unless the
+            // urls use them, segments keep the numbers they had by time and
+            // the first load numbers by time in segments of the usual length
+            let media = template.media.as_deref().unwrap_or_default();
+            if self.dynamic && !media.contains("$Number") {
+                let usual = template.duration.filter(|d| *d > 0);
+                if let Some(usual) = usual.or_else(|| usual_duration(&segments)) {
+                    let since = segments[0].time.saturating_sub(offset) as f64;
+                    let first = numbers
+                        .first(period.start, &segments, usual)
+                        .unwrap_or(start_number + (since / usual as f64).round() as u64);
+                    for (i, segment) in segments.iter_mut().enumerate() {
+                        segment.number = first + i as u64;
+                    }
+                }
+            }
+            return segments;
+        }
+        let duration = match template.duration.filter(|d| *d > 0) {
+            Some(duration) => duration,
+            None => return segments,
+        };
+        let (first, count) = match (self.dynamic, end) {
+            // complete segments within the live window
+            (true, Some(end)) => {
+                let count = (end - offset) / duration;
+                let window = self.time_shift_buffer_depth.unwrap_or(LIVE_WINDOW);
+                let window = window.min(LIVE_WINDOW).as_secs_f64() * timescale as f64;
+                let listed = ((window / duration as f64) as u64).max(1);
+                (count.saturating_sub(listed), count.min(listed))
+            }
+            (_, Some(end)) => (0, (end - offset).div_ceil(duration)),
+            (_, None) => (0, 0),
+        };
+        (first..first + count)
+            .map(|i| Segment {
+                number: start_number + i,
+                time: offset + i * duration,
+                duration,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,962 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    cmp::min,
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    time::Duration,
+};
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use bytes::Bytes;
+use m3u8_rs::{ByteRange, Key, MasterPlaylist, MediaPlaylist, VariantStream};
+use reqwest::Url;
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+// RFC 8216 6.3.3: live playback starts at least three target durations
+// before the end of the playlist.
+const LIVE_EDGE_SEGMENTS: usize = 3;
+
+/// How a variant is picked when a station publishes a master playlist.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VariantPolicy {
+    Highest,
+    Lowest,
+    // bits per second
+    Closest(u64),
+}
+
+impl VariantPolicy {
+    /// `highest`, `lowest` or a target bitrate in kbps such as `128`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "highest" => Some(VariantPolicy::Highest),
+            "lowest" => Some(VariantPolicy::Lowest),
+            other => other
+                .trim_end_matches("kbps")
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .map(|kbps| VariantPolicy::Closest(kbps * 1000)),
+        }
+    }
+
+    /// The text `parse` reads back.
+    pub fn setting(&self) -> String {
+        match self {
+            VariantPolicy::Highest => "highest".to_string(),
+            VariantPolicy::Lowest => "lowest".to_string(),
+            VariantPolicy::Closest(bps) => (bps / 1000).to_string(),
+        }
+    }
+}
+
+pub fn bandwidth(variant: &VariantStream) -> u64 {
+    variant.bandwidth.trim().parse::<u64>().unwrap_or(0)
+}
+
+/// Index of the variant to play, i-frame only streams are never picked.
+pub fn select_variant(master: &MasterPlaylist, policy: VariantPolicy) -> Option<usize> {
+    let candidates = master
+        .variants
+        .iter()
+        .enumerate()
+        .filter(|(_, variant)| !variant.is_i_frame);
+    match policy {
+        VariantPolicy::Highest => candidates
+            .max_by_key(|(_, variant)| bandwidth(variant))
+            .map(|(i, _)| i),
+        VariantPolicy::Lowest => candidates
+            .min_by_key(|(_, variant)| bandwidth(variant))
+            .map(|(i, _)| i),
+        VariantPolicy::Closest(target) => candidates
+            .min_by_key(|(_, variant)| bandwidth(variant).abs_diff(target))
+            .map(|(i, _)| i),
+    }
+}
+
+/// Next playable variant after `current`, wrapping around.
+pub fn next_variant(variants: &[VariantStream], current: usize) -> Option<usize> {
+    (1..=variants.len())
+        .map(|step| (current + step) % variants.len())
+        .find(|i| !variants[*i].is_i_frame)
+}
+
+/// e.g. `128 kbps, mp4a.40.2`
+pub fn variant_label(variant: &VariantStream) -> String {
+    let kbps = bandwidth(variant) / 1000;
+    match &variant.codecs {
+        Some(codecs) => format!("{} kbps, {}", kbps, codecs),
+        None => format!("{} kbps", kbps),
+    }
+}
+
+/// Resolve a playlist or segment uri against the url the playlist was
+/// finally served from (RFC 3986 section 5).
+pub fn resolve_uri(playlist_url: &str, uri: &str) -> String {
+    match Url::parse(playlist_url).and_then(|base| base.join(uri.trim())) {
+        Ok(url) => url.to_string(),
+        Err(_) => uri.to_string(),
+    }
+}
+
+/// Whether the playlist's query string (often a signed CDN token) is copied
+/// to the uris found in it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QueryPropagation {
+    // plain RFC 3986 resolution
+    Off,
+    // copy the playlist query to uris without one
+    Missing,
+    // add playlist parameters the uri does not set itself
+    Merge,
+}
+
+impl QueryPropagation {
+    /// `off`, `missing` or `merge`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "none" => Some(QueryPropagation::Off),
+            "missing" => Some(QueryPropagation::Missing),
+            "merge" => Some(QueryPropagation::Merge),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves uris of one playlist.
+#[derive(Clone, Debug)]
+pub struct UriResolver {
+    // final url of the playlist, after redirects
+    pub base: String,
+    pub query: QueryPropagation,
+}
+
+impl UriResolver {
+    pub fn new(base: &str, query: QueryPropagation) -> Self {
+        Self {
+            base: base.to_string(),
+            query,
+        }
+    }
+
+    pub fn resolve(&self, uri: &str) -> String {
+        let resolved = resolve_uri(self.base.as_str(), uri);
+        if self.query == QueryPropagation::Off {
+            return resolved;
+        }
+        let (base, mut url) = match (
+            Url::parse(self.base.as_str()),
+            Url::parse(resolved.as_str()),
+        ) {
+            (Ok(base), Ok(url)) => (base, url),
+            _ => return resolved,
+        };
+        if base.query().is_none() {
+            return resolved;
+        }
+        match (self.query, url.query()) {
+            (QueryPropagation::Missing, None) | (QueryPropagation::Merge, None) => {
+                url.set_query(base.query());
+            }
+            (QueryPropagation::Merge, Some(_)) => {
+                let own: Vec<String> = url.query_pairs().map(|(k, _)| k.to_string()).collect();
+                let missing: Vec<(String, String)> = base
+                    .query_pairs()
+                    .filter(|(k, _)| !own.contains(&k.to_string()))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                url.query_pairs_mut().extend_pairs(missing);
+            }
+            _ => {}
+        }
+        url.to_string()
+    }
+}
+
+/// A media segment to download, with everything needed to fetch it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentRef {
+    pub sequence: u64,
+    pub url: String,
+    pub duration: Duration,
+    // (offset, length) from EXT-X-BYTERANGE
+    pub byte_range: Option<(u64, u64)>,
+    pub encryption: Encryption,
+    pub init: Option<InitSection>,
+    // index of the LL-HLS partial segment, None for a whole segment
+    pub part: Option<usize>,
+    // wall clock start in unix milliseconds, from EXT-X-PROGRAM-DATE-TIME
+    pub date_time: Option<i64>,
+}
+
+/// EXT-X-MAP media initialization section, fMP4/CMAF fragments only decode
+/// with it in front of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitSection {
+    pub url: String,
+    pub byte_range: Option<(u64, u64)>,
+    pub encryption: Encryption,
+}
+
+impl InitSection {
+    fn cache_key(&self) -> String {
+        match self.byte_range {
+            Some((offset, length)) => format!("{}@{}+{}", self.url, offset, length),
+            None => self.url.clone(),
+        }
+    }
+}
+
+/// EXT-X-KEY in effect for a segment.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Encryption {
+    None,
+    // resolved key url and the IV, derived from the media sequence number
+    // when the tag has none
+    Aes128 { key_url: String, iv: [u8; 16] },
+    // SAMPLE-AES and friends, the segment cannot be played
+    Unsupported(String),
+}
+
+impl Encryption {
+    fn from_key(key: &Key, sequence: u64, resolver: &UriResolver) -> Self {
+        match (key.method.trim(), &key.uri) {
+            ("NONE", _) => Encryption::None,
+            ("AES-128", Some(uri)) => {
+                let iv = match &key.iv {
+                    Some(iv) => parse_iv(iv.as_str()),
+                    None => Some((sequence as u128).to_be_bytes()),
+                };
+                match iv {
+                    Some(iv) => Encryption::Aes128 {
+                        key_url: resolver.resolve(uri.as_str()),
+                        iv,
+                    },
+                    None => Encryption::Unsupported(format!("bad IV {:?}", key.iv)),
+                }
+            }
+            (method, _) => Encryption::Unsupported(method.to_string()),
+        }
+    }
+}
+
+/// `0x` prefixed hexadecimal IV of up to 128 bits.
+pub fn parse_iv(s: &str) -> Option<[u8; 16]> {
+    let hex = s.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    u128::from_str_radix(hex, 16)
+        .ok()
+        .map(|iv| iv.to_be_bytes())
+}
+
+/// AES-128-CBC with PKCS7 padding, as used by `METHOD=AES-128`.
+pub fn decrypt_aes128(data: &[u8], key: &[u8], iv: &[u8; 16]) -> Result<Vec<u8>, failure::Error> {
+    let decryptor = Aes128CbcDec::new_from_slices(key, iv)
+        .map_err(|_| failure::format_err!("AES-128 key must be 16 bytes, got {}", key.len()))?;
+    decryptor
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| failure::format_err!("Bad padding, wrong key?"))
+}
+
+/// Keys and init sections by url; stations change them far less often than
+/// segments, so each is downloaded once per station.
+#[derive(Default)]
+pub struct FetchCache {
+    entries: HashMap<String, Bytes>,
+}
+
+impl FetchCache {
+    pub fn get_or_fetch<F>(&mut self, key: &str, fetch: F) -> Result<Bytes, failure::Error>
+    where
+        F: FnOnce() -> Result<Bytes, failure::Error>,
+    {
+        if let Some(data) = self.entries.get(key) {
+            return Ok(data.clone());
+        }
+        let data = fetch()?;
+        self.entries.insert(key.to_string(), data.clone());
+        Ok(data)
+    }
+}
+
+fn decrypt<F>(
+    encryption: &Encryption,
+    data: Bytes,
+    cache: &mut FetchCache,
+    fetch: &mut F,
+) -> Result<Bytes, failure::Error>
+where
+    F: FnMut(&str, Option<(u64, u64)>) -> Result<Bytes, failure::Error>,
+{
+    match encryption {
+        Encryption::None => Ok(data),
+        Encryption::Aes128 { key_url, iv } => {
+            let key = cache.get_or_fetch(key_url.as_str(), || fetch(key_url.as_str(), None))?;
+            Ok(Bytes::from(decrypt_aes128(&data, &key, iv)?))
+        }
+        Encryption::Unsupported(method) => {
+            Err(failure::format_err!("Unsupported encryption {}", method))
+        }
+    }
+}
+
+impl SegmentRef {
+    /// Turn downloaded segment data into something the decoder can open on
+    /// its own: decrypted, with the init section in front. `fetch` downloads
+    /// keys and init sections missing from `cache`.
+    pub fn prepare<F>(
+        &self,
+        data: Bytes,
+        cache: &mut FetchCache,
+        mut fetch: F,
+    ) -> Result<Bytes, failure::Error>
+    where
+        F: FnMut(&str, Option<(u64, u64)>) -> Result<Bytes, failure::Error>,
+    {
+        let data = decrypt(&self.encryption, data, cache, &mut fetch)?;
+        let init = match &self.init {
+            Some(init) => init,
+            None => return Ok(data),
+        };
+        let init_data = cache.get_or_fetch(init.cache_key().as_str(), || {
+            fetch(init.url.as_str(), init.byte_range)
+        })?;
+        // the init section is cached encrypted, the key is cached as well
+        let init_data = decrypt(&init.encryption, init_data, cache, &mut fetch)?;
+        let mut whole = Vec::with_capacity(init_data.len() + data.len());
+        whole.extend_from_slice(&init_data);
+        whole.extend_from_slice(&data);
+        Ok(Bytes::from(whole))
+    }
+}
+
+/// Segments of `list` from media sequence number `next` on. Without `next`
+/// playback starts near the live edge, or at the beginning of a VOD
+/// playlist. If the playlist no longer reaches back to `next` it continues
+/// from its oldest segment, if it was restarted with smaller numbers from
+/// the live edge.
+pub fn new_segments(
+    list: &MediaPlaylist,
+    resolver: &UriResolver,
+    next: Option<u64>,
+) -> Vec<SegmentRef> {
+    let first = list.media_sequence.max(0) as u64;
+    let count = list.segments.len() as u64;
+    let live_start = count.saturating_sub(LIVE_EDGE_SEGMENTS as u64);
+    let skip = match next {
+        None if list.end_list => 0,
+        None => live_start,
+        Some(next) if next < first => 0,
+        Some(next) if next > first + count => live_start,
+        Some(next) => next - first,
+    };
+    segment_refs(list, resolver)
+        .into_iter()
+        .skip(skip as usize)
+        .collect()
+}
+
+/// Every segment of the playlist with byte ranges made absolute.
+pub fn segment_refs(list: &MediaPlaylist, resolver: &UriResolver) -> Vec<SegmentRef> {
+    let first = list.media_sequence.max(0) as u64;
+    // a range without offset continues where the previous one of the same
+    // resource ended
+    let mut range_ends: HashMap<String, u64> = HashMap::new();
+    // m3u8-rs only attaches EXT-X-KEY to the segment following the tag
+    let mut key: Option<&Key> = None;
+    // and EXT-X-MAP as well
+    let mut init: Option<InitSection> = None;
+    // EXT-X-PROGRAM-DATE-TIME continues over the following segments
+    let mut clock: Option<i64> = None;
+    list.segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let sequence = first + i as u64;
+            let date_time = segment
+                .program_date_time
+                .as_deref()
+                .and_then(parse_date_time)
+                .or(clock);
+            let duration = Duration::from_secs_f32(segment.duration.max(0.0));
+            clock = date_time.map(|start| start + duration.as_millis() as i64);
+            if segment.key.is_some() {
+                key = segment.key.as_ref();
+            }
+            if let Some(map) = &segment.map {
+                init = Some(InitSection {
+                    url: resolver.resolve(map.uri.as_str()),
+                    byte_range: map.byte_range.as_ref().map(map_range),
+                    encryption: key.map_or(Encryption::None, |key| {
+                        Encryption::from_key(key, sequence, resolver)
+                    }),
+                });
+            }
+            let url = resolver.resolve(segment.uri.as_str());
+            let byte_range = segment.byte_range.as_ref().map(|range| {
+                let length = range.length.max(0) as u64;
+                let offset = match range.offset {
+                    Some(offset) => offset.max(0) as u64,
+                    None => range_ends.get(&url).copied().unwrap_or(0),
+                };
+                range_ends.insert(url.clone(), offset + length);
+                (offset, length)
+            });
+            SegmentRef {
+                sequence,
+                url,
+                duration,
+                byte_range,
+                encryption: key.map_or(Encryption::None, |key| {
+                    Encryption::from_key(key, sequence, resolver)
+                }),
+                init: init.clone(),
+                part: None,
+                date_time,
+            }
+        })
+        .collect()
+}
+
+/// Unix milliseconds of an ISO 8601 date-time such as
+/// `2024-05-01T20:15:03.250+02:00`.
+pub fn parse_date_time(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-').map(|v| v.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    // the zone starts at Z, + or the second -
+    let zone_at = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(zone_at);
+    let mut clock = clock.splitn(3, ':');
+    let hour: i64 = clock.next()?.parse().ok()?;
+    let minute: i64 = clock.next()?.parse().ok()?;
+    let seconds: f64 = clock.next().unwrap_or("0").parse().ok()?;
+    let offset_minutes = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let zone = zone[1..].replace(':', "");
+            let hours: i64 = zone.get(..2)?.parse().ok()?;
+            let minutes: i64 = zone.get(2..4).unwrap_or("0").parse().ok()?;
+            let offset = hours * 60 + minutes;
+            if sign == '-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => 0,
+    };
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + hour * 3600 + minute * 60 - offset_minutes * 60;
+    Some(secs * 1000 + (seconds * 1000.0).round() as i64)
+}
+
+/// Unix milliseconds as `2024-05-01T18:15:03.250Z`, what `parse_date_time`
+/// reads back.
+pub fn format_date_time(millis: i64) -> String {
+    let days = millis.div_euclid(86_400_000);
+    let ms = millis.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date, Howard Hinnant's
+/// algorithm.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `(year, month, day)` of days since 1970-01-01, the inverse of
+/// `days_from_civil`.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// EXT-X-MAP byte ranges start at 0 without an offset
+fn map_range(range: &ByteRange) -> (u64, u64) {
+    (
+        range.offset.unwrap_or(0).max(0) as u64,
+        range.length.max(0) as u64,
+    )
+}
+
+pub fn playlist_duration(list: &MediaPlaylist) -> Duration {
+    list.segments
+        .iter()
+        .map(|segment| Duration::from_secs_f32(segment.duration.max(0.0)))
+        .sum()
+}
+
+/// When to reload a live playlist (RFC 8216 6.3.4): one target duration
+/// after a change, half of it after an unchanged reload, backing off up to
+/// three target durations while the playlist stays the same.
+pub struct ReloadScheduler {
+    target: Duration,
+    unchanged: u32,
+    last_sequence: Option<(u64, usize)>,
+}
+
+impl Default for ReloadScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReloadScheduler {
+    pub fn new() -> Self {
+        Self {
+            target: Duration::from_secs(5),
+            unchanged: 0,
+            last_sequence: None,
+        }
+    }
+
+    /// Record a freshly loaded playlist, returns the delay to the next reload.
+    pub fn loaded(&mut self, list: &MediaPlaylist) -> Duration {
+        if list.target_duration > 0.0 {
+            self.target = Duration::from_secs_f32(list.target_duration);
+        }
+        let sequence = (list.media_sequence.max(0) as u64, list.segments.len());
+        if self.last_sequence == Some(sequence) {
+            self.unchanged += 1;
+        } else {
+            self.unchanged = 0;
+        }
+        self.last_sequence = Some(sequence);
+        self.delay()
+    }
+
+    pub fn delay(&self) -> Duration {
+        match self.unchanged {
+            0 => self.target,
+            n => min(self.target / 2 * 2u32.pow(min(n, 8) - 1), self.target * 3),
+        }
+    }
+}
+
+/// How close to the live edge live streams are played.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LatencyMode {
+    // whole segments, three target durations behind live
+    Stable,
+    // LL-HLS partial segments and blocking reloads when the server has them
+    Low,
+}
+
+impl LatencyMode {
+    /// `stable` or `low`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "stable" => Some(LatencyMode::Stable),
+            "low" => Some(LatencyMode::Low),
+            _ => None,
+        }
+    }
+}
+
+/// Media sequence number and part index of the next chunk to download.
+pub type PartCursor = (u64, usize);
+
+/// An EXT-X-PART, or the part announced by EXT-X-PRELOAD-HINT.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part {
+    pub sequence: u64,
+    pub index: usize,
+    pub uri: String,
+    pub duration: Duration,
+    pub byte_range: Option<(u64, u64)>,
+    pub independent: bool,
+}
+
+/// LL-HLS tags of a media playlist. m3u8-rs does not know them and drops
+/// the ones after the last segment, so they are read from the raw text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LowLatency {
+    pub can_block_reload: bool,
+    pub part_hold_back: Option<Duration>,
+    pub part_target: Option<Duration>,
+    // in playlist order, the last ones may belong to a segment still being
+    // written
+    pub parts: Vec<Part>,
+    pub preload_hint: Option<Part>,
+}
+
+/// `KEY=value,KEY="quoted, value"` attribute lists.
+fn attributes(list: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = list.trim();
+    while let Some((key, tail)) = rest.split_once('=') {
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, tail)) => (value, tail),
+                None => (quoted, ""),
+            },
+            None => tail.split_once(',').map_or((tail, ""), |(v, t)| (v, t)),
+        };
+        attributes.insert(key.trim().to_string(), value.trim().to_string());
+        rest = tail.trim_start_matches(',').trim_start();
+    }
+    attributes
+}
+
+fn seconds(value: Option<&String>) -> Option<Duration> {
+    value
+        .and_then(|v| v.parse::<f32>().ok())
+        .filter(|v| *v >= 0.0)
+        .map(Duration::from_secs_f32)
+}
+
+impl LowLatency {
+    pub fn parse(text: &str) -> Self {
+        let mut ll = LowLatency::default();
+        let mut sequence = 0u64;
+        let mut index = 0usize;
+        let mut range_ends: HashMap<String, u64> = HashMap::new();
+        for line in text.lines().map(str::trim) {
+            if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                sequence = value.trim().parse().unwrap_or(0);
+            } else if let Some(value) = line.strip_prefix("#EXT-X-SERVER-CONTROL:") {
+                let attrs = attributes(value);
+                ll.can_block_reload = attrs.get("CAN-BLOCK-RELOAD").is_some_and(|v| v == "YES");
+                ll.part_hold_back = seconds(attrs.get("PART-HOLD-BACK"));
+            } else if let Some(value) = line.strip_prefix("#EXT-X-PART-INF:") {
+                ll.part_target = seconds(attributes(value).get("PART-TARGET"));
+            } else if let Some(value) = line.strip_prefix("#EXT-X-PART:") {
+                let attrs = attributes(value);
+                let uri = match attrs.get("URI") {
+                    Some(uri) => uri.clone(),
+                    None => continue,
+                };
+                let byte_range = attrs.get("BYTERANGE").map(|range| {
+                    let (length, offset) = match range.split_once('@') {
+                        Some((length, offset)) => (length, offset.parse().ok()),
+                        None => (range.as_str(), None),
+                    };
+                    let length = length.parse().unwrap_or(0);
+                    let offset =
+                        offset.unwrap_or_else(|| range_ends.get(&uri).copied().unwrap_or(0));
+                    range_ends.insert(uri.clone(), offset + length);
+                    (offset, length)
+                });
+                ll.parts.push(Part {
+                    sequence,
+                    index,
+                    uri,
+                    duration: seconds(attrs.get("DURATION")).unwrap_or_default(),
+                    byte_range,
+                    independent: index == 0 || attrs.get("INDEPENDENT").is_some_and(|v| v == "YES"),
+                });
+                index += 1;
+            } else if let Some(value) = line.strip_prefix("#EXT-X-PRELOAD-HINT:") {
+                let attrs = attributes(value);
+                // open ended byte range hints are not worth the trouble
+                if attrs.get("TYPE").is_some_and(|v| v == "PART")
+                    && !attrs.contains_key("BYTERANGE-START")
+                {
+                    ll.preload_hint = attrs.get("URI").map(|uri| Part {
+                        sequence,
+                        index,
+                        uri: uri.clone(),
+                        duration: ll.part_target.unwrap_or_default(),
+                        byte_range: None,
+                        independent: index == 0,
+                    });
+                }
+            } else if !line.is_empty() && !line.starts_with('#') {
+                sequence += 1;
+                index = 0;
+            }
+        }
+        ll
+    }
+
+    pub fn has_parts(&self) -> bool {
+        !self.parts.is_empty()
+    }
+
+    /// How long a blocking reload may take: servers hold the request for up
+    /// to three target durations, then the part still has to arrive.
+    pub fn blocking_timeout(&self, target_duration: Duration) -> Duration {
+        target_duration * 3 + self.part_target.unwrap_or(target_duration)
+    }
+
+    // RFC 8216bis: at least two, three recommended part target durations
+    fn hold_back(&self) -> Duration {
+        self.part_hold_back
+            .unwrap_or_else(|| self.part_target.unwrap_or_default() * 3)
+    }
+}
+
+fn part_ref(template: Option<&SegmentRef>, part: &Part, resolver: &UriResolver) -> SegmentRef {
+    SegmentRef {
+        sequence: part.sequence,
+        url: resolver.resolve(part.uri.as_str()),
+        duration: part.duration,
+        byte_range: part.byte_range,
+        encryption: template.map_or(Encryption::None, |t| t.encryption.clone()),
+        init: template.and_then(|t| t.init.clone()),
+        part: Some(part.index),
+        // set by `low_latency_segments` from the chunk before
+        date_time: template
+            .filter(|t| part.index == 0 && t.sequence == part.sequence)
+            .and_then(|t| t.date_time),
+    }
+}
+
+fn cursor_of(segment: &SegmentRef) -> PartCursor {
+    (segment.sequence, segment.part.unwrap_or(0))
+}
+
+/// Like `new_segments`, but with the partial segments of an LL-HLS playlist
+/// where it lists them. Without `next` playback starts PART-HOLD-BACK before
+/// the live edge, on an independent part.
+pub fn low_latency_segments(
+    list: &MediaPlaylist,
+    ll: &LowLatency,
+    resolver: &UriResolver,
+    next: Option<PartCursor>,
+) -> Vec<SegmentRef> {
+    if !ll.has_parts() {
+        return new_segments(list, resolver, next.map(|(sequence, _)| sequence));
+    }
+    let segments = segment_refs(list, resolver);
+    let mut chunks: Vec<(SegmentRef, bool)> = vec![];
+    for segment in &segments {
+        let parts: Vec<&Part> = ll
+            .parts
+            .iter()
+            .filter(|part| part.sequence == segment.sequence)
+            .collect();
+        if parts.is_empty() {
+            chunks.push((segment.clone(), true));
+        }
+        for part in parts {
+            chunks.push((part_ref(Some(segment), part, resolver), part.independent));
+        }
+    }
+    // parts of the segment being written, keys and maps carry over
+    let end = list.media_sequence.max(0) as u64 + segments.len() as u64;
+    for part in ll.parts.iter().filter(|part| part.sequence >= end) {
+        chunks.push((part_ref(segments.last(), part, resolver), part.independent));
+    }
+    let listed = chunks.len();
+    if let Some(hint) = &ll.preload_hint {
+        chunks.push((part_ref(segments.last(), hint, resolver), hint.independent));
+    }
+    // parts start where the chunk before them ended
+    let mut clock = None;
+    for (chunk, _) in chunks.iter_mut() {
+        if chunk.date_time.is_none() {
+            chunk.date_time = clock;
+        }
+        clock = chunk
+            .date_time
+            .map(|start| start + chunk.duration.as_millis() as i64);
+    }
+
+    let mut live_start = listed;
+    let mut held = Duration::ZERO;
+    while live_start > 0 && held < ll.hold_back() {
+        live_start -= 1;
+        held += chunks[live_start].0.duration;
+    }
+    while live_start > 0 && !chunks[live_start].1 {
+        live_start -= 1;
+    }
+    let start = match next {
+        None => live_start,
+        // an older cursor than the first chunk continues from the oldest one
+        Some(next) => match chunks
+            .iter()
+            .position(|(chunk, _)| cursor_of(chunk) >= next)
+        {
+            Some(i) => i,
+            // nothing that new: all caught up, or the playlist was restarted
+            None => match chunks.last() {
+                Some((last, _)) if next <= next_cursor(list, ll, last) => chunks.len(),
+                _ => live_start,
+            },
+        },
+    };
+    chunks
+        .into_iter()
+        .skip(start)
+        .map(|(chunk, _)| chunk)
+        .collect()
+}
+
+/// Cursor following `last`, moving on to the next segment after the last
+/// part of a complete one.
+pub fn next_cursor(list: &MediaPlaylist, ll: &LowLatency, last: &SegmentRef) -> PartCursor {
+    let end = list.media_sequence.max(0) as u64 + list.segments.len() as u64;
+    match last.part {
+        None => (last.sequence + 1, 0),
+        Some(index) => {
+            let last_part = ll
+                .parts
+                .iter()
+                .filter(|part| part.sequence == last.sequence)
+                .map(|part| part.index)
+                .max();
+            if last.sequence < end && last_part.is_none_or(|last_part| index >= last_part) {
+                (last.sequence + 1, 0)
+            } else {
+                (last.sequence, index + 1)
+            }
+        }
+    }
+}
+
+/// Playlist url asking the server to hold the response until the part at
+/// `cursor` is available (EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES).
+pub fn blocking_reload_url(url: &str, cursor: PartCursor) -> String {
+    let mut url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return url.to_string(),
+    };
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !k.starts_with("_HLS_"))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("_HLS_msn", cursor.0.to_string().as_str())
+        .append_pair("_HLS_part", cursor.1.to_string().as_str());
+    url.to_string()
+}
+
+/// Segments waiting for download, in media sequence order and without the
+/// ones already queued by an earlier reload of the playlist.
+#[derive(Default)]
+pub struct SegmentQueue {
+    pending: VecDeque<SegmentRef>,
+    last: Option<PartCursor>,
+}
+
+impl SegmentQueue {
+    /// Queue segments of a playlist reload whose media sequence numbers span
+    /// `window`. Queued segments that dropped out of the window can no longer
+    /// be downloaded and are skipped, returns how many.
+    pub fn push(&mut self, segments: Vec<SegmentRef>, window: Range<u64>) -> usize {
+        // the playlist was restarted with smaller numbers
+        if self.last.is_some_and(|(sequence, _)| sequence > window.end) {
+            self.last = None;
+        }
+        let before = self.pending.len();
+        self.pending
+            .retain(|segment| segment.sequence >= window.start);
+        let skipped = before - self.pending.len();
+        for segment in segments {
+            let cursor = cursor_of(&segment);
+            if self.last.is_none_or(|last| cursor > last) {
+                self.last = Some(cursor);
+                self.pending.push_back(segment);
+            }
+        }
+        skipped
+    }
+
+    pub fn pop(&mut self) -> Option<SegmentRef> {
+        self.pending.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Wait before retrying a failed download, doubling from 250 ms up to 4 s.
+pub fn retry_delay(attempt: u32) -> Duration {
+    min(
+        Duration::from_millis(250) * 2u32.pow(min(attempt, 8)),
+        Duration::from_secs(4),
+    )
+}
+
+/// Wait before reconnecting to a station that stopped answering, doubling
+/// from 1 s up to 30 s.
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    min(
+        Duration::from_secs(1) * 2u32.pow(min(attempt, 8)),
+        Duration::from_secs(30),
+    )
+}
+
+// reconnects in a row before giving up
+pub const MAX_RECONNECTS: u32 = 10;
+
+/// Reconnects of a station in a row, until audio arrives again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reconnects {
+    attempts: u32,
+}
+
+impl Reconnects {
+    /// The connection was lost: the attempt to make and how long to wait
+    /// before it, None once `MAX_RECONNECTS` failed in a row.
+    pub fn lost(&mut self) -> Option<(u32, Duration)> {
+        if self.attempts >= MAX_RECONNECTS {
+            return None;
+        }
+        let delay = reconnect_delay(self.attempts);
+        self.attempts += 1;
+        Some((self.attempts, delay))
+    }
+
+    /// Audio arrived, true if that ends reconnecting.
+    pub fn connected(&mut self) -> bool {
+        let reconnected = self.attempts > 0;
+        self.attempts = 0;
+        reconnected
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Read;
+use std::sync::mpsc::Sender;
+
+/// Request header asking Icecast/Shoutcast servers to interleave metadata.
+pub const ICY_METADATA: &str = "Icy-MetaData";
+/// Response header with the number of audio bytes between metadata blocks.
+pub const ICY_METAINT: &str = "icy-metaint";
+
+/// `StreamTitle` of an ICY metadata block such as
+/// `StreamTitle='Artist - Title';StreamUrl='';`
+pub fn parse_stream_title(meta: &[u8]) -> Option<String> {
+    // servers send UTF-8 or Latin-1
+    let text = match std::str::from_utf8(meta) {
+        Ok(text) => text.to_string(),
+        Err(_) => meta.iter().map(|b| *b as char).collect(),
+    };
+    let text = text.trim_end_matches('\0');
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    // titles may contain quotes, the field ends with `';`
+    let end = rest
+        .find("';")
+        .unwrap_or_else(|| rest.trim_end_matches('\'').len());
+    let title = rest[..end].trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+/// Strips the metadata blocks from an ICY stream, sending each new stream
+/// title to `titles` as it passes by.
+pub struct IcyReader<R> {
+    inner: R,
+    // audio bytes between metadata blocks, None if the server sends none
+    metaint: Option<usize>,
+    until_meta: usize,
+    titles: Sender<String>,
+    title: Option<String>,
+}
+
+impl<R: Read> IcyReader<R> {
+    pub fn new(inner: R, metaint: Option<usize>, titles: Sender<String>) -> Self {
+        let metaint = metaint.filter(|metaint| *metaint > 0);
+        Self {
+            inner,
+            metaint,
+            until_meta: metaint.unwrap_or(0),
+            titles,
+            title: None,
+        }
+    }
+
+    fn read_metadata(&mut self) -> std::io::Result<()> {
+        let mut length = [0u8; 1];
+        self.inner.read_exact(&mut length)?;
+        let mut meta = vec![0u8; length[0] as usize * 16];
+        self.inner.read_exact(&mut meta)?;
+        if let Some(title) = parse_stream_title(&meta) {
+            if self.title.as_ref() != Some(&title) {
+                self.title = Some(title.clone());
+                let _ = self.titles.send(title);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let metaint = match self.metaint {
+            Some(metaint) => metaint,
+            None => return self.inner.read(buf),
+        };
+        if self.until_meta == 0 {
+            self.read_metadata()?;
+            self.until_meta = metaint;
+        }
+        let len = buf.len().min(self.until_meta);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.until_meta -= read;
+        Ok(read)
+    }
+}
@@ -0,0 +1,313 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, time::Duration};
+
+const TS_PACKET: usize = 188;
+// PMT stream_type of ID3 timed metadata (ISO/IEC 13818-1 metadata in PES)
+const STREAM_TYPE_ID3: u8 = 0x15;
+// PRIV owner carrying the 33 bit MPEG-TS timestamp of packed audio segments
+const TRANSPORT_STREAM_TIMESTAMP: &str = "com.apple.streaming.transportStreamTimestamp";
+const PTS_HZ: u64 = 90_000;
+
+/// Frames of an ID3v2 tag the player has a use for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Id3Tag {
+    // TIT2
+    pub title: Option<String>,
+    // TPE1
+    pub artist: Option<String>,
+    // TALB, or the TIT1 content group
+    pub programme: Option<String>,
+    // PRIV owner and data
+    pub private: Vec<(String, Vec<u8>)>,
+}
+
+impl Id3Tag {
+    /// 90kHz timestamp of the segment start in packed audio.
+    pub fn transport_timestamp(&self) -> Option<u64> {
+        let (_, data) = self
+            .private
+            .iter()
+            .find(|(owner, _)| owner == TRANSPORT_STREAM_TIMESTAMP)?;
+        let bytes: [u8; 8] = data.get(..8)?.try_into().ok()?;
+        Some(u64::from_be_bytes(bytes) & 0x1_ffff_ffff)
+    }
+}
+
+/// A tag and how far into its segment it applies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedId3 {
+    pub offset: Duration,
+    pub tag: Id3Tag,
+}
+
+/// Timed ID3 tags of a media segment: the tag in front of packed audio
+/// (AAC/MP3) or the ID3 PES stream of an MPEG-TS segment.
+pub fn segment_id3(data: &[u8]) -> Vec<TimedId3> {
+    if data.starts_with(b"ID3") {
+        return parse_id3(data)
+            .map(|(tag, _)| {
+                vec![TimedId3 {
+                    offset: Duration::ZERO,
+                    tag,
+                }]
+            })
+            .unwrap_or_default();
+    }
+    if data.first() == Some(&0x47) {
+        return ts_id3(data);
+    }
+    vec![]
+}
+
+/// The ID3v2.3/2.4 tag at the start of `data` and its length.
+pub fn parse_id3(data: &[u8]) -> Option<(Id3Tag, usize)> {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return None;
+    }
+    let version = data[3];
+    let flags = data[5];
+    let size = syncsafe(&data[6..10]) as usize;
+    let end = (10 + size).min(data.len());
+    let mut pos = 10;
+    // extended header
+    if flags & 0x40 != 0 && data.len() >= 14 {
+        let ext = match version {
+            4 => syncsafe(&data[10..14]) as usize,
+            _ => u32::from_be_bytes(data[10..14].try_into().ok()?) as usize + 4,
+        };
+        pos += ext;
+    }
+    let mut tag = Id3Tag::default();
+    let mut group = None;
+    while pos + 10 <= end {
+        let id = &data[pos..pos + 4];
+        // padding
+        if id[0] == 0 {
+            break;
+        }
+        let size = match version {
+            4 => syncsafe(&data[pos + 4..pos + 8]),
+            _ => u32::from_be_bytes(data[pos + 4..pos + 8].try_into().ok()?),
+        } as usize;
+        let body_start = pos + 10;
+        let body_end = (body_start + size).min(end);
+        let body = &data[body_start..body_end];
+        match id {
+            b"TIT2" => tag.title = text_frame(body),
+            b"TPE1" => tag.artist = text_frame(body),
+            b"TALB" => tag.programme = text_frame(body),
+            b"TIT1" => group = text_frame(body),
+            b"PRIV" => {
+                let nul = body.iter().position(|b| *b == 0).unwrap_or(body.len());
+                let owner: String = body[..nul].iter().map(|b| *b as char).collect();
+                let data = body.get(nul + 1..).unwrap_or_default().to_vec();
+                tag.private.push((owner, data));
+            }
+            _ => {}
+        }
+        pos = body_end;
+    }
+    if tag.programme.is_none() {
+        tag.programme = group;
+    }
+    Some((tag, end))
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |size, b| (size << 7) | (*b & 0x7f) as u32)
+}
+
+fn text_frame(body: &[u8]) -> Option<String> {
+    let (encoding, text) = body.split_first()?;
+    let text = match encoding {
+        // UTF-16 with BOM, or big endian without
+        1 | 2 => {
+            let mut units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            match units.first() {
+                Some(0xfeff) => {
+                    units.remove(0);
+                }
+                Some(0xfffe) => {
+                    units.remove(0);
+                    units = units.iter().map(|unit| unit.swap_bytes()).collect();
+                }
+                _ => {}
+            }
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).to_string(),
+        _ => text.iter().map(|b| *b as char).collect(),
+    };
+    // several values are separated by NUL, the first one is enough
+    let text = text
+        .split('\0')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// ID3 tags of the metadata stream of an MPEG-TS segment, timed against
+/// the first audio PES.
+fn ts_id3(data: &[u8]) -> Vec<TimedId3> {
+    let mut pmt_pids: Vec<u16> = vec![];
+    let mut id3_pids: Vec<u16> = vec![];
+    // PES being assembled per ID3 pid
+    let mut pes: HashMap<u16, Vec<u8>> = HashMap::new();
+    let mut finished: Vec<Vec<u8>> = vec![];
+    let mut first_pts: Option<u64> = None;
+    for packet in data.chunks_exact(TS_PACKET) {
+        if packet[0] != 0x47 {
+            continue;
+        }
+        let start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1f, packet[2]]);
+        let adaptation = (packet[3] >> 4) & 0x3;
+        let mut offset = 4;
+        if adaptation & 0x2 != 0 {
+            offset += 1 + packet[4] as usize;
+        }
+        if adaptation & 0x1 == 0 || offset >= TS_PACKET {
+            continue;
+        }
+        let payload = &packet[offset..];
+        if pid == 0 && start {
+            pmt_pids = psi_section(payload)
+                .map(|section| {
+                    section
+                        .chunks_exact(4)
+                        .filter(|entry| u16::from_be_bytes([entry[0], entry[1]]) != 0)
+                        .map(|entry| u16::from_be_bytes([entry[2] & 0x1f, entry[3]]))
+                        .collect()
+                })
+                .unwrap_or_default();
+        } else if pmt_pids.contains(&pid) && start {
+            if let Some(section) = psi_section(payload) {
+                id3_pids = pmt_streams(section)
+                    .into_iter()
+                    .filter(|(stream_type, _)| *stream_type == STREAM_TYPE_ID3)
+                    .map(|(_, pid)| pid)
+                    .collect();
+            }
+        } else if id3_pids.contains(&pid) {
+            if start {
+                if let Some(done) = pes.remove(&pid) {
+                    finished.push(done);
+                }
+            }
+            if start || pes.contains_key(&pid) {
+                pes.entry(pid).or_default().extend_from_slice(payload);
+            }
+        } else if start && first_pts.is_none() && !pmt_pids.is_empty() {
+            first_pts = pes_header(payload).and_then(|(pts, _)| pts);
+        }
+    }
+    finished.extend(pes.into_values());
+    let mut tags = vec![];
+    for packet in finished {
+        let (pts, header) = match pes_header(&packet) {
+            Some(header) => header,
+            None => continue,
+        };
+        let mut rest = &packet[header..];
+        while let Some((tag, len)) = parse_id3(rest) {
+            let pts = pts.or_else(|| tag.transport_timestamp());
+            let offset = match (pts, first_pts) {
+                // 33 bit timestamps wrap around
+                (Some(pts), Some(first)) => {
+                    let ticks = pts.wrapping_sub(first) & 0x1_ffff_ffff;
+                    if ticks < 0x1_0000_0000 {
+                        Duration::from_micros(ticks * 1_000_000 / PTS_HZ)
+                    } else {
+                        Duration::ZERO
+                    }
+                }
+                _ => Duration::ZERO,
+            };
+            tags.push(TimedId3 { offset, tag });
+            rest = &rest[len.min(rest.len())..];
+        }
+    }
+    tags.sort_by_key(|timed| timed.offset);
+    tags
+}
+
+/// Body of the PSI section starting in `payload`, between the section
+/// header and the CRC.
+fn psi_section(payload: &[u8]) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    if section.len() < 8 {
+        return None;
+    }
+    let length = (u16::from_be_bytes([section[1] & 0x0f, section[2]])) as usize;
+    // 5 bytes of header after the length, 4 bytes of CRC
+    let end = (3 + length).min(section.len());
+    section.get(8..end.saturating_sub(4))
+}
+
+/// (stream_type, pid) entries of a PMT section body.
+fn pmt_streams(section: &[u8]) -> Vec<(u8, u16)> {
+    let mut streams = vec![];
+    if section.len() < 4 {
+        return streams;
+    }
+    let info_len = u16::from_be_bytes([section[2] & 0x0f, section[3]]) as usize;
+    let mut pos = 4 + info_len;
+    while pos + 5 <= section.len() {
+        let stream_type = section[pos];
+        let pid = u16::from_be_bytes([section[pos + 1] & 0x1f, section[pos + 2]]);
+        let es_info = u16::from_be_bytes([section[pos + 3] & 0x0f, section[pos + 4]]) as usize;
+        streams.push((stream_type, pid));
+        pos += 5 + es_info;
+    }
+    streams
+}
+
+/// PTS and header length of a PES packet.
+fn pes_header(pes: &[u8]) -> Option<(Option<u64>, usize)> {
+    if pes.len() < 9 || pes[..3] != [0, 0, 1] {
+        return None;
+    }
+    let header = 9 + pes[8] as usize;
+    let pts = if pes[7] & 0x80 != 0 && pes.len() >= 14 {
+        let p = &pes[9..14];
+        Some(
+            ((p[0] as u64 >> 1) & 0x07) << 30
+                | (p[1] as u64) << 22
+                | ((p[2] as u64) >> 1) << 15
+                | (p[3] as u64) << 7
+                | (p[4] as u64) >> 1,
+        )
+    } else {
+        None
+    };
+    Some((pts, header.min(pes.len())))
+}
@@ -15,12 +15,26 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{fmt::Display, fs::File, io::Read, path::PathBuf, time::Duration, vec};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{read_dir, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+    vec,
+};
 
+use lofty::prelude::*;
 use regex::Regex;
 
+// in order of preference
+const LYRICS_EXTENSIONS: [&str; 4] = ["lrc", "srt", "vtt", "txt"];
+
 pub struct Lyrics {
     pub list: Vec<Lyric>,
+    // false for plain text lyrics without timestamps
+    pub synced: bool,
 }
 
 impl Display for Lyrics {
@@ -34,41 +48,242 @@ impl Display for Lyrics {
 
 pub struct Lyric {
     pub time: Duration,
+    // end of a subtitle cue, lrc lines last until the next one
+    pub end: Option<Duration>,
     pub content: String,
+    // translated line sharing the same timestamp, if any
+    pub translation: Option<String>,
+}
+
+/// Which part of a bilingual lyric is shown in the lyrics panel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LyricsMode {
+    Both,
+    Original,
+    Translation,
+}
+
+impl LyricsMode {
+    pub fn next(self) -> Self {
+        match self {
+            LyricsMode::Both => LyricsMode::Original,
+            LyricsMode::Original => LyricsMode::Translation,
+            LyricsMode::Translation => LyricsMode::Both,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LyricsMode::Both => "Both",
+            LyricsMode::Original => "Original",
+            LyricsMode::Translation => "Translation",
+        }
+    }
+}
+
+impl Lyric {
+    /// Whether the line should be sung at `at`. Subtitle cues end on their
+    /// own, leaving a gap until the next cue.
+    pub fn is_active(&self, at: Duration) -> bool {
+        at >= self.time && self.end.is_none_or(|end| at < end)
+    }
+
+    /// Lines to render for the given mode, falling back to the original
+    /// when a translation is requested but missing.
+    pub fn lines(&self, mode: LyricsMode) -> (Option<&str>, Option<&str>) {
+        let translation = self.translation.as_deref();
+        match mode {
+            LyricsMode::Both => (Some(self.content.as_str()), translation),
+            LyricsMode::Original => (Some(self.content.as_str()), None),
+            LyricsMode::Translation => match translation {
+                Some(t) => (None, Some(t)),
+                None => (Some(self.content.as_str()), None),
+            },
+        }
+    }
 }
 
 impl Lyrics {
+    #[allow(dead_code)]
     pub fn from_music_path(s: &str) -> Self {
-        // change to *.lrc
+        // try *.lrc, then subtitle files, then plain text
         let mut p = PathBuf::from(s);
-        p.set_extension("lrc");
-        let f = File::open(p);
-        match f {
-            Ok(mut f) => {
-                return Lyrics::from_read(&mut f);
+        for ext in LYRICS_EXTENSIONS {
+            p.set_extension(ext);
+            if let Some(lyrics) = Lyrics::from_path(&p) {
+                return lyrics;
             }
-            Err(_) => return Self { list: vec![] },
+        }
+        Lyrics::empty()
+    }
+
+    /// Parse a lyrics file according to its extension, `None` if it cannot
+    /// be opened or contains no lines.
+    pub fn from_path(p: &Path) -> Option<Self> {
+        let mut f = File::open(p).ok()?;
+        let ext = p.extension()?.to_string_lossy().to_lowercase();
+        let lyrics = match ext.as_str() {
+            "lrc" => Lyrics::from_read(&mut f),
+            "srt" => Lyrics::from_srt(read_text(&mut f).as_str()),
+            "vtt" => Lyrics::from_vtt(read_text(&mut f).as_str()),
+            "txt" => Lyrics::from_plain(read_text(&mut f).as_str()),
+            _ => return None,
+        };
+        if lyrics.list.is_empty() {
+            None
+        } else {
+            Some(lyrics)
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            list: vec![],
+            synced: true,
         }
     }
 
     pub fn from_read(f: &mut File) -> Self {
-        let mut buffer = vec![];
+        Lyrics::from_lrc(read_text(f).as_str())
+    }
+
+    pub fn from_lrc(m: &str) -> Self {
         let regex =
             Regex::new(r"\[(?P<min>\d+):(?P<sec>\d+).(?P<ms>\d+)](?P<content>[^\[\]]*)").unwrap();
-        f.read_to_end(&mut buffer).unwrap();
-        let m = String::from_utf8(buffer).unwrap();
-        let mut lyrics_vec = vec![];
-        for cap in regex.captures_iter(m.as_str()) {
+        let mut lines = vec![];
+        for cap in regex.captures_iter(m) {
             let min = cap["min"].parse::<u64>().unwrap();
             let sec = cap["sec"].parse::<u64>().unwrap();
             let ms = cap["ms"].parse::<u64>().unwrap();
             let dur = Duration::from_millis(ms + sec * 1000 + min * 1000 * 60);
-            lyrics_vec.push(Lyric {
-                time: dur,
-                content: String::from(&cap["content"]),
+            lines.push((dur, cap["content"].trim().to_string()));
+        }
+        Self {
+            list: Lyrics::group_by_time(lines),
+            synced: true,
+        }
+    }
+
+    /// SubRip: numbered cues with `00:00:01,000 --> 00:00:04,000` timings.
+    pub fn from_srt(m: &str) -> Self {
+        Lyrics::from_cues(m)
+    }
+
+    /// WebVTT: like SubRip, but with a `WEBVTT` header, optional hours,
+    /// cue settings after the timing and NOTE/STYLE blocks.
+    pub fn from_vtt(m: &str) -> Self {
+        Lyrics::from_cues(m)
+    }
+
+    /// Unsynced lyrics: one line per row, no timestamps.
+    pub fn from_plain(m: &str) -> Self {
+        let list = m
+            .lines()
+            .map(|line| Lyric {
+                time: Duration::ZERO,
+                end: None,
+                content: line.trim().to_string(),
+                translation: None,
+            })
+            .collect();
+        Self {
+            list,
+            synced: false,
+        }
+    }
+
+    // srt and vtt cues only differ in details the parser can ignore: a cue
+    // is a block whose timing line contains `-->`, anything else is skipped.
+    fn from_cues(m: &str) -> Self {
+        let timing = Regex::new(
+            r"^\s*(?P<start>(?:\d+:)?\d+:\d+[,.]\d+)\s*-->\s*(?P<end>(?:\d+:)?\d+:\d+[,.]\d+)",
+        )
+        .unwrap();
+        let tag = Regex::new(r"<[^>]*>").unwrap();
+        let mut list = vec![];
+        let normalized = m.replace("\r\n", "\n");
+        for block in normalized.split("\n\n") {
+            let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+            let cap = match lines.next().and_then(|line| timing.captures(line)) {
+                Some(cap) => cap,
+                None => continue,
+            };
+            let (start, end) = match (parse_cue_time(&cap["start"]), parse_cue_time(&cap["end"])) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            // multi-line cues are usually original + translation
+            let mut text = lines
+                .map(|line| tag.replace_all(line, "").trim().to_string())
+                .filter(|line| !line.is_empty());
+            let content = text.next().unwrap_or_default();
+            let rest: Vec<String> = text.collect();
+            list.push(Lyric {
+                time: start,
+                end: Some(end),
+                content,
+                translation: if rest.is_empty() {
+                    None
+                } else {
+                    Some(rest.join(" / "))
+                },
             });
         }
-        Self { list: lyrics_vec }
+        list.sort_by_key(|lyric| lyric.time);
+        Self { list, synced: true }
+    }
+
+    /// Merge lines sharing a timestamp: the first one is the original, the
+    /// following ones are its translation. Translations may also be listed
+    /// as a separate block, so lines are (stably) sorted by time first.
+    fn group_by_time(mut lines: Vec<(Duration, String)>) -> Vec<Lyric> {
+        lines.sort_by_key(|(time, _)| *time);
+        let mut lyrics_vec: Vec<Lyric> = vec![];
+        for (time, content) in lines {
+            match lyrics_vec.last_mut() {
+                Some(last) if last.time == time => {
+                    if content.is_empty() {
+                        continue;
+                    }
+                    match &mut last.translation {
+                        Some(translation) => {
+                            translation.push_str(" / ");
+                            translation.push_str(content.as_str());
+                        }
+                        None => last.translation = Some(content),
+                    }
+                }
+                _ => lyrics_vec.push(Lyric {
+                    time,
+                    end: None,
+                    content,
+                    translation: None,
+                }),
+            }
+        }
+        lyrics_vec
+    }
+
+    /// Serialize as `[mm:ss.mmm]` lines, translations on a second line with
+    /// the same timestamp.
+    pub fn to_lrc(&self) -> String {
+        let mut out = String::new();
+        for lyric in &self.list {
+            let stamp = format_lrc_time(lyric.time);
+            out.push_str(format!("{}{}\n", stamp, lyric.content).as_str());
+            if let Some(translation) = &lyric.translation {
+                out.push_str(format!("{}{}\n", stamp, translation).as_str());
+            }
+        }
+        out
+    }
+
+    /// Index of the line being sung at `at`.
+    pub fn index_at(&self, at: Duration) -> usize {
+        self.list
+            .iter()
+            .rposition(|lyric| lyric.time <= at)
+            .unwrap_or(0)
     }
 
     #[allow(dead_code)]
@@ -76,3 +291,173 @@ impl Lyrics {
         self.list.len()
     }
 }
+
+fn read_text(f: &mut File) -> String {
+    let mut buffer = vec![];
+    f.read_to_end(&mut buffer).unwrap();
+    let text = String::from_utf8_lossy(&buffer);
+    text.trim_start_matches('\u{feff}').to_string()
+}
+
+pub fn format_lrc_time(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!(
+        "[{:0>2}:{:0>2}.{:0>3}]",
+        ms / 60_000,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// `hh:mm:ss,mmm`, `mm:ss.mmm` or `hh:mm:ss.mmm`
+fn parse_cue_time(s: &str) -> Option<Duration> {
+    let (clock, frac) = s.split_once([',', '.'])?;
+    let mut secs = 0;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    // pad fractions such as ".5" to milliseconds
+    let ms = format!("{:0<3}", frac).get(0..3)?.parse::<u64>().ok()?;
+    Some(Duration::from_millis(secs * 1000 + ms))
+}
+
+/// Looks up lyrics beside the song and in the configured lyrics folders,
+/// remembering where each track's lyrics were found. Misses are not
+/// remembered, lyrics added later are found on the next look.
+pub struct LyricsFinder {
+    pub dirs: Vec<PathBuf>,
+    cache: HashMap<PathBuf, PathBuf>,
+}
+
+impl LyricsFinder {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn find(&mut self, music_path: &str) -> Lyrics {
+        let music_path = Path::new(music_path)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(music_path));
+        if let Some(lyrics) = self
+            .cache
+            .get(&music_path)
+            .and_then(|p| Lyrics::from_path(p))
+        {
+            return lyrics;
+        }
+        // not found before, or the file went away
+        self.cache.remove(&music_path);
+        let found = match self.resolve(&music_path) {
+            Some(found) => found,
+            None => return Lyrics::empty(),
+        };
+        let lyrics = Lyrics::from_path(&found);
+        self.cache.insert(music_path, found);
+        lyrics.unwrap_or_else(Lyrics::empty)
+    }
+
+    /// Forget a track's resolved path, e.g. after writing a new lyrics file.
+    pub fn invalidate(&mut self, music_path: &str) {
+        let music_path = Path::new(music_path)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(music_path));
+        self.cache.remove(&music_path);
+    }
+
+    /// exact file stem, then "Artist - Title" from tags, then a normalised
+    /// match of either against every lyrics file in the search folders.
+    pub fn resolve(&self, music_path: &Path) -> Option<PathBuf> {
+        let stem = music_path.file_stem()?.to_string_lossy().to_string();
+        let mut dirs = vec![];
+        if let Some(parent) = music_path.parent() {
+            dirs.push(parent.to_path_buf());
+        }
+        dirs.extend(self.dirs.iter().cloned());
+
+        if let Some(p) = find_with_stem(&dirs, stem.as_str()) {
+            return Some(p);
+        }
+        let tagged = read_artist_title(music_path);
+        if let Some((artist, title)) = &tagged {
+            let name = format!("{} - {}", artist, title);
+            if let Some(p) = find_with_stem(&dirs, name.as_str()) {
+                return Some(p);
+            }
+        }
+
+        let mut keys = vec![normalize(stem.as_str())];
+        if let Some((artist, title)) = &tagged {
+            keys.push(normalize(format!("{}{}", artist, title).as_str()));
+            keys.push(normalize(format!("{}{}", title, artist).as_str()));
+        }
+        keys.retain(|key| !key.is_empty());
+        for dir in &dirs {
+            let entries = match read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut candidates: Vec<PathBuf> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| lyrics_rank(p).is_some())
+                .filter(|p| {
+                    p.file_stem()
+                        .map(|s| keys.contains(&normalize(s.to_string_lossy().as_ref())))
+                        .unwrap_or(false)
+                })
+                .collect();
+            candidates.sort_by_key(|p| lyrics_rank(p));
+            if let Some(p) = candidates.into_iter().next() {
+                return Some(p);
+            }
+        }
+        None
+    }
+}
+
+fn find_with_stem(dirs: &[PathBuf], stem: &str) -> Option<PathBuf> {
+    for dir in dirs {
+        for ext in LYRICS_EXTENSIONS {
+            let p = dir.join(format!("{}.{}", stem, ext));
+            if p.is_file() {
+                return Some(p);
+            }
+        }
+    }
+    None
+}
+
+fn lyrics_rank(p: &Path) -> Option<usize> {
+    let ext = p.extension()?.to_string_lossy().to_lowercase();
+    LYRICS_EXTENSIONS.iter().position(|e| *e == ext)
+}
+
+fn read_artist_title(music_path: &Path) -> Option<(String, String)> {
+    let tagged_file = lofty::read_from_path(music_path).ok()?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+    let artist = tag.artist()?.trim().to_string();
+    let title = tag.title()?.trim().to_string();
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+    Some((artist, title))
+}
+
+/// Lowercase, fold full-width forms to ASCII and drop punctuation and
+/// spaces, so "Ｐｅｔｅｒ - Song (Live)" matches "peter_song live".
+pub fn normalize(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
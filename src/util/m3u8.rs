@@ -1,41 +1,36 @@
-use std::fs::read_dir;
-use std::sync::mpsc;
-
-use std::thread;
-use std::time::Duration;
-
 use failure::format_err;
 use m3u8_rs::Playlist;
 
-use crate::net::download;
+use std::time::Duration;
 
-pub fn download_m3u8_playlist(url: String) -> Result<Playlist, failure::Error> {
-    let (tx, rx) = mpsc::channel();
-    thread::spawn(move || download(url.as_str(), &tx));
-    let resp = rx.recv_timeout(Duration::from_secs(5));
-    return if let Ok(data) = resp {
-        let playlist = m3u8_rs::parse_playlist(data.as_bytes());
-        match playlist {
-            Ok(list) => Ok(list.1),
-            Err(_err) => Err(format_err!("Parse Playlist Failed")),
-        }
-    } else {
-        println!("{:?}", resp);
-        Err(format_err!("Download Timeout in 5 seconds."))
-    };
+use crate::net::{fetch_text, fetch_text_within};
+use crate::util::hls::LowLatency;
+
+/// A fetched playlist with the url it was served from after redirects (the
+/// base for the uris inside it), and the LL-HLS tags m3u8-rs does not parse.
+pub struct FetchedPlaylist {
+    pub playlist: Playlist,
+    pub url: String,
+    pub low_latency: LowLatency,
 }
 
-pub fn empty_cache() {
-    let mut dir = dirs::cache_dir().unwrap();
-    dir.push("RustPlayer");
-    if dir.exists() && dir.is_dir() {
-        let it = read_dir(dir.clone());
-        if let Ok(dirs) = it {
-            for dir in dirs {
-                if let Ok(d) = dir {
-                    std::fs::remove_file(d.path()).unwrap();
-                }
-            }
-        }
+pub fn download_m3u8_playlist(
+    url: String,
+    headers: Vec<(String, String)>,
+    // of a blocking reload, instead of the read timeout
+    timeout: Option<Duration>,
+) -> Result<FetchedPlaylist, failure::Error> {
+    let (data, final_url) = match timeout {
+        Some(timeout) => fetch_text_within(url.as_str(), &headers, timeout)?,
+        None => fetch_text(url.as_str(), &headers)?,
+    };
+    let playlist = m3u8_rs::parse_playlist(data.as_bytes());
+    match playlist {
+        Ok(list) => Ok(FetchedPlaylist {
+            playlist: list.1,
+            url: final_url,
+            low_latency: LowLatency::parse(data.as_str()),
+        }),
+        Err(_err) => Err(format_err!("Parse Playlist Failed")),
     }
 }
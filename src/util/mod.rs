@@ -15,6 +15,16 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod catalogue;
+pub mod dash;
+pub mod history;
+pub mod hls;
+pub mod icy;
+pub mod id3;
 pub mod lyrics;
 pub mod m3u8;
 pub mod net;
+pub mod playlist;
+pub mod podcast;
+pub mod stations;
+pub mod timeshift;
@@ -1,38 +1,312 @@
-use std::fmt::{Debug, Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    future::Future,
+    sync::{mpsc::Sender, OnceLock},
+    time,
+};
 
-use std::sync::mpsc::Sender;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, RANGE},
+    Client, Proxy, RequestBuilder, Response, StatusCode,
+};
+use tokio::runtime::Runtime;
 
-pub struct DownloadTimeoutError {
-    msg: String,
+/// Settings of the HTTP client shared by the whole app, `config.ini` keys
+/// of the same names.
+#[derive(Clone, Debug)]
+pub struct HttpConfig {
+    // http://, https://, socks5:// or socks5h:// url every request goes through
+    pub proxy: Option<String>,
+    pub user_agent: String,
+    // sent with every request, before the ones of a station
+    pub headers: Vec<(String, String)>,
+    pub connect_timeout: time::Duration,
+    // longest wait for a response or the next part of its body
+    pub read_timeout: time::Duration,
 }
 
-impl Display for DownloadTimeoutError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Download Timeout! {}", self.msg.as_str())
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            user_agent: format!("RustPlayer/{}", env!("CARGO_PKG_VERSION")),
+            headers: vec![],
+            connect_timeout: time::Duration::from_secs(10),
+            read_timeout: time::Duration::from_secs(15),
+        }
     }
 }
 
-impl Debug for DownloadTimeoutError {
+#[derive(Debug)]
+pub enum NetError {
+    // bad url, refused connection, TLS, proxy, ...
+    Request(reqwest::Error),
+    // the server answered with an error status
+    Status(StatusCode, String),
+    // nothing arrived within the read timeout
+    Timeout(String),
+    // the client could not be set up as configured
+    Config(String),
+}
+
+impl Display for NetError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Download Timeout: {}", self.msg.as_str())
+        match self {
+            NetError::Request(err) => write!(f, "{}", err),
+            NetError::Status(status, url) => write!(f, "{} from {}", status, url),
+            NetError::Timeout(url) => write!(f, "Timed out waiting for {}", url),
+            NetError::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl failure::Fail for NetError {}
+
+impl From<reqwest::Error> for NetError {
+    fn from(err: reqwest::Error) -> Self {
+        match (err.is_timeout(), err.url()) {
+            (true, Some(url)) => NetError::Timeout(url.to_string()),
+            _ => NetError::Request(err),
+        }
+    }
+}
+
+/// One runtime and connection pool for every request of the app.
+pub struct Http {
+    runtime: Runtime,
+    client: Client,
+    read_timeout: time::Duration,
+}
+
+static HTTP: OnceLock<Http> = OnceLock::new();
+
+/// Set up the shared client with `config`, before the first request.
+/// Without it the defaults are used.
+pub fn init(config: &HttpConfig) -> Result<(), NetError> {
+    let http = Http::new(config)?;
+    HTTP.set(http)
+        .map_err(|_| NetError::Config("The HTTP client is already set up".to_string()))
+}
+
+/// The shared client.
+pub fn http() -> &'static Http {
+    HTTP.get_or_init(|| Http::new(&HttpConfig::default()).expect("HTTP client"))
+}
+
+impl Http {
+    fn new(config: &HttpConfig) -> Result<Self, NetError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("http")
+            .enable_all()
+            .build()
+            .map_err(|err| NetError::Config(err.to_string()))?;
+        let mut builder = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .user_agent(config.user_agent.as_str())
+            .default_headers(header_map(&config.headers)?);
+        if let Some(proxy) = &config.proxy {
+            let proxy = Proxy::all(proxy.as_str())
+                .map_err(|err| NetError::Config(format!("Proxy {}: {}", proxy, err)))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(Self {
+            runtime,
+            client: builder.build()?,
+            read_timeout: config.read_timeout,
+        })
+    }
+
+    /// Run `future` on the shared runtime, from a thread outside of it.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn get(&self, url: &str, headers: &[(String, String)]) -> RequestBuilder {
+        let mut req = self.client.get(url);
+        for (name, value) in headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        req
+    }
+
+    // `future`, unless it takes longer than the read timeout
+    async fn timed<T, F>(&self, url: &str, future: F) -> Result<T, NetError>
+    where
+        F: Future<Output = Result<T, reqwest::Error>>,
+    {
+        self.timed_for(url, self.read_timeout, future).await
+    }
+
+    async fn timed_for<T, F>(
+        &self,
+        url: &str,
+        timeout: time::Duration,
+        future: F,
+    ) -> Result<T, NetError>
+    where
+        F: Future<Output = Result<T, reqwest::Error>>,
+    {
+        match tokio::time::timeout(timeout, future).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(NetError::Timeout(url.to_string())),
+        }
     }
+
+    // send `req` and wait for the head of a successful response
+    async fn send(&self, url: &str, req: RequestBuilder) -> Result<Response, NetError> {
+        self.send_for(url, self.read_timeout, req).await
+    }
+
+    async fn send_for(
+        &self,
+        url: &str,
+        timeout: time::Duration,
+        req: RequestBuilder,
+    ) -> Result<Response, NetError> {
+        let resp = self.timed_for(url, timeout, req.send()).await?;
+        let status = resp.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(NetError::Status(status, url.to_string()));
+        }
+        Ok(resp)
+    }
+
+    /// GET `url` with extra `headers`, returning the body and the url it
+    /// was served from after following redirects. Blocks, waiting up to
+    /// `timeout` instead of the read timeout when given.
+    pub fn get_text(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Option<time::Duration>,
+    ) -> Result<(String, String), NetError> {
+        let timeout = timeout.unwrap_or(self.read_timeout);
+        self.block_on(async {
+            let resp = self.send_for(url, timeout, self.get(url, headers)).await?;
+            let final_url = resp.url().to_string();
+            let text = self.timed_for(url, timeout, resp.text()).await?;
+            Ok((text, final_url))
+        })
+    }
+
+    /// Download `length` bytes at `offset` with a Range request, or the
+    /// whole resource without a range.
+    pub async fn fetch_range(
+        &self,
+        url: &str,
+        range: Option<(u64, u64)>,
+        headers: &[(String, String)],
+    ) -> Result<bytes::Bytes, NetError> {
+        let mut req = self.get(url, headers);
+        if let Some((offset, length)) = range {
+            let end = offset + length.max(1) - 1;
+            req = req.header(RANGE, format!("bytes={}-{}", offset, end));
+        }
+        let resp = self.send(url, req).await?;
+        let partial = resp.status() == StatusCode::PARTIAL_CONTENT;
+        let mut data = self.timed(url, resp.bytes()).await?;
+        // servers ignoring Range answer with the whole resource
+        if let (Some((offset, length)), false) = (range, partial) {
+            let start = (offset as usize).min(data.len());
+            let end = (start + length as usize).min(data.len());
+            data = data.slice(start..end);
+        }
+        Ok(data)
+    }
+}
+
+/// `Name: value; Name: value` of `headers` in `config.ini`, parts without
+/// a colon are ignored.
+pub fn parse_headers(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
 }
 
-impl failure::Fail for DownloadTimeoutError {}
+fn header_map(headers: &[(String, String)]) -> Result<HeaderMap, NetError> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let invalid = || NetError::Config(format!("Invalid header {}: {}", name, value));
+        let header = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+        let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+        map.insert(header, value);
+    }
+    Ok(map)
+}
 
-#[tokio::main]
-pub async fn download(url: &str, tx: &Sender<String>) -> std::result::Result<(), failure::Error> {
-    let resp = reqwest::get(url).await?.text().await?;
-    tx.send(resp)?;
+// kept for the playlist fetch test
+#[allow(dead_code)]
+pub fn download(url: &str, tx: &Sender<String>) -> Result<(), NetError> {
+    let (text, _) = http().get_text(url, &[], None)?;
+    let _ = tx.send(text);
     Ok(())
 }
 
-#[tokio::main]
-pub async fn download_as_bytes(
+/// GET `url` with extra `headers` on the shared client, returning the body
+/// and the url it was served from after following redirects.
+pub fn fetch_text(url: &str, headers: &[(String, String)]) -> Result<(String, String), NetError> {
+    http().get_text(url, headers, None)
+}
+
+/// `fetch_text` for a request the server may hold longer than the read
+/// timeout, such as a blocking playlist reload.
+pub fn fetch_text_within(
     url: &str,
-    tx: &Sender<bytes::Bytes>,
-) -> std::result::Result<(), failure::Error> {
-    let resp = reqwest::get(url).await?.bytes().await?;
-    tx.send(resp)?;
-    Ok(())
+    headers: &[(String, String)],
+    timeout: time::Duration,
+) -> Result<(String, String), NetError> {
+    http().get_text(url, headers, Some(timeout))
+}
+
+/// Blocking `Read` over a response body that may never end, such as an
+/// Icecast stream. Meant for a thread of its own.
+pub struct BodyReader {
+    url: String,
+    response: Response,
+    chunk: bytes::Bytes,
+}
+
+impl BodyReader {
+    /// GET `url` with extra request `headers`, failing on error statuses.
+    pub fn open(url: &str, headers: &[(&str, &str)]) -> Result<Self, NetError> {
+        let headers: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let http = http();
+        let response = http.block_on(http.send(url, http.get(url, &headers)))?;
+        Ok(Self {
+            url: url.to_string(),
+            response,
+            chunk: bytes::Bytes::new(),
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<String> {
+        self.response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    }
+}
+
+impl std::io::Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            let http = http();
+            let chunk = http.block_on(http.timed(self.url.as_str(), self.response.chunk()));
+            match chunk {
+                Ok(Some(chunk)) => self.chunk = chunk,
+                Ok(None) => return Ok(0),
+                Err(err) => return Err(std::io::Error::other(err.to_string())),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
 }
@@ -0,0 +1,272 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use reqwest::Url;
+
+/// How a radio url is played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamKind {
+    Hls,
+    // MPEG-DASH manifest
+    Dash,
+    // a continuous HTTP body, e.g. Icecast or Shoutcast
+    Progressive,
+}
+
+/// HLS for `.m3u8` urls or HLS content types, DASH for `.mpd` urls or
+/// the DASH content type, a progressive stream otherwise.
+pub fn detect_kind(url: &str, content_type: Option<&str>) -> StreamKind {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if path.ends_with(".m3u8") {
+        return StreamKind::Hls;
+    }
+    if path.ends_with(".mpd") {
+        return StreamKind::Dash;
+    }
+    let mime = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase());
+    match mime.as_deref() {
+        Some("application/vnd.apple.mpegurl") | Some("application/x-mpegurl") => StreamKind::Hls,
+        Some("application/dash+xml") => StreamKind::Dash,
+        _ => StreamKind::Progressive,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    Pls,
+    M3u,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    /// By extension, so files in the explorer can be told apart.
+    pub fn from_location(location: &str) -> Option<Self> {
+        let path = location.split(['?', '#']).next().unwrap_or_default();
+        let ext = path.rsplit('.').next()?.to_lowercase();
+        match ext.as_str() {
+            "pls" => Some(PlaylistFormat::Pls),
+            "m3u" => Some(PlaylistFormat::M3u),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+
+    /// By content, falling back to the extension.
+    pub fn detect(location: &str, text: &str) -> Option<Self> {
+        let head = text.trim_start_matches('\u{feff}').trim_start();
+        if head.to_lowercase().starts_with("[playlist]") {
+            Some(PlaylistFormat::Pls)
+        } else if head.starts_with("<?xml") || head.starts_with("<playlist") {
+            Some(PlaylistFormat::Xspf)
+        } else if head.starts_with("#EXTM3U") {
+            Some(PlaylistFormat::M3u)
+        } else {
+            Self::from_location(location)
+        }
+    }
+}
+
+/// A station of an imported playlist, later urls are fallbacks of the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedStation {
+    pub name: String,
+    pub urls: Vec<String>,
+}
+
+/// Stations of a PLS, M3U or XSPF playlist loaded from `location`, against
+/// which relative urls are resolved. Entries with the same title are merged
+/// into one station with fallback urls, untitled ones stay apart. An HLS
+/// media or master playlist is a single station of its own.
+pub fn parse_playlist(location: &str, text: &str) -> Vec<ImportedStation> {
+    if text.contains("#EXT-X-") {
+        return vec![ImportedStation {
+            name: name_from_url(location),
+            urls: vec![location.to_string()],
+        }];
+    }
+    let entries = match PlaylistFormat::detect(location, text) {
+        Some(PlaylistFormat::Pls) => pls_entries(text),
+        Some(PlaylistFormat::M3u) | None => m3u_entries(text),
+        Some(PlaylistFormat::Xspf) => xspf_entries(text),
+    };
+    let mut stations: Vec<ImportedStation> = vec![];
+    // whether a station's name is a title from the playlist
+    let mut titled: Vec<bool> = vec![];
+    for (title, urls) in entries {
+        let urls: Vec<String> = urls
+            .iter()
+            .filter_map(|url| resolve_station_url(location, url))
+            .collect();
+        let first = match urls.first() {
+            Some(first) => first,
+            None => continue,
+        };
+        let title = title
+            .map(|title| strip_listeners(title.as_str()))
+            .filter(|title| !title.is_empty());
+        let same = title.as_ref().and_then(|title| {
+            (0..stations.len()).find(|i| titled[*i] && stations[*i].name == *title)
+        });
+        match (same, title) {
+            (Some(i), _) => {
+                for url in urls {
+                    if !stations[i].urls.contains(&url) {
+                        stations[i].urls.push(url);
+                    }
+                }
+            }
+            (None, Some(name)) => {
+                stations.push(ImportedStation { name, urls });
+                titled.push(true);
+            }
+            // named after the host, which other untitled streams may share
+            (None, None) => {
+                if stations.iter().any(|station| station.urls.contains(first)) {
+                    continue;
+                }
+                let name = name_from_url(first);
+                stations.push(ImportedStation { name, urls });
+                titled.push(false);
+            }
+        }
+    }
+    stations
+}
+
+// (title, urls) in playlist order
+type Entry = (Option<String>, Vec<String>);
+
+fn pls_entries(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<(u32, Entry)> = vec![];
+    for line in text.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim().to_string()),
+            None => continue,
+        };
+        let (field, index) = if let Some(index) = key.strip_prefix("file") {
+            ("file", index)
+        } else if let Some(index) = key.strip_prefix("title") {
+            ("title", index)
+        } else {
+            continue;
+        };
+        let index = match index.parse::<u32>() {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+        let position = match entries.iter().position(|(i, _)| *i == index) {
+            Some(position) => position,
+            None => {
+                entries.push((index, (None, vec![])));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[position].1;
+        if field == "file" {
+            entry.1.push(value);
+        } else {
+            entry.0 = Some(value);
+        }
+    }
+    entries.sort_by_key(|(index, _)| *index);
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn m3u_entries(text: &str) -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut title = None;
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // `#EXTINF:-1 tvg-name="..",Title`, the title follows the first
+            // comma outside quotes
+            let mut quoted = false;
+            let comma = info.char_indices().find(|(_, c)| {
+                if *c == '"' {
+                    quoted = !quoted;
+                }
+                *c == ',' && !quoted
+            });
+            title = comma.map(|(i, _)| info[i + 1..].trim().to_string());
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            entries.push((title.take(), vec![line.to_string()]));
+        }
+    }
+    entries
+}
+
+fn xspf_entries(text: &str) -> Vec<Entry> {
+    let doc = match roxmltree::Document::parse(text) {
+        Ok(doc) => doc,
+        Err(_) => return vec![],
+    };
+    doc.descendants()
+        .filter(|node| node.tag_name().name() == "track")
+        .map(|track| {
+            let title = xml_children(track, "title").into_iter().next();
+            (title, xml_children(track, "location"))
+        })
+        .collect()
+}
+
+/// Text of the `name` elements directly inside `node`.
+fn xml_children(node: roxmltree::Node, name: &str) -> Vec<String> {
+    node.children()
+        .filter(|child| child.tag_name().name() == name)
+        .map(|child| child.text().unwrap_or_default().trim().to_string())
+        .collect()
+}
+
+/// Absolute http(s) url of a playlist entry, None for local files.
+fn resolve_station_url(location: &str, url: &str) -> Option<String> {
+    let url = url.trim();
+    let resolved = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => Url::parse(location).ok()?.join(url).ok()?,
+    };
+    match resolved.scheme() {
+        "http" | "https" => Some(resolved.to_string()),
+        _ => None,
+    }
+}
+
+/// Shoutcast titles look like `(#1 - 250/500) Station`.
+fn strip_listeners(title: &str) -> String {
+    let title = title.trim();
+    match (title.starts_with("(#"), title.find(')')) {
+        (true, Some(end)) => title[end + 1..].trim().to_string(),
+        _ => title.to_string(),
+    }
+}
+
+fn name_from_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => parsed.host_str().unwrap_or(url).to_string(),
+        Err(_) => std::path::Path::new(url)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| url.to_string()),
+    }
+}
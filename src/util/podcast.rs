@@ -0,0 +1,358 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use failure::format_err;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+
+use super::hls::{format_date_time, parse_date_time, resolve_uri};
+
+const ITUNES: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+// this close to the end an episode counts as played
+const PLAYED_MARGIN: Duration = Duration::from_secs(30);
+
+/// An episode with an audio enclosure.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Episode {
+    // guid, or the enclosure url without one
+    pub id: String,
+    pub title: String,
+    // enclosure, resolved against the feed url
+    pub url: String,
+    pub mime_type: Option<String>,
+    // enclosure length in bytes, 0 or missing in many feeds
+    pub length: Option<u64>,
+    // unix milliseconds
+    pub published: Option<i64>,
+    pub duration: Option<Duration>,
+}
+
+/// An RSS 2.0 or Atom feed, newest episode first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    pub episodes: Vec<Episode>,
+}
+
+/// A feed of `podcasts.toml`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subscription {
+    pub url: String,
+    // from the feed, shown until it is loaded
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+}
+
+/// What is known of an episode besides the feed, by episode id.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EpisodeState {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub played: bool,
+    // resume position in seconds
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub position: u64,
+    // path of the finished download
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl EpisodeState {
+    /// Where to start playing, the beginning once it was played.
+    pub fn resume(&self) -> Duration {
+        match self.played {
+            true => Duration::ZERO,
+            false => Duration::from_secs(self.position),
+        }
+    }
+
+    /// Playback of an episode of `duration` stopped at `position`, close to
+    /// the end it counts as played and starts over next time.
+    pub fn stopped(&mut self, duration: Duration) {
+        if Duration::from_secs(self.position) + PLAYED_MARGIN >= duration {
+            self.played = true;
+            self.position = 0;
+        }
+    }
+}
+
+/// Roughly where `resume` is in a file of `total` bytes and `duration`,
+/// 0 when either is unknown.
+pub fn resume_offset(total: Option<u64>, duration: Option<Duration>, resume: Duration) -> u64 {
+    match (total, duration) {
+        (Some(total), Some(duration)) if !duration.is_zero() => {
+            let offset = total as f64 * resume.as_secs_f64() / duration.as_secs_f64();
+            (offset as u64).min(total)
+        }
+        _ => 0,
+    }
+}
+
+/// `podcasts.toml` in the config folder.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PodcastsFile {
+    #[serde(default)]
+    pub podcast: Vec<Subscription>,
+    #[serde(default)]
+    pub episode: BTreeMap<String, EpisodeState>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl PodcastsFile {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|err| err.to_string())
+    }
+}
+
+// child element `name` of `node`, in namespace `ns` or in none
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &str,
+    ns: Option<&str>,
+) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name && child.tag_name().namespace() == ns)
+}
+
+fn text(node: Node, name: &str, ns: Option<&str>) -> Option<String> {
+    let text = child(node, name, ns)?.text()?.trim();
+    Some(text.to_string()).filter(|text| !text.is_empty())
+}
+
+/// Parse an RSS 2.0 or Atom feed served from `url`. Entries without an
+/// audio enclosure are left out.
+pub fn parse_feed(text: &str, url: &str) -> Result<Feed, failure::Error> {
+    let doc = Document::parse(text).map_err(|err| format_err!("Bad feed: {}", err))?;
+    let root = doc.root_element();
+    let mut feed = match root.tag_name().name() {
+        "rss" => {
+            let channel = child(root, "channel", None)
+                .ok_or_else(|| format_err!("RSS feed without a channel"))?;
+            rss_feed(channel, url)
+        }
+        "feed" => atom_feed(root, url),
+        other => return Err(format_err!("Not a podcast feed: <{}>", other)),
+    };
+    // newest first, undated ones keep their place at the end
+    feed.episodes
+        .sort_by_key(|episode| std::cmp::Reverse(episode.published));
+    Ok(feed)
+}
+
+fn rss_feed(channel: Node, url: &str) -> Feed {
+    let episodes = channel
+        .children()
+        .filter(|item| item.has_tag_name("item"))
+        .filter_map(|item| {
+            let enclosure = child(item, "enclosure", None)?;
+            let enclosure_url = resolve_uri(url, enclosure.attribute("url")?);
+            Some(Episode {
+                id: text(item, "guid", None).unwrap_or_else(|| enclosure_url.clone()),
+                title: text(item, "title", None).unwrap_or_default(),
+                url: enclosure_url,
+                mime_type: enclosure.attribute("type").map(str::to_string),
+                length: enclosure
+                    .attribute("length")
+                    .and_then(|length| length.trim().parse().ok())
+                    .filter(|length| *length > 0),
+                published: text(item, "pubDate", None).and_then(|date| parse_rfc2822(&date)),
+                duration: text(item, "duration", Some(ITUNES))
+                    .and_then(|duration| parse_clock(&duration)),
+            })
+        })
+        .collect();
+    Feed {
+        title: text(channel, "title", None).unwrap_or_default(),
+        episodes,
+    }
+}
+
+fn atom_feed(root: Node, url: &str) -> Feed {
+    let ns = root.tag_name().namespace();
+    let episodes = root
+        .children()
+        .filter(|entry| entry.tag_name().name() == "entry")
+        .filter_map(|entry| {
+            let enclosure = entry.children().find(|link| {
+                link.tag_name().name() == "link" && link.attribute("rel") == Some("enclosure")
+            })?;
+            let enclosure_url = resolve_uri(url, enclosure.attribute("href")?);
+            let date = text(entry, "published", ns).or_else(|| text(entry, "updated", ns));
+            Some(Episode {
+                id: text(entry, "id", ns).unwrap_or_else(|| enclosure_url.clone()),
+                title: text(entry, "title", ns).unwrap_or_default(),
+                url: enclosure_url,
+                mime_type: enclosure.attribute("type").map(str::to_string),
+                length: enclosure
+                    .attribute("length")
+                    .and_then(|length| length.trim().parse().ok())
+                    .filter(|length| *length > 0),
+                published: date.and_then(|date| parse_date_time(&date)),
+                duration: text(entry, "duration", Some(ITUNES))
+                    .and_then(|duration| parse_clock(&duration)),
+            })
+        })
+        .collect();
+    Feed {
+        title: text(root, "title", ns).unwrap_or_default(),
+        episodes,
+    }
+}
+
+/// The feeds of an OPML subscription list, nested outlines included.
+pub fn parse_opml(text: &str) -> Result<Vec<Subscription>, failure::Error> {
+    let doc = Document::parse(text).map_err(|err| format_err!("Bad OPML: {}", err))?;
+    if !doc.root_element().has_tag_name("opml") {
+        return Err(format_err!("Not an OPML file"));
+    }
+    Ok(doc
+        .descendants()
+        .filter(|outline| outline.has_tag_name("outline"))
+        .filter_map(|outline| {
+            let url = outline.attribute("xmlUrl")?.trim();
+            let title = outline.attribute("title").or(outline.attribute("text"));
+            Some(Subscription {
+                url: url.to_string(),
+                title: title.unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Whether `text` is an OPML document rather than a feed.
+pub fn is_opml(text: &str) -> bool {
+    Document::parse(text).is_ok_and(|doc| doc.root_element().has_tag_name("opml"))
+}
+
+/// Unix milliseconds of an RFC 2822 date such as
+/// `Wed, 01 May 2024 18:15:03 +0200`, as found in `pubDate`.
+pub fn parse_rfc2822(s: &str) -> Option<i64> {
+    // the weekday is optional
+    let s = s.split_once(',').map_or(s, |(_, rest)| rest);
+    let mut fields = s.split_whitespace();
+    let day: u32 = fields.next()?.parse().ok()?;
+    let month = fields.next()?.to_lowercase();
+    let month = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ]
+    .iter()
+    .position(|name| month.starts_with(name))?
+        + 1;
+    let year: i64 = fields.next()?.parse().ok()?;
+    // two digit years are from 1950 to 2049
+    let year = match year {
+        0..=49 => year + 2000,
+        50..=99 => year + 1900,
+        _ => year,
+    };
+    let clock = fields.next().unwrap_or("00:00:00");
+    let zone = match fields.next().unwrap_or("GMT").to_uppercase().as_str() {
+        "GMT" | "UT" | "UTC" | "Z" => "+00:00".to_string(),
+        "EDT" => "-04:00".to_string(),
+        "EST" | "CDT" => "-05:00".to_string(),
+        "CST" | "MDT" => "-06:00".to_string(),
+        "MST" | "PDT" => "-07:00".to_string(),
+        "PST" => "-08:00".to_string(),
+        zone if zone.len() == 5 && zone.starts_with(['+', '-']) => {
+            format!("{}:{}", &zone[..3], &zone[3..])
+        }
+        _ => "+00:00".to_string(),
+    };
+    parse_date_time(format!("{:04}-{:02}-{:02}T{}{}", year, month, day, clock, zone).as_str())
+}
+
+/// `itunes:duration` as seconds, `mm:ss` or `hh:mm:ss`.
+pub fn parse_clock(s: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for field in s.trim().split(':') {
+        let field = field.trim().parse::<f64>().ok().filter(|f| f.is_finite())?;
+        seconds = seconds * 60.0 + field;
+    }
+    // none when longer than a Duration holds
+    Duration::try_from_secs_f64(seconds.max(0.0)).ok()
+}
+
+/// `2024-05-01`
+pub fn format_date(millis: i64) -> String {
+    format_date_time(millis)[..10].to_string()
+}
+
+/// `name` made safe as a single file or directory name: characters
+/// Windows or a path would trip over replaced, leading and trailing dots
+/// dropped so it cannot be `.` or `..`, `fallback` if nothing is left.
+pub fn safe_file_name(name: &str, fallback: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+    if name.is_empty() {
+        fallback.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// FNV-1a, the same on every build unlike the std hasher
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A file name for the download of `episode`: its title, a hash of its id
+/// so episodes of the same title do not overwrite each other, and the
+/// extension of the enclosure.
+pub fn file_name(episode: &Episode) -> String {
+    let path = episode.url.split(['?', '#']).next().unwrap_or_default();
+    let last = path.rsplit('/').next().unwrap_or_default();
+    let extension = match last.rsplit_once('.') {
+        Some((_, ext)) if (1..=4).contains(&ext.len()) => ext.to_lowercase(),
+        _ => match episode.mime_type.as_deref() {
+            Some("audio/mp4") | Some("audio/x-m4a") | Some("audio/aac") => "m4a".to_string(),
+            Some("audio/ogg") | Some("audio/opus") => "ogg".to_string(),
+            _ => "mp3".to_string(),
+        },
+    };
+    let name = safe_file_name(episode.title.as_str(), "episode");
+    let hash = fnv1a(episode.id.as_str());
+    format!(
+        "{} {:08x}.{}",
+        name,
+        (hash ^ (hash >> 32)) as u32,
+        extension
+    )
+}
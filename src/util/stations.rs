@@ -0,0 +1,344 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use toml::{Spanned, Value};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TomlError};
+
+/// A station of `stations.toml`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Station {
+    pub name: String,
+    // the first is played, the others are fallbacks
+    pub urls: Vec<String>,
+    pub group: Option<String>,
+    pub favourite: bool,
+    pub headers: Vec<(String, String)>,
+    pub user_agent: Option<String>,
+    // `highest`, `lowest` or kbps, instead of `variant` of `config.ini`
+    pub variant: Option<String>,
+    // of the `[[station]]` header, for error messages
+    pub line: usize,
+    // index among the `[[station]]` tables, skipped ones included
+    pub entry: usize,
+}
+
+/// Stations and playlists of `stations.toml`, with the entries skipped.
+#[derive(Debug, Default)]
+pub struct Stations {
+    pub stations: Vec<Station>,
+    // PLS/M3U/XSPF paths or urls whose stations are imported
+    pub playlists: Vec<String>,
+    // `line n: reason` of every invalid entry
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct StationsFile {
+    #[serde(default)]
+    station: Vec<Spanned<Value>>,
+    #[serde(default)]
+    playlist: Vec<Spanned<Value>>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(deny_unknown_fields)]
+struct StationEntry {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    favourite: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variant: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PlaylistEntry {
+    location: String,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Parse `stations.toml`. A file that is not TOML is an error, invalid
+/// entries are skipped and reported in `errors`.
+pub fn parse_stations(text: &str) -> Result<Stations, String> {
+    let file: StationsFile = toml::from_str(text).map_err(|err| err.to_string())?;
+    let mut stations = Stations::default();
+    for (i, entry) in file.station.into_iter().enumerate() {
+        let line = line_of(text, entry.span().start);
+        match to_station(entry.into_inner(), line) {
+            Ok(station) => stations.stations.push(Station {
+                entry: i,
+                ..station
+            }),
+            Err(err) => stations.errors.push(format!("line {}: {}", line, err)),
+        }
+    }
+    for entry in file.playlist {
+        let line = line_of(text, entry.span().start);
+        match entry.into_inner().try_into::<PlaylistEntry>() {
+            Ok(playlist) => stations.playlists.push(playlist.location),
+            Err(err) => stations
+                .errors
+                .push(format!("line {}: {}", line, err.message())),
+        }
+    }
+    Ok(stations)
+}
+
+fn to_station(value: Value, line: usize) -> Result<Station, String> {
+    let entry: StationEntry = value.try_into().map_err(|err| err.message().to_string())?;
+    let name = entry.name.trim().to_string();
+    if name.is_empty() {
+        return Err("station without a name".to_string());
+    }
+    let urls: Vec<String> = entry.url.into_iter().chain(entry.urls).collect();
+    if urls.is_empty() {
+        return Err(format!("{} has no url", name));
+    }
+    if let Some(url) = urls.iter().find(|url| !url.contains("://")) {
+        return Err(format!("{}: {} is not a url", name, url));
+    }
+    // requests with them could never be sent
+    for (header, value) in &entry.headers {
+        if HeaderName::from_bytes(header.as_bytes()).is_err() {
+            return Err(format!("{}: {} is not a header name", name, header));
+        }
+        if HeaderValue::from_str(value).is_err() {
+            return Err(format!("{}: invalid value of header {}", name, header));
+        }
+    }
+    if let Some(user_agent) = &entry.user_agent {
+        if HeaderValue::from_str(user_agent).is_err() {
+            return Err(format!("{}: invalid user_agent", name));
+        }
+    }
+    Ok(Station {
+        name,
+        urls,
+        group: entry.group,
+        favourite: entry.favourite,
+        headers: entry.headers.into_iter().collect(),
+        user_agent: entry.user_agent,
+        variant: entry.variant,
+        line,
+        entry: 0,
+    })
+}
+
+// 1-based line of a byte offset
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// `[[station]]` table to append to `stations.toml`.
+pub fn station_toml(station: &Station) -> String {
+    let mut entry = StationEntry {
+        name: station.name.clone(),
+        group: station.group.clone(),
+        favourite: station.favourite,
+        user_agent: station.user_agent.clone(),
+        variant: station.variant.clone(),
+        headers: station.headers.iter().cloned().collect(),
+        ..Default::default()
+    };
+    match station.urls.as_slice() {
+        [url] => entry.url = Some(url.clone()),
+        urls => entry.urls = urls.to_vec(),
+    }
+    table_toml("station", &entry)
+}
+
+/// `[[playlist]]` table to append to `stations.toml`.
+pub fn playlist_toml(location: &str) -> String {
+    let entry = PlaylistEntry {
+        location: location.to_string(),
+    };
+    table_toml("playlist", &entry)
+}
+
+/// `stations.toml` as written, edited in place so that comments and the
+/// entries `parse_stations` skipped are kept.
+pub struct StationsDocument {
+    doc: DocumentMut,
+}
+
+impl StationsDocument {
+    /// Fails on invalid TOML and on `station` or `playlist` that are not
+    /// arrays of tables, which could not be edited by entry.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc: DocumentMut = text.parse().map_err(|err: TomlError| err.to_string())?;
+        for key in ["station", "playlist"] {
+            if let Some(item) = doc.get(key) {
+                if !item.is_array_of_tables() {
+                    return Err(format!("{} is not a list of [[{}]] tables", key, key));
+                }
+            }
+        }
+        Ok(Self { doc })
+    }
+
+    /// Replace the `[[station]]` table `entry`, keeping the comments before
+    /// it, or append one when None. Returns the entry.
+    pub fn set_station(&mut self, entry: Option<usize>, station: &Station) -> usize {
+        let mut table = new_table("station", station_toml(station).as_str());
+        if let Some(entry) = entry {
+            if let Some(old) = self.tables("station").and_then(|t| t.get_mut(entry)) {
+                move_place(old, &mut table);
+                *old = table;
+                return entry;
+            }
+        }
+        self.push("station", table)
+    }
+
+    /// Remove the `[[station]]` table `entry`, the later ones move up.
+    pub fn remove_station(&mut self, entry: usize) {
+        if let Some(tables) = self.tables("station") {
+            if entry < tables.len() {
+                tables.remove(entry);
+            }
+        }
+    }
+
+    /// Swap the contents of two `[[station]]` tables, the comments stay.
+    pub fn swap_stations(&mut self, a: usize, b: usize) {
+        let tables = match self.tables("station") {
+            Some(tables) if a != b && a.max(b) < tables.len() => tables,
+            _ => return,
+        };
+        let mut first = std::mem::take(tables.get_mut(a).unwrap());
+        let mut second = std::mem::take(tables.get_mut(b).unwrap());
+        let mut place = Table::new();
+        move_place(&first, &mut place);
+        move_place(&second, &mut first);
+        move_place(&place, &mut second);
+        *tables.get_mut(a).unwrap() = second;
+        *tables.get_mut(b).unwrap() = first;
+    }
+
+    /// Append a `[[playlist]]` table.
+    pub fn add_playlist(&mut self, location: &str) {
+        let table = new_table("playlist", playlist_toml(location).as_str());
+        self.push("playlist", table);
+    }
+
+    fn tables(&mut self, key: &str) -> Option<&mut ArrayOfTables> {
+        self.doc.get_mut(key)?.as_array_of_tables_mut()
+    }
+
+    // after the last table of `key`, returns its index
+    fn push(&mut self, key: &str, mut table: Table) -> usize {
+        if self.doc.iter().next().is_none() {
+            // the comments of a file without tables stay above the first
+            table.decor_mut().set_prefix(self.doc.trailing().clone());
+            self.doc.set_trailing("");
+        }
+        let tables = self.doc[key]
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .unwrap();
+        tables.push(table);
+        tables.len() - 1
+    }
+}
+
+// the comments before `from` and its place in the file
+fn move_place(from: &Table, to: &mut Table) {
+    *to.decor_mut() = from.decor().clone();
+    if let Some(position) = from.position() {
+        to.set_position(position);
+    }
+}
+
+// the first `[[key]]` table of `text`, without a place in any file
+fn new_table(key: &str, text: &str) -> Table {
+    let doc: DocumentMut = text.parse().unwrap_or_default();
+    let mut table = Table::new();
+    let parsed = doc.get(key).and_then(|item| item.as_array_of_tables());
+    if let Some(parsed) = parsed.and_then(|tables| tables.get(0)) {
+        for (key, item) in parsed.iter() {
+            table.insert(key, item.clone());
+        }
+    }
+    // `headers = { .. }` as in the examples, not a `[station.headers]`
+    if let Some(headers) = table.get_mut("headers") {
+        headers.make_value();
+    }
+    table
+}
+
+impl std::fmt::Display for StationsDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.doc.fmt(f)
+    }
+}
+
+fn table_toml<T: Serialize>(name: &str, entry: &T) -> String {
+    let mut table = BTreeMap::new();
+    table.insert(name, vec![entry]);
+    toml::to_string(&table).unwrap_or_default()
+}
+
+/// Convert the lines of the old `radio.ini`, `name url [fallback urls]`
+/// where the name may contain spaces, or a single playlist path or url.
+pub fn migrate_radio_ini(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() == 1 {
+            out.push_str(playlist_toml(tokens[0]).as_str());
+            out.push('\n');
+            continue;
+        }
+        let first_url = tokens
+            .iter()
+            .position(|token| token.contains("://"))
+            .filter(|i| *i > 0)
+            .unwrap_or(1);
+        let station = Station {
+            name: tokens[..first_url].join(" "),
+            urls: tokens[first_url..]
+                .iter()
+                .map(|url| url.to_string())
+                .collect(),
+            ..Default::default()
+        };
+        out.push_str(station_toml(&station).as_str());
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,109 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, time::Duration};
+
+/// Rolling buffer of the last `capacity` of a live stream. Positions are
+/// measured from the start of the oldest segment still buffered.
+pub struct Timeshift<T> {
+    capacity: Duration,
+    segments: VecDeque<(Duration, T)>,
+    // the next segment to hand out for playback
+    next: usize,
+}
+
+impl<T: Clone> Timeshift<T> {
+    pub fn new(capacity: Duration) -> Self {
+        Self {
+            capacity,
+            segments: VecDeque::new(),
+            next: 0,
+        }
+    }
+
+    /// Append a downloaded segment, dropping the oldest ones beyond the
+    /// capacity. Playback paused for longer continues with the oldest.
+    pub fn push(&mut self, duration: Duration, segment: T) {
+        self.segments.push_back((duration, segment));
+        // the newest segment is always kept
+        while self.segments.len() > 1 && self.total() > self.capacity {
+            self.segments.pop_front();
+            self.next = self.next.saturating_sub(1);
+        }
+    }
+
+    /// The segment to play next.
+    pub fn next_segment(&mut self) -> Option<(Duration, T)> {
+        let segment = self.segments.get(self.next)?.clone();
+        self.next += 1;
+        Some(segment)
+    }
+
+    pub fn total(&self) -> Duration {
+        self.segments.iter().map(|(duration, _)| *duration).sum()
+    }
+
+    /// Playback position with `in_flight` segments handed out and not
+    /// finished, `elapsed` into the oldest of them.
+    pub fn position(&self, in_flight: usize, elapsed: Duration) -> Duration {
+        let playing = self.next.saturating_sub(in_flight);
+        self.start_of(playing) + elapsed
+    }
+
+    /// How far `position` is behind the newest buffered audio.
+    pub fn behind(&self, position: Duration) -> Duration {
+        self.total().saturating_sub(position)
+    }
+
+    /// Continue playback at `to`, returns how far into the segment to
+    /// start.
+    pub fn seek(&mut self, to: Duration) -> Duration {
+        let mut start = Duration::ZERO;
+        for (i, (duration, _)) in self.segments.iter().enumerate() {
+            if to < start + *duration {
+                self.next = i;
+                return to - start;
+            }
+            start += *duration;
+        }
+        self.seek_live();
+        Duration::ZERO
+    }
+
+    /// Continue playback with the newest segment.
+    pub fn seek_live(&mut self) {
+        self.next = self.segments.len().saturating_sub(1);
+    }
+
+    fn start_of(&self, index: usize) -> Duration {
+        self.segments
+            .iter()
+            .take(index)
+            .map(|(duration, _)| *duration)
+            .sum()
+    }
+}
+
+/// `mm:ss`, or `h:mm:ss` from an hour on.
+pub fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/catalogue.rs");
+
+fn fixture() -> Catalogue {
+    let json = std::fs::read_to_string("assets/stations.json").unwrap();
+    Catalogue::parse(json.as_str()).unwrap()
+}
+
+#[test]
+fn test_parse_catalogue() {
+    let catalogue = fixture();
+    // the broken station and the duplicate url are skipped, most voted first
+    let names: Vec<_> = catalogue.stations.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Groove Salad", "Radio Swiss Jazz", "经济之声"]);
+    let jazz = &catalogue.stations[1];
+    assert_eq!(jazz.bitrate, 96);
+    assert_eq!(jazz.languages, vec!["german", "english"]);
+    assert!(catalogue.stations[2].hls);
+    assert!(Catalogue::parse("{}").is_err());
+}
+
+#[test]
+fn test_filter_catalogue() {
+    let catalogue = fixture();
+    let mut filter = Filter::default();
+    assert_eq!(
+        catalogue.values(Facet::Language, &filter)[0],
+        ("english".to_string(), 2)
+    );
+    filter.values.insert(Facet::Language, "English".to_string());
+    assert_eq!(catalogue.filter(&filter), vec![0, 1]);
+    // values of a facet ignore its own choice but follow the others
+    assert_eq!(catalogue.values(Facet::Language, &filter).len(), 4);
+    let codecs = catalogue.values(Facet::Codec, &filter);
+    assert_eq!(codecs.len(), 2);
+    filter.values.insert(Facet::Tag, "jazz".to_string());
+    assert_eq!(catalogue.filter(&filter), vec![1]);
+    filter.values.insert(Facet::Country, "China".to_string());
+    assert!(catalogue.filter(&filter).is_empty());
+}
@@ -0,0 +1,137 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An HTTP server on localhost for the tests that download.
+
+#![allow(dead_code)]
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+/// The answer of `serve_with` to a request.
+pub struct Reply {
+    pub status: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // before anything is sent
+    pub delay: Duration,
+    // after the head and `stall_after` bytes of the body
+    pub stall: Duration,
+    pub stall_after: usize,
+    // without Content-Length the body ends when the connection closes
+    pub length: bool,
+}
+
+impl Reply {
+    pub fn ok(body: Vec<u8>) -> Self {
+        Self::status("200 OK", body)
+    }
+
+    pub fn status(status: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body,
+            delay: Duration::ZERO,
+            stall: Duration::ZERO,
+            stall_after: 0,
+            length: true,
+        }
+    }
+}
+
+/// Serve `files` over HTTP on localhost, returns the base url.
+pub fn serve(files: Vec<(&'static str, Vec<u8>)>) -> String {
+    serve_with(
+        move |request| match files.iter().find(|(name, _)| *name == path(request)) {
+            Some((_, body)) => Reply::ok(body.clone()),
+            None => Reply::status("404 Not Found", vec![]),
+        },
+    )
+}
+
+/// Answer every request, given with its headers, with `reply`, returns the
+/// base url.
+pub fn serve_with<F>(reply: F) -> String
+where
+    F: Fn(&str) -> Reply + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}/", listener.local_addr().unwrap());
+    let reply = Arc::new(reply);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let reply = reply.clone();
+            // slow replies must not hold up the next requests
+            thread::spawn(move || {
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let reply = reply(String::from_utf8_lossy(&request).as_ref());
+                thread::sleep(reply.delay);
+                let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\n", reply.status);
+                if reply.length {
+                    head.push_str(format!("Content-Length: {}\r\n", reply.body.len()).as_str());
+                }
+                for (name, value) in &reply.headers {
+                    head.push_str(format!("{}: {}\r\n", name, value).as_str());
+                }
+                head.push_str("\r\n");
+                let (before, after) = reply.body.split_at(reply.stall_after.min(reply.body.len()));
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(before);
+                let _ = stream.flush();
+                thread::sleep(reply.stall);
+                let _ = stream.write_all(after);
+            });
+        }
+    });
+    base
+}
+
+/// The path of `request` without the leading slash.
+pub fn path(request: &str) -> &str {
+    request
+        .split(' ')
+        .nth(1)
+        .unwrap_or("/")
+        .trim_start_matches('/')
+}
+
+/// The value of header `name` of `request`.
+pub fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
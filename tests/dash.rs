@@ -0,0 +1,266 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+mod hls {
+    #![allow(dead_code)]
+    include!("../src/util/hls.rs");
+}
+
+mod dash {
+    include!("../src/util/dash.rs");
+}
+
+use dash::{parse_duration, Manifest, TimelineNumbers};
+use hls::{format_date_time, new_segments, parse_date_time, QueryPropagation, UriResolver};
+use std::time::Duration;
+
+const ON_DEMAND: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT9S">
+  <BaseURL>audio/</BaseURL>
+  <Period>
+    <AdaptationSet contentType="video" mimeType="video/mp4">
+      <SegmentTemplate media="v-$Number$.m4s" duration="2"/>
+      <Representation id="v1" bandwidth="3000000"/>
+    </AdaptationSet>
+    <AdaptationSet contentType="audio" lang="en">
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011"/>
+      <SegmentTemplate media="drm-$Number$.m4s" duration="2"/>
+      <Representation id="drm" bandwidth="128000"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4" codecs="mp4a.40.2">
+      <SegmentTemplate timescale="1000" duration="4000" startNumber="1"
+          initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/$Number%05d$.m4s"/>
+      <Representation id="aac-64" bandwidth="64000"/>
+      <Representation id="aac-128" bandwidth="128000">
+        <BaseURL>hq/</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+#[test]
+fn test_on_demand() {
+    let manifest = Manifest::parse(ON_DEMAND, "https://cdn.example.com/show/manifest.mpd").unwrap();
+    assert!(!manifest.dynamic);
+    let variants = manifest.variants().variants;
+    let uris: Vec<&str> = variants.iter().map(|v| v.uri.as_str()).collect();
+    assert_eq!(uris, vec!["#aac-64", "#aac-128"]);
+    assert_eq!(variants[1].codecs.as_deref(), Some("mp4a.40.2"));
+
+    let list = manifest.media_playlist("aac-128", 0, &mut TimelineNumbers::default());
+    assert!(list.end_list);
+    assert_eq!(list.media_sequence, 1);
+    assert_eq!(list.target_duration, 4.0);
+    // 9 seconds in 4 second segments
+    let uris: Vec<&str> = list.segments.iter().map(|s| s.uri.as_str()).collect();
+    assert_eq!(
+        uris,
+        vec![
+            "https://cdn.example.com/show/audio/hq/aac-128/00001.m4s",
+            "https://cdn.example.com/show/audio/hq/aac-128/00002.m4s",
+            "https://cdn.example.com/show/audio/hq/aac-128/00003.m4s",
+        ]
+    );
+    assert_eq!(
+        list.segments[0].map.as_ref().unwrap().uri,
+        "https://cdn.example.com/show/audio/hq/aac-128/init.mp4"
+    );
+}
+
+const LIVE_TIMELINE: &str = r#"<MPD type="dynamic" availabilityStartTime="2024-05-01T18:00:00Z"
+    timeShiftBufferDepth="PT1M">
+  <Period id="1" start="PT0S">
+    <AdaptationSet contentType="audio">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="alternate"/>
+      <SegmentTemplate media="alt/$Time$.m4s" timescale="48000"/>
+      <Representation id="alt" bandwidth="96000"/>
+    </AdaptationSet>
+    <AdaptationSet contentType="audio">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+      <SegmentTemplate timescale="48000" startNumber="100" initialization="main/init.mp4"
+          media="main/$Time$.m4s">
+        <SegmentTimeline>
+          <S t="96000" d="96256" r="1"/>
+          <S d="95232"/>
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="main" bandwidth="128000" codecs="opus"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+#[test]
+fn test_live_timeline() {
+    let manifest = Manifest::parse(LIVE_TIMELINE, "http://radio.example.com/live.mpd").unwrap();
+    assert!(manifest.dynamic);
+    let list = manifest.media_playlist("main", 0, &mut TimelineNumbers::default());
+    assert!(!list.end_list);
+    // the first segment starts about one 2s segment into the period
+    assert_eq!(list.media_sequence, 101);
+    let uris: Vec<&str> = list.segments.iter().map(|s| s.uri.as_str()).collect();
+    assert_eq!(
+        uris,
+        vec![
+            "http://radio.example.com/main/96000.m4s",
+            "http://radio.example.com/main/192256.m4s",
+            "http://radio.example.com/main/288512.m4s",
+        ]
+    );
+    // two seconds after the availability start
+    let start = parse_date_time("2024-05-01T18:00:02Z").unwrap();
+    let date_time = list.segments[0].program_date_time.as_deref().unwrap();
+    assert_eq!(parse_date_time(date_time), Some(start));
+}
+
+#[test]
+fn test_live_timeline_reload() {
+    let url = "http://radio.example.com/live.mpd";
+    let manifest = Manifest::parse(LIVE_TIMELINE, url).unwrap();
+    let mut numbers = TimelineNumbers::default();
+    let list = manifest.media_playlist("main", 0, &mut numbers);
+    let resolver = UriResolver::new(url, QueryPropagation::Off);
+    let played = new_segments(&list, &resolver, Some(101));
+    let next = played.last().unwrap().sequence + 1;
+    // the window slid by one segment and one was added
+    let slid = LIVE_TIMELINE.replace(
+        r#"<S t="96000" d="96256" r="1"/>
+          <S d="95232"/>"#,
+        r#"<S t="192256" d="96256"/>
+          <S d="95232"/>
+          <S d="96256"/>"#,
+    );
+    let manifest = Manifest::parse(slid.as_str(), url).unwrap();
+    let list = manifest.media_playlist("main", 0, &mut numbers);
+    assert_eq!(list.media_sequence, 102);
+    let new: Vec<String> = new_segments(&list, &resolver, Some(next))
+        .into_iter()
+        .map(|segment| segment.url)
+        .collect();
+    assert_eq!(new, vec!["http://radio.example.com/main/383744.m4s"]);
+}
+
+// a live timeline listing `count` segments from the `first` one, their
+// durations alternating the way 1024 sample AAC frames round to 2s
+fn live_window(first: u64, count: u64) -> (String, Vec<u64>) {
+    let duration = |i: u64| if i.is_multiple_of(2) { 96256 } else { 95232 };
+    let mut time = 96000;
+    for i in 0..first {
+        time += duration(i);
+    }
+    let mut times = vec![];
+    let mut timeline = String::new();
+    for i in first..first + count {
+        timeline.push_str(format!(r#"<S t="{}" d="{}"/>"#, time, duration(i)).as_str());
+        times.push(time);
+        time += duration(i);
+    }
+    let manifest = format!(
+        r#"<MPD type="dynamic" availabilityStartTime="2024-05-01T18:00:00Z">
+  <Period start="PT0S">
+    <AdaptationSet contentType="audio">
+      <SegmentTemplate timescale="48000" startNumber="100" media="$Time$.m4s">
+        <SegmentTimeline>{}</SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="main" bandwidth="128000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        timeline
+    );
+    (manifest, times)
+}
+
+#[test]
+fn test_live_timeline_slides_far() {
+    let url = "http://radio.example.com/live.mpd";
+    let resolver = UriResolver::new(url, QueryPropagation::Off);
+    let mut numbers = TimelineNumbers::default();
+    let (text, _) = live_window(0, 5);
+    let list = Manifest::parse(text.as_str(), url)
+        .unwrap()
+        .media_playlist("main", 0, &mut numbers);
+    let mut next = list.media_sequence as u64 + 5;
+    // every reload the window slides by one segment: only the added one is
+    // new, however far the segments drift from the usual duration
+    for first in 1..=150 {
+        let (text, times) = live_window(first, 5);
+        let list =
+            Manifest::parse(text.as_str(), url)
+                .unwrap()
+                .media_playlist("main", 0, &mut numbers);
+        let new: Vec<String> = new_segments(&list, &resolver, Some(next))
+            .into_iter()
+            .map(|segment| segment.url)
+            .collect();
+        let last = format!("http://radio.example.com/{}.m4s", times[4]);
+        assert_eq!(new, vec![last], "window from segment {}", first);
+        next += 1;
+    }
+    // after missing reloads the window moved past everything numbered,
+    // numbers continue after the last one
+    let (text, times) = live_window(160, 5);
+    let list = Manifest::parse(text.as_str(), url)
+        .unwrap()
+        .media_playlist("main", 0, &mut numbers);
+    assert!(list.media_sequence as u64 >= next);
+    let new = new_segments(&list, &resolver, Some(next));
+    assert_eq!(new.len(), 5);
+    assert!(new[0].url.ends_with(format!("/{}.m4s", times[0]).as_str()));
+}
+
+const LIVE_NUMBER: &str = r#"<MPD type="dynamic" availabilityStartTime="2024-05-01T18:00:00Z"
+    timeShiftBufferDepth="PT10S">
+  <Period start="PT0S">
+    <AdaptationSet mimeType="audio/mp4">
+      <SegmentTemplate media="seg-$Number$.m4s" duration="4" startNumber="0"/>
+      <Representation id="a" bandwidth="64000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+#[test]
+fn test_live_number() {
+    let manifest = Manifest::parse(LIVE_NUMBER, "http://radio.example.com/live.mpd").unwrap();
+    // 61 seconds in: 15 complete segments, the last two fit the 10s window
+    let now = parse_date_time("2024-05-01T18:01:01Z").unwrap();
+    let list = manifest.media_playlist("a", now, &mut TimelineNumbers::default());
+    assert_eq!(list.media_sequence, 13);
+    let uris: Vec<&str> = list.segments.iter().map(|s| s.uri.as_str()).collect();
+    assert_eq!(
+        uris,
+        vec![
+            "http://radio.example.com/seg-13.m4s",
+            "http://radio.example.com/seg-14.m4s",
+        ]
+    );
+}
+
+#[test]
+fn test_time() {
+    assert_eq!(
+        parse_duration("PT1H2M3.5S"),
+        Some(Duration::from_millis(3_723_500))
+    );
+    assert_eq!(
+        parse_duration("P1DT12H"),
+        Some(Duration::from_secs(129_600))
+    );
+    assert_eq!(parse_duration("P1Y"), None);
+    let millis = parse_date_time("2024-02-29T23:59:58.250+01:00").unwrap();
+    assert_eq!(format_date_time(millis), "2024-02-29T22:59:58.250Z");
+    assert!(Manifest::parse("<MPD type=\"static\"/>", "http://a/").is_err());
+}
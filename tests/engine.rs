@@ -0,0 +1,55 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+mod engine {
+    #![allow(dead_code)]
+    include!("../src/media/engine.rs");
+}
+
+use engine::{Focus, FocusPolicy};
+
+#[test]
+fn test_focus_policy() {
+    assert_eq!(
+        FocusPolicy::parse("Exclusive", 0.2),
+        Some(FocusPolicy::Exclusive)
+    );
+    assert_eq!(
+        FocusPolicy::parse("duck", 0.35),
+        Some(FocusPolicy::Duck(0.35))
+    );
+    assert_eq!(FocusPolicy::parse("mix", 0.2), None);
+
+    assert_eq!(FocusPolicy::parse_duck_level("50%"), Some(0.5));
+    assert_eq!(FocusPolicy::parse_duck_level(" 25 "), Some(0.25));
+    assert_eq!(FocusPolicy::parse_duck_level("250"), Some(1.0));
+    assert_eq!(FocusPolicy::parse_duck_level("half"), None);
+}
+
+#[test]
+fn test_ducking() {
+    let duck = FocusPolicy::Duck(0.2);
+    // the source without focus is ducked while the focused one plays
+    assert_eq!(duck.ducked(Focus::Radio, true), (Some(0.2), None));
+    assert_eq!(duck.ducked(Focus::Music, true), (None, Some(0.2)));
+    assert_eq!(duck.ducked(Focus::Music, false), (None, None));
+    // exclusive pauses instead
+    assert_eq!(
+        FocusPolicy::Exclusive.ducked(Focus::Radio, true),
+        (None, None)
+    );
+}
@@ -0,0 +1,450 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use aes::cipher::BlockEncryptMut;
+
+mod common;
+
+use common::serve;
+
+include!("../src/util/hls.rs");
+
+mod net {
+    #![allow(dead_code)]
+    include!("../src/util/net.rs");
+}
+
+const MASTER: &str = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.5\"
+low/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=256000,CODECS=\"mp4a.40.2\"
+high/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\"
+mid/index.m3u8
+";
+
+fn master() -> MasterPlaylist {
+    match m3u8_rs::parse_playlist_res(MASTER.as_bytes()).unwrap() {
+        m3u8_rs::Playlist::MasterPlaylist(master) => master,
+        m3u8_rs::Playlist::MediaPlaylist(_) => panic!("not a master playlist"),
+    }
+}
+
+#[test]
+fn test_select_variant() {
+    let master = master();
+    assert_eq!(select_variant(&master, VariantPolicy::Highest), Some(1));
+    assert_eq!(select_variant(&master, VariantPolicy::Lowest), Some(0));
+    let closest = VariantPolicy::parse("150").unwrap();
+    assert_eq!(closest, VariantPolicy::Closest(150_000));
+    assert_eq!(closest.setting(), "150");
+    assert_eq!(select_variant(&master, closest), Some(2));
+    assert_eq!(VariantPolicy::parse("fastest"), None);
+    assert_eq!(next_variant(&master.variants, 2), Some(0));
+    assert_eq!(variant_label(&master.variants[1]), "256 kbps, mp4a.40.2");
+}
+
+#[test]
+fn test_resolve_uri() {
+    let url = "http://radio.example/live/master.m3u8?token=abc";
+    assert_eq!(
+        resolve_uri(url, "high/index.m3u8"),
+        "http://radio.example/live/high/index.m3u8"
+    );
+    assert_eq!(
+        resolve_uri(url, "https://cdn.example/a.m3u8"),
+        "https://cdn.example/a.m3u8"
+    );
+    assert_eq!(
+        resolve_uri(url, "/other/seg.ts"),
+        "http://radio.example/other/seg.ts"
+    );
+    assert_eq!(
+        resolve_uri(url, "../seg.ts?x=1"),
+        "http://radio.example/seg.ts?x=1"
+    );
+    assert_eq!(
+        resolve_uri(url, "//cdn.example/seg.ts"),
+        "http://cdn.example/seg.ts"
+    );
+}
+
+#[test]
+fn test_query_propagation() {
+    let url = "http://radio.example/live/index.m3u8?token=abc&exp=1";
+    let off = UriResolver::new(url, QueryPropagation::Off);
+    assert_eq!(off.resolve("a.ts"), "http://radio.example/live/a.ts");
+    let missing = UriResolver::new(url, QueryPropagation::parse("missing").unwrap());
+    assert_eq!(
+        missing.resolve("a.ts"),
+        "http://radio.example/live/a.ts?token=abc&exp=1"
+    );
+    assert_eq!(
+        missing.resolve("a.ts?token=own"),
+        "http://radio.example/live/a.ts?token=own"
+    );
+    let merge = UriResolver::new(url, QueryPropagation::Merge);
+    assert_eq!(
+        merge.resolve("a.ts?token=own"),
+        "http://radio.example/live/a.ts?token=own&exp=1"
+    );
+}
+
+const LIVE: &str = "#EXTM3U
+#EXT-X-TARGETDURATION:4
+#EXT-X-MEDIA-SEQUENCE:100
+#EXTINF:4.0,
+a.ts
+#EXT-X-BYTERANGE:1000@0
+#EXTINF:4.0,
+b.ts
+#EXT-X-BYTERANGE:500
+#EXTINF:4.0,
+b.ts
+#EXT-X-DISCONTINUITY
+#EXTINF:4.0,
+c.ts
+#EXTINF:4.0,
+d.ts
+";
+
+fn media(text: &str) -> MediaPlaylist {
+    m3u8_rs::parse_media_playlist_res(text.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_new_segments() {
+    let list = media(LIVE);
+    let url = &UriResolver::new(
+        "http://radio.example/live/index.m3u8",
+        QueryPropagation::Off,
+    );
+    // live playback starts three segments before the end
+    let start = new_segments(&list, url, None);
+    assert_eq!(start.len(), 3);
+    assert_eq!(start[0].sequence, 102);
+    assert_eq!(start[0].byte_range, Some((1000, 500)));
+
+    assert_eq!(new_segments(&list, url, Some(104)).len(), 1);
+    assert!(new_segments(&list, url, Some(105)).is_empty());
+    // fell out of the window: continue from the oldest segment
+    assert_eq!(new_segments(&list, url, Some(90)).len(), 5);
+    // playlist restarted with smaller numbers
+    assert_eq!(new_segments(&list, url, Some(400))[0].sequence, 102);
+
+    let mut vod = list.clone();
+    vod.end_list = true;
+    assert_eq!(new_segments(&vod, url, None).len(), 5);
+    assert_eq!(playlist_duration(&vod), Duration::from_secs(20));
+}
+
+#[test]
+fn test_program_date_time() {
+    assert_eq!(parse_date_time("1970-01-01T00:00:01.5Z"), Some(1500));
+    assert_eq!(
+        parse_date_time("2024-05-01T22:15:03+02:00"),
+        parse_date_time("2024-05-01T20:15:03.000Z")
+    );
+    assert_eq!(
+        parse_date_time("2024-05-01T20:15:03Z"),
+        Some(1_714_594_503_000)
+    );
+    assert_eq!(parse_date_time("yesterday"), None);
+    for (date, days) in [
+        ((1970, 1, 1), 0),
+        ((1969, 12, 31), -1),
+        ((2000, 2, 29), 11_016),
+        ((2024, 5, 1), 19_844),
+    ] {
+        assert_eq!(days_from_civil(date.0, date.1, date.2), days);
+        assert_eq!(civil_from_days(days), date);
+    }
+
+    let text = LIVE.replace(
+        "#EXTINF:4.0,\na.ts",
+        "#EXT-X-PROGRAM-DATE-TIME:2024-05-01T20:15:03Z\n#EXTINF:4.0,\na.ts",
+    );
+    let url = &UriResolver::new("http://radio.example/index.m3u8", QueryPropagation::Off);
+    let segments = segment_refs(&media(text.as_str()), url);
+    // later segments continue from the tagged one
+    assert_eq!(segments[0].date_time, Some(1_714_594_503_000));
+    assert_eq!(segments[2].date_time, Some(1_714_594_511_000));
+    assert_eq!(segment_refs(&media(LIVE), url)[0].date_time, None);
+}
+
+#[test]
+fn test_reload_scheduler() {
+    let mut list = media(LIVE);
+    let mut reload = ReloadScheduler::new();
+    assert_eq!(reload.loaded(&list), Duration::from_secs(4));
+    assert_eq!(reload.loaded(&list), Duration::from_secs(2));
+    assert_eq!(reload.loaded(&list), Duration::from_secs(4));
+    assert_eq!(reload.loaded(&list), Duration::from_secs(8));
+    assert_eq!(reload.loaded(&list), Duration::from_secs(12));
+    list.media_sequence += 1;
+    assert_eq!(reload.loaded(&list), Duration::from_secs(4));
+}
+
+fn encrypt(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Vec<u8> {
+    cbc::Encryptor::<aes::Aes128>::new_from_slices(key, iv)
+        .unwrap()
+        .encrypt_padded_vec_mut::<Pkcs7>(data)
+}
+
+const ENCRYPTED: &str = "#EXTM3U
+#EXT-X-TARGETDURATION:4
+#EXT-X-MEDIA-SEQUENCE:7
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"
+#EXTINF:4.0,
+seg7.ts
+#EXTINF:4.0,
+seg8.ts
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=0x0102030405060708090a0b0c0d0e0f10
+#EXTINF:4.0,
+seg9.ts
+#EXT-X-KEY:METHOD=NONE
+#EXTINF:4.0,
+seg10.ts
+#EXT-X-ENDLIST
+";
+
+#[test]
+fn test_aes128_segments() {
+    let key = [0x2au8; 16];
+    let explicit_iv = parse_iv("0x0102030405060708090a0b0c0d0e0f10").unwrap();
+    assert_eq!(explicit_iv[0], 1);
+    assert_eq!(explicit_iv[15], 0x10);
+    let plain: Vec<Vec<u8>> = (7u8..11).map(|i| vec![i; 1000 + i as usize]).collect();
+    let served = vec![
+        ("index.m3u8", ENCRYPTED.as_bytes().to_vec()),
+        ("key.bin", key.to_vec()),
+        // no IV attribute: the media sequence number is the IV
+        ("seg7.ts", encrypt(&plain[0], &key, &7u128.to_be_bytes())),
+        ("seg8.ts", encrypt(&plain[1], &key, &8u128.to_be_bytes())),
+        ("seg9.ts", encrypt(&plain[2], &key, &explicit_iv)),
+        ("seg10.ts", plain[3].clone()),
+    ];
+    let base = serve(served);
+
+    let (text, final_url) = net::fetch_text(format!("{}index.m3u8", base).as_str(), &[]).unwrap();
+    let list = media(text.as_str());
+    let resolver = UriResolver::new(final_url.as_str(), QueryPropagation::Off);
+    let segments = segment_refs(&list, &resolver);
+    assert_eq!(
+        segments[1].encryption,
+        Encryption::Aes128 {
+            key_url: format!("{}key.bin", base),
+            iv: 8u128.to_be_bytes(),
+        }
+    );
+    assert_eq!(segments[3].encryption, Encryption::None);
+
+    let mut cache = FetchCache::default();
+    let mut key_fetches = 0;
+    for (segment, plain) in segments.iter().zip(&plain) {
+        let data = fetch(segment.url.as_str(), None).unwrap();
+        let data = segment
+            .prepare(data, &mut cache, |url, range| {
+                key_fetches += 1;
+                fetch(url, range)
+            })
+            .unwrap();
+        assert_eq!(&data[..], &plain[..]);
+    }
+    assert_eq!(key_fetches, 1);
+
+    // a wrong key is reported instead of handing garbage to the decoder
+    let mut wrong = FetchCache::default();
+    let data = fetch(segments[0].url.as_str(), None).unwrap();
+    assert!(segments[0]
+        .prepare(data, &mut wrong, |_, _| Ok(Bytes::from(vec![0u8; 16])))
+        .is_err());
+}
+
+fn fetch(url: &str, range: Option<(u64, u64)>) -> Result<Bytes, failure::Error> {
+    let http = net::http();
+    Ok(http.block_on(http.fetch_range(url, range, &[]))?)
+}
+
+const FMP4: &str = "#EXTM3U
+#EXT-X-TARGETDURATION:4
+#EXT-X-MEDIA-SEQUENCE:1
+#EXT-X-MAP:URI=\"init.mp4\"
+#EXTINF:4.0,
+a.m4s
+#EXTINF:4.0,
+b.m4s
+#EXT-X-DISCONTINUITY
+#EXT-X-MAP:URI=\"stream.mp4\",BYTERANGE=\"4@0\"
+#EXTINF:4.0,
+#EXT-X-BYTERANGE:3@4
+stream.mp4
+";
+
+#[test]
+fn test_fmp4_init_sections() {
+    let base = serve(vec![
+        ("init.mp4", b"moov".to_vec()),
+        ("a.m4s", b"moof-a".to_vec()),
+        ("b.m4s", b"moof-b".to_vec()),
+        ("stream.mp4", b"ftypabc".to_vec()),
+    ]);
+    let list = media(FMP4);
+    let resolver = UriResolver::new(
+        format!("{}index.m3u8", base).as_str(),
+        QueryPropagation::Off,
+    );
+    let segments = segment_refs(&list, &resolver);
+    // the map carries over to following segments
+    assert_eq!(segments[0].init, segments[1].init);
+    assert_eq!(segments[2].init.as_ref().unwrap().byte_range, Some((0, 4)));
+
+    let mut cache = FetchCache::default();
+    let mut init_fetches = 0;
+    let expected: [&[u8]; 3] = [b"moovmoof-a", b"moovmoof-b", b"ftypabc"];
+    for (segment, expected) in segments.iter().zip(expected) {
+        let data = fetch(segment.url.as_str(), segment.byte_range).unwrap();
+        let data = segment
+            .prepare(data, &mut cache, |url, range| {
+                init_fetches += 1;
+                fetch(url, range)
+            })
+            .unwrap();
+        assert_eq!(&data[..], expected);
+    }
+    assert_eq!(init_fetches, 2);
+}
+
+const LOW_LATENCY: &str = "#EXTM3U
+#EXT-X-TARGETDURATION:4
+#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK=3.0
+#EXT-X-PART-INF:PART-TARGET=1.0
+#EXT-X-MEDIA-SEQUENCE:20
+#EXTINF:4.0,
+s20.ts
+#EXT-X-PART:DURATION=1.0,URI=\"s21.0.ts\",INDEPENDENT=YES
+#EXT-X-PART:DURATION=1.0,URI=\"s21.1.ts\"
+#EXT-X-PART:DURATION=1.0,URI=\"s21.2.ts\",INDEPENDENT=YES
+#EXT-X-PART:DURATION=1.0,URI=\"s21.3.ts\"
+#EXTINF:4.0,
+s21.ts
+#EXT-X-PART:DURATION=1.0,URI=\"s22.0.ts\",INDEPENDENT=YES
+#EXT-X-PART:DURATION=1.0,URI=\"s22.1.ts\"
+#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"s22.2.ts\"
+";
+
+#[test]
+fn test_low_latency() {
+    let list = media(LOW_LATENCY);
+    let ll = LowLatency::parse(LOW_LATENCY);
+    assert!(ll.can_block_reload);
+    assert_eq!(ll.part_hold_back, Some(Duration::from_secs(3)));
+    assert_eq!(ll.parts.len(), 6);
+    assert_eq!((ll.parts[5].sequence, ll.parts[5].index), (22, 1));
+    assert_eq!(ll.preload_hint.as_ref().unwrap().uri, "s22.2.ts");
+    assert_eq!(LatencyMode::parse("low"), Some(LatencyMode::Low));
+
+    let url = &UriResolver::new("http://radio.example/ll/index.m3u8", QueryPropagation::Off);
+    // three parts back is s21.3, not independent, so s21.2
+    let start = low_latency_segments(&list, &ll, url, None);
+    let names: Vec<&str> = start
+        .iter()
+        .map(|s| s.url.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["s21.2.ts", "s21.3.ts", "s22.0.ts", "s22.1.ts", "s22.2.ts"]
+    );
+    // after the hint, wait for the part following it
+    let cursor = next_cursor(&list, &ll, start.last().unwrap());
+    assert_eq!(cursor, (22, 3));
+    assert!(low_latency_segments(&list, &ll, url, Some(cursor)).is_empty());
+    // the last part of a complete segment moves on to the next segment
+    assert_eq!(next_cursor(&list, &ll, &start[1]), (22, 0));
+    assert_eq!(
+        low_latency_segments(&list, &ll, url, Some((22, 0))).len(),
+        3
+    );
+    // whole segments where no parts are listed
+    assert_eq!(
+        low_latency_segments(&list, &ll, url, Some((20, 0)))[0].part,
+        None
+    );
+
+    assert_eq!(
+        blocking_reload_url("http://radio.example/ll/index.m3u8?t=1&_HLS_msn=3", cursor),
+        "http://radio.example/ll/index.m3u8?t=1&_HLS_msn=22&_HLS_part=3"
+    );
+    // the server may hold it three target durations, longer than a read
+    let target = Duration::from_secs_f32(list.target_duration);
+    assert_eq!(ll.blocking_timeout(target), Duration::from_secs(13));
+}
+
+#[test]
+fn test_segment_queue() {
+    let list = media(LIVE);
+    let url = &UriResolver::new(
+        "http://radio.example/live/index.m3u8",
+        QueryPropagation::Off,
+    );
+    let mut queue = SegmentQueue::default();
+    assert_eq!(queue.push(new_segments(&list, url, Some(100)), 100..105), 0);
+    // a reload listing the same segments again adds nothing
+    queue.push(new_segments(&list, url, Some(103)), 100..105);
+    assert_eq!(queue.pop().unwrap().sequence, 100);
+    // the window moved on while downloads were behind
+    assert_eq!(queue.push(vec![], 103..108), 2);
+    assert_eq!(queue.pop().unwrap().sequence, 103);
+    assert_eq!(queue.pop().unwrap().sequence, 104);
+    assert!(queue.is_empty());
+    // restarted stream with smaller numbers is accepted
+    let mut restarted = list.clone();
+    restarted.media_sequence = 0;
+    queue.push(new_segments(&restarted, url, None), 0..5);
+    assert_eq!(queue.pop().unwrap().sequence, 2);
+}
+
+#[test]
+fn test_backoff() {
+    assert_eq!(retry_delay(0), Duration::from_millis(250));
+    assert_eq!(retry_delay(2), Duration::from_secs(1));
+    assert_eq!(retry_delay(10), Duration::from_secs(4));
+    assert_eq!(reconnect_delay(0), Duration::from_secs(1));
+    assert_eq!(reconnect_delay(3), Duration::from_secs(8));
+    assert_eq!(reconnect_delay(20), Duration::from_secs(30));
+}
+
+#[test]
+fn test_reconnects() {
+    let mut reconnects = Reconnects::default();
+    // audio without a lost connection is no reconnect
+    assert!(!reconnects.connected());
+    assert_eq!(reconnects.lost(), Some((1, Duration::from_secs(1))));
+    assert_eq!(reconnects.lost(), Some((2, Duration::from_secs(2))));
+    assert_eq!(reconnects.attempts(), 2);
+    assert!(reconnects.connected());
+    assert_eq!(reconnects.attempts(), 0);
+
+    let delays: Vec<Duration> = (0..MAX_RECONNECTS)
+        .map(|_| reconnects.lost().unwrap().1)
+        .collect();
+    assert_eq!(delays[5], Duration::from_secs(30));
+    // gives up after MAX_RECONNECTS in a row
+    assert_eq!(reconnects.lost(), None);
+    assert!(reconnects.connected());
+    assert!(reconnects.lost().is_some());
+}
@@ -0,0 +1,64 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/icy.rs");
+
+use std::io::Cursor;
+use std::sync::mpsc::channel;
+
+fn metadata(text: &str) -> Vec<u8> {
+    let blocks = text.len().div_ceil(16);
+    let mut meta = vec![blocks as u8];
+    meta.extend_from_slice(text.as_bytes());
+    meta.resize(1 + blocks * 16, 0);
+    meta
+}
+
+#[test]
+fn test_parse_stream_title() {
+    let title = parse_stream_title(b"StreamTitle='Artist - It's Here';StreamUrl='';\0\0");
+    assert_eq!(title.as_deref(), Some("Artist - It's Here"));
+    assert_eq!(parse_stream_title(b"StreamTitle='';"), None);
+    assert_eq!(parse_stream_title(b""), None);
+    // Latin-1
+    assert_eq!(
+        parse_stream_title(b"StreamTitle='Caf\xe9';").as_deref(),
+        Some("Café")
+    );
+}
+
+#[test]
+fn test_icy_reader() {
+    let mut stream = vec![1u8; 8];
+    stream.extend(metadata("StreamTitle='First';"));
+    stream.extend(vec![2u8; 8]);
+    stream.push(0);
+    stream.extend(vec![3u8; 8]);
+    stream.extend(metadata("StreamTitle='First';"));
+    stream.extend(vec![4u8; 4]);
+    let (tx, titles) = channel();
+    let mut reader = IcyReader::new(Cursor::new(stream), Some(8), tx);
+    let mut audio = vec![];
+    reader.read_to_end(&mut audio).unwrap();
+    let mut expected = vec![1u8; 8];
+    expected.extend(vec![2u8; 8]);
+    expected.extend(vec![3u8; 8]);
+    expected.extend(vec![4u8; 4]);
+    assert_eq!(audio, expected);
+    // repeated titles are sent once
+    assert_eq!(titles.try_iter().collect::<Vec<_>>(), vec!["First"]);
+}
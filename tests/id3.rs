@@ -0,0 +1,133 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/id3.rs");
+
+fn syncsafe_bytes(n: usize) -> [u8; 4] {
+    [
+        (n >> 21) as u8 & 0x7f,
+        (n >> 14) as u8 & 0x7f,
+        (n >> 7) as u8 & 0x7f,
+        n as u8 & 0x7f,
+    ]
+}
+
+fn frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut frame = id.to_vec();
+    frame.extend(syncsafe_bytes(body.len()));
+    frame.extend([0, 0]);
+    frame.extend(body);
+    frame
+}
+
+fn id3_tag(frames: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = frames.concat();
+    let mut tag = b"ID3\x04\x00\x00".to_vec();
+    tag.extend(syncsafe_bytes(body.len()));
+    tag.extend(body);
+    tag
+}
+
+fn ts_packet(pid: u16, start: bool, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0x47, ((start as u8) << 6) | (pid >> 8) as u8, pid as u8];
+    let room = 184;
+    let payload = &payload[..payload.len().min(room)];
+    if payload.len() == room {
+        packet.push(0x10);
+    } else {
+        // adaptation field stuffing up to the payload
+        packet.push(0x30);
+        let stuffing = room - payload.len() - 1;
+        packet.push(stuffing as u8);
+        if stuffing > 0 {
+            packet.push(0);
+            packet.extend(vec![0xff; stuffing - 1]);
+        }
+    }
+    packet.extend(payload);
+    packet
+}
+
+fn psi(table_id: u8, body: &[u8]) -> Vec<u8> {
+    let length = 5 + body.len() + 4;
+    let mut section = vec![0, table_id, 0xb0 | (length >> 8) as u8, length as u8];
+    section.extend([0, 1, 0xc1, 0, 0]);
+    section.extend(body);
+    section.extend([0; 4]);
+    section
+}
+
+fn pes(stream_id: u8, pts: u64, data: &[u8]) -> Vec<u8> {
+    let mut pes = vec![0, 0, 1, stream_id, 0, 0, 0x80, 0x80, 5];
+    pes.extend([
+        0x21 | ((pts >> 29) as u8 & 0x0e),
+        (pts >> 22) as u8,
+        0x01 | ((pts >> 14) as u8 & 0xfe),
+        (pts >> 7) as u8,
+        0x01 | ((pts << 1) as u8 & 0xfe),
+    ]);
+    pes.extend(data);
+    pes
+}
+
+#[test]
+fn test_packed_audio_id3() {
+    let mut timestamp = vec![];
+    timestamp.extend(b"com.apple.streaming.transportStreamTimestamp\0");
+    timestamp.extend(900_000u64.to_be_bytes());
+    let mut data = id3_tag(&[
+        frame(b"TIT2", b"\x03Blue in Green\0"),
+        frame(b"TPE1", b"\x01\xff\xfeM\0i\0l\0e\0s\0"),
+        frame(b"PRIV", &timestamp),
+    ]);
+    data.extend([0xff, 0xf1, 0x50, 0x80]);
+    let tags = segment_id3(&data);
+    assert_eq!(tags.len(), 1);
+    let tag = &tags[0].tag;
+    assert_eq!(tag.title.as_deref(), Some("Blue in Green"));
+    assert_eq!(tag.artist.as_deref(), Some("Miles"));
+    assert_eq!(tag.transport_timestamp(), Some(900_000));
+    assert_eq!(tags[0].offset, Duration::ZERO);
+}
+
+#[test]
+fn test_ts_id3() {
+    let pat = psi(0, &[0, 1, 0xe1, 0x00]);
+    let mut pmt_body = vec![0xe1, 0x01, 0xf0, 0x00];
+    pmt_body.extend([0x0f, 0xe1, 0x01, 0xf0, 0x00]);
+    pmt_body.extend([0x15, 0xe1, 0x02, 0xf0, 0x00]);
+    let pmt = psi(2, &pmt_body);
+    let tag = id3_tag(&[
+        frame(b"TIT2", b"\x00Morning Show\0"),
+        frame(b"TALB", b"\x03Breakfast\0"),
+    ]);
+    let mut data = vec![];
+    data.extend(ts_packet(0, true, &pat));
+    data.extend(ts_packet(0x100, true, &pmt));
+    data.extend(ts_packet(0x101, true, &pes(0xc0, 900_000, &[0xff, 0xf1])));
+    data.extend(ts_packet(
+        0x102,
+        true,
+        &pes(0xbd, 900_000 + 3 * 90_000, &tag),
+    ));
+    let tags = segment_id3(&data);
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].offset, Duration::from_secs(3));
+    assert_eq!(tags[0].tag.title.as_deref(), Some("Morning Show"));
+    assert_eq!(tags[0].tag.programme.as_deref(), Some("Breakfast"));
+    assert!(segment_id3(b"not a segment").is_empty());
+}
@@ -22,3 +22,111 @@ fn test_lyrics() {
     let lrc = Lyrics::from_music_path("assets/test.lrc");
     assert_ne!(lrc.count(), 0)
 }
+
+#[test]
+fn test_bilingual_lyrics() {
+    let lrc = Lyrics::from_music_path("assets/bilingual.mp3");
+    assert_eq!(lrc.count(), 3);
+    let first = &lrc.list[0];
+    assert_eq!(first.content, "君の名は");
+    assert_eq!(first.translation.as_deref(), Some("Your name"));
+    let second = &lrc.list[1];
+    assert_eq!(second.time, Duration::from_millis(5500));
+    assert_eq!(second.translation.as_deref(), Some("I had a dream"));
+    assert_eq!(lrc.list[2].translation, None);
+    assert_eq!(
+        first.lines(LyricsMode::Translation),
+        (None, Some("Your name"))
+    );
+    assert_eq!(lrc.list[2].lines(LyricsMode::Translation), (Some(""), None));
+}
+
+#[test]
+fn test_srt_lyrics() {
+    let lrc = Lyrics::from_music_path("assets/subtitle.mp3");
+    assert!(lrc.synced);
+    assert_eq!(lrc.count(), 2);
+    let first = &lrc.list[0];
+    assert_eq!(first.time, Duration::from_millis(1000));
+    assert_eq!(first.end, Some(Duration::from_millis(3500)));
+    assert_eq!(first.content, "First line");
+    assert_eq!(first.translation.as_deref(), Some("第一行"));
+}
+
+#[test]
+fn test_vtt_lyrics() {
+    let mut f = File::open("assets/subtitle.vtt").unwrap();
+    let mut text = String::new();
+    f.read_to_string(&mut text).unwrap();
+    let lrc = Lyrics::from_vtt(text.as_str());
+    assert_eq!(lrc.count(), 2);
+    assert_eq!(lrc.list[0].time, Duration::from_millis(1500));
+    assert_eq!(lrc.list[0].content, "Hello");
+    assert_eq!(lrc.list[1].time, Duration::from_millis(3_602_250));
+    assert_eq!(lrc.list[1].content, "World");
+}
+
+#[test]
+fn test_plain_lyrics() {
+    let lrc = Lyrics::from_music_path("assets/plain.flac");
+    assert!(!lrc.synced);
+    assert_eq!(lrc.count(), 2);
+    assert_eq!(lrc.list[1].content, "Plain second line");
+}
+
+#[test]
+fn test_lyrics_finder() {
+    let root = std::env::temp_dir().join(format!("rustplayer-lyrics-{}", std::process::id()));
+    let music_dir = root.join("music");
+    let lyrics_dir = root.join("lyrics");
+    std::fs::create_dir_all(&music_dir).unwrap();
+    std::fs::create_dir_all(&lyrics_dir).unwrap();
+    std::fs::write(music_dir.join("Ｐｅｔｅｒ - Song (Live).mp3"), b"").unwrap();
+    std::fs::write(music_dir.join("exact.mp3"), b"").unwrap();
+    std::fs::write(music_dir.join("later.mp3"), b"").unwrap();
+    std::fs::write(lyrics_dir.join("peter_song live.lrc"), "[00:01.000]fuzzy").unwrap();
+    std::fs::write(
+        lyrics_dir.join("exact.srt"),
+        "1\n00:00:01,000 --> 00:00:02,000\nexact\n",
+    )
+    .unwrap();
+
+    let mut finder = LyricsFinder::new(vec![lyrics_dir.clone()]);
+    let fuzzy = music_dir.join("Ｐｅｔｅｒ - Song (Live).mp3");
+    let lrc = finder.find(fuzzy.to_str().unwrap());
+    assert_eq!(lrc.list[0].content, "fuzzy");
+    let lrc = finder.find(music_dir.join("exact.mp3").to_str().unwrap());
+    assert_eq!(lrc.list[0].content, "exact");
+
+    // resolved paths are cached per track
+    std::fs::write(
+        lyrics_dir.join("Ｐｅｔｅｒ - Song (Live).lrc"),
+        "[00:01.000]new",
+    )
+    .unwrap();
+    let lrc = finder.find(fuzzy.to_str().unwrap());
+    assert_eq!(lrc.list[0].content, "fuzzy");
+    assert_eq!(normalize("Ａ ｂ,Ｃ"), "abc");
+
+    // misses are not, lyrics added later are found
+    let later = music_dir.join("later.mp3");
+    assert_eq!(finder.find(later.to_str().unwrap()).count(), 0);
+    std::fs::write(lyrics_dir.join("later.lrc"), "[00:01.000]later").unwrap();
+    let lrc = finder.find(later.to_str().unwrap());
+    assert_eq!(lrc.list[0].content, "later");
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_lrc_round_trip() {
+    let lrc = Lyrics::from_music_path("assets/bilingual.mp3");
+    let written = lrc.to_lrc();
+    assert!(written.starts_with("[00:01.000]君の名は\n[00:01.000]Your name\n"));
+    let parsed = Lyrics::from_lrc(written.as_str());
+    assert_eq!(parsed.count(), lrc.count());
+    assert_eq!(parsed.list[1].time, Duration::from_millis(5500));
+    assert_eq!(parsed.list[1].translation.as_deref(), Some("I had a dream"));
+    assert_eq!(parsed.index_at(Duration::from_millis(6000)), 1);
+    assert_eq!(parsed.index_at(Duration::ZERO), 0);
+}
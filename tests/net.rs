@@ -0,0 +1,138 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+#![allow(dead_code)]
+
+use std::io::Read;
+
+mod common;
+
+use common::{header, path, serve, serve_with, Reply};
+
+include!("../src/util/net.rs");
+
+// a client that gives up quickly
+fn client(read_timeout: time::Duration) -> Http {
+    let config = HttpConfig {
+        headers: vec![("X-Client".to_string(), "RustPlayer".to_string())],
+        read_timeout,
+        ..Default::default()
+    };
+    Http::new(&config).unwrap()
+}
+
+#[test]
+fn test_parse_headers() {
+    let headers = parse_headers(" Referer: http://radio.example/ ;X-Token:1;broken; ");
+    assert_eq!(
+        headers,
+        vec![
+            ("Referer".to_string(), "http://radio.example/".to_string()),
+            ("X-Token".to_string(), "1".to_string()),
+        ]
+    );
+    assert!(parse_headers("").is_empty());
+    // invalid names are kept and rejected when the client is set up
+    let config = HttpConfig {
+        headers: parse_headers("Bad Name: x"),
+        ..Default::default()
+    };
+    assert!(matches!(Http::new(&config), Err(NetError::Config(_))));
+}
+
+#[test]
+fn test_headers() {
+    let base = serve_with(|request| {
+        let echo = format!(
+            "{} {}",
+            header(request, "x-client").unwrap_or_default(),
+            header(request, "referer").unwrap_or_default()
+        );
+        Reply::ok(echo.into_bytes())
+    });
+    let http = client(time::Duration::from_secs(5));
+    let url = format!("{}radio", base);
+    let headers = [("Referer".to_string(), "http://radio.example/".to_string())];
+    let (text, final_url) = http.get_text(url.as_str(), &headers, None).unwrap();
+    assert_eq!(text, "RustPlayer http://radio.example/");
+    assert_eq!(final_url, url);
+}
+
+#[test]
+fn test_error_status() {
+    let base = serve_with(|request| match path(request) {
+        "gone" => Reply::status("404 Not Found", vec![]),
+        _ => Reply::status("503 Service Unavailable", b"busy".to_vec()),
+    });
+    let http = client(time::Duration::from_secs(5));
+    for (file, status) in [("gone", 404), ("busy", 503)] {
+        let url = format!("{}{}", base, file);
+        match http.get_text(url.as_str(), &[], None) {
+            Err(NetError::Status(code, at)) => {
+                assert_eq!(code.as_u16(), status);
+                assert_eq!(at, url);
+            }
+            other => panic!("{}: {:?}", file, other.map(|(text, _)| text)),
+        }
+        let range = http.block_on(http.fetch_range(url.as_str(), Some((0, 10)), &[]));
+        assert!(matches!(range, Err(NetError::Status(..))));
+    }
+    // the shared client of the readers
+    let url = format!("{}gone", base);
+    assert!(matches!(
+        BodyReader::open(url.as_str(), &[]),
+        Err(NetError::Status(..))
+    ));
+}
+
+#[test]
+fn test_timeouts() {
+    let base = serve_with(|request| {
+        let mut reply = Reply::ok(b"late".to_vec());
+        match path(request) {
+            "slow-head" => reply.delay = time::Duration::from_millis(800),
+            "slow-body" => reply.stall = time::Duration::from_millis(800),
+            _ => {}
+        }
+        reply
+    });
+    let http = client(time::Duration::from_millis(200));
+    for file in ["slow-head", "slow-body"] {
+        let url = format!("{}{}", base, file);
+        match http.get_text(url.as_str(), &[], None) {
+            Err(NetError::Timeout(at)) => assert_eq!(at, url),
+            other => panic!("{}: {:?}", file, other.map(|(text, _)| text)),
+        }
+    }
+    // a longer wait given for the request wins over the read timeout
+    let url = format!("{}slow-head", base);
+    let within = Some(time::Duration::from_secs(5));
+    let (text, _) = http.get_text(url.as_str(), &[], within).unwrap();
+    assert_eq!(text, "late");
+}
+
+#[test]
+fn test_body_reader() {
+    let audio: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+    let base = serve(vec![("stream", audio.clone())]);
+    let url = format!("{}stream", base);
+    let mut reader = BodyReader::open(url.as_str(), &[]).unwrap();
+    assert_eq!(reader.header("content-length").as_deref(), Some("5000"));
+    let mut body = vec![];
+    reader.read_to_end(&mut body).unwrap();
+    assert_eq!(body, audio);
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/playlist.rs");
+
+#[test]
+fn test_detect_kind() {
+    assert_eq!(
+        detect_kind("http://a/live/index.m3u8?token=1", None),
+        StreamKind::Hls
+    );
+    assert_eq!(
+        detect_kind("http://a/live", Some("application/vnd.apple.mpegurl")),
+        StreamKind::Hls
+    );
+    assert_eq!(
+        detect_kind("http://a/stream", Some("audio/mpeg")),
+        StreamKind::Progressive
+    );
+    assert_eq!(
+        detect_kind("http://a/stream.mp3", None),
+        StreamKind::Progressive
+    );
+    assert_eq!(
+        detect_kind("http://a/live/Manifest.MPD", None),
+        StreamKind::Dash
+    );
+    assert_eq!(
+        detect_kind("http://a/live", Some("application/dash+xml")),
+        StreamKind::Dash
+    );
+}
+
+#[test]
+fn test_pls() {
+    let pls = "[playlist]\nNumberOfEntries=3\n\
+        File1=http://ice1.example.com/groove\nTitle1=(#1 - 12/500) Groove Salad\n\
+        File2=http://ice2.example.com/groove\nTitle2=(#2 - 40/500) Groove Salad\n\
+        File3=http://example.com/drone\nTitle3=Drone Zone\nLength3=-1\nVersion=2\n";
+    let stations = parse_playlist("/tmp/groove.pls", pls);
+    assert_eq!(
+        stations,
+        vec![
+            ImportedStation {
+                name: "Groove Salad".to_string(),
+                urls: vec![
+                    "http://ice1.example.com/groove".to_string(),
+                    "http://ice2.example.com/groove".to_string(),
+                ],
+            },
+            ImportedStation {
+                name: "Drone Zone".to_string(),
+                urls: vec!["http://example.com/drone".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_m3u() {
+    let m3u = "#EXTM3U\n#EXTINF:-1 tvg-name=\"a,b\",Jazz FM\nhttp://jazz.example.com/live\n\
+        # a comment\nhttp://other.example.com:8000/stream\nrelative/stream\n";
+    let stations = parse_playlist("http://lists.example.com/radio/all.m3u", m3u);
+    let names: Vec<_> = stations.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["Jazz FM", "other.example.com", "lists.example.com"]
+    );
+    // relative entries follow the playlist, local files are not stations
+    assert_eq!(
+        stations[2].urls,
+        vec!["http://lists.example.com/radio/relative/stream"]
+    );
+    assert!(parse_playlist("/home/me/music.m3u", "/home/me/song.mp3\n").is_empty());
+    // untitled streams of one host are different stations
+    let m3u = "http://radio.example/jazz\nhttp://radio.example/rock\nhttp://radio.example/jazz\n";
+    let stations = parse_playlist("http://radio.example/all.m3u", m3u);
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[1].urls, vec!["http://radio.example/rock"]);
+    // an HLS playlist is one station
+    let hls = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nseg1.ts\n";
+    let stations = parse_playlist("http://a.example.com/live/index.m3u8", hls);
+    assert_eq!(stations.len(), 1);
+    assert_eq!(
+        stations[0].urls,
+        vec!["http://a.example.com/live/index.m3u8"]
+    );
+}
+
+#[test]
+fn test_xspf() {
+    let xspf = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Radios</title>
+  <trackList>
+    <track>
+      <location>http://a.example.com/rock?x=1&amp;y=2</location>
+      <location>http://b.example.com/rock</location>
+      <title>Rock &amp; Roll</title>
+    </track>
+    <track><location>http://c.example.com/news.m3u8</location></track>
+    <track><title>Local</title><location>file:///music/a.mp3</location></track>
+  </trackList>
+</playlist>"#;
+    let stations = parse_playlist("radios.xspf", xspf);
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[0].name, "Rock & Roll");
+    assert_eq!(
+        stations[0].urls,
+        vec![
+            "http://a.example.com/rock?x=1&y=2",
+            "http://b.example.com/rock"
+        ]
+    );
+    assert_eq!(stations[1].name, "c.example.com");
+}
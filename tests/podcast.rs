@@ -0,0 +1,397 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    thread,
+    time::{Duration, Instant},
+};
+
+mod common;
+
+use common::{serve, serve_with, Reply};
+
+mod util {
+    pub mod hls {
+        #![allow(dead_code)]
+        include!("../src/util/hls.rs");
+    }
+
+    pub mod net {
+        #![allow(dead_code)]
+        include!("../src/util/net.rs");
+    }
+
+    pub mod podcast {
+        include!("../src/util/podcast.rs");
+    }
+}
+
+mod episode {
+    #![allow(dead_code)]
+    include!("../src/media/episode.rs");
+}
+
+use episode::{EpisodeAudio, EpisodeDecoder, EpisodeDownload};
+use util::{
+    net,
+    podcast::{
+        file_name, format_date, is_opml, parse_clock, parse_feed, parse_opml, parse_rfc2822,
+        resume_offset, safe_file_name, Episode, EpisodeState, PodcastsFile,
+    },
+};
+
+const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+<channel>
+  <title>Rust Talk</title>
+  <item>
+    <title>Episode 1: Ownership</title>
+    <guid isPermaLink="false">rust-talk-1</guid>
+    <pubDate>Mon, 02 Jan 2023 08:00:00 +0000</pubDate>
+    <itunes:duration>45:30</itunes:duration>
+    <enclosure url="media/ep1.mp3" length="1234" type="audio/mpeg"/>
+  </item>
+  <item>
+    <title>Episode 2: Lifetimes</title>
+    <pubDate>Tue, 14 Feb 2023 20:30:00 +0100</pubDate>
+    <itunes:duration>3725</itunes:duration>
+    <enclosure url="https://cdn.example.com/ep2.m4a?token=1" type="audio/mp4"/>
+  </item>
+  <item>
+    <title>Show notes only</title>
+  </item>
+</channel>
+</rss>
+"#;
+
+const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom Cast</title>
+  <entry>
+    <title>First</title>
+    <id>urn:uuid:1</id>
+    <updated>2023-03-01T10:00:00Z</updated>
+    <link rel="alternate" href="https://example.com/first"/>
+    <link rel="enclosure" href="first.ogg" type="audio/ogg" length="99"/>
+  </entry>
+</feed>
+"#;
+
+const OPML: &str = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title>Subscriptions</title></head>
+  <body>
+    <outline text="Audio">
+      <outline type="rss" text="Rust Talk" xmlUrl="http://localhost/rust.xml"/>
+      <outline type="rss" title="Atom Cast" xmlUrl="http://localhost/atom.xml"/>
+    </outline>
+  </body>
+</opml>
+"#;
+
+#[test]
+fn test_parse_rss() {
+    let feed = parse_feed(RSS, "http://localhost/feeds/rust.xml").unwrap();
+    assert_eq!(feed.title, "Rust Talk");
+    // items without an enclosure are not episodes, newest first
+    assert_eq!(feed.episodes.len(), 2);
+    let (newer, older) = (&feed.episodes[0], &feed.episodes[1]);
+    assert_eq!(newer.title, "Episode 2: Lifetimes");
+    assert_eq!(newer.id, "https://cdn.example.com/ep2.m4a?token=1");
+    assert_eq!(newer.duration, Some(Duration::from_secs(3725)));
+    assert_eq!(
+        newer.published,
+        parse_rfc2822("Tue, 14 Feb 2023 19:30:00 GMT")
+    );
+    let name = file_name(newer);
+    assert!(name.starts_with("Episode 2_ Lifetimes "), "{}", name);
+    assert!(name.ends_with(".m4a"), "{}", name);
+    // the same title of another episode gets its own file
+    let rerun = Episode {
+        id: "rust-talk-2-rerun".to_string(),
+        ..newer.clone()
+    };
+    assert_ne!(file_name(&rerun), name);
+    assert_eq!(file_name(newer), name);
+    assert_eq!(older.id, "rust-talk-1");
+    assert_eq!(older.url, "http://localhost/feeds/media/ep1.mp3");
+    assert_eq!(older.length, Some(1234));
+    assert_eq!(older.duration, parse_clock("45:30"));
+    assert_eq!(format_date(older.published.unwrap()), "2023-01-02");
+}
+
+#[test]
+fn test_safe_file_name() {
+    assert_eq!(safe_file_name("Rust: Talk?", "podcast"), "Rust_ Talk_");
+    assert_eq!(safe_file_name("..", "podcast"), "podcast");
+    assert_eq!(safe_file_name(" . ", "podcast"), "podcast");
+    assert_eq!(safe_file_name("../..", "podcast"), "_");
+    assert_eq!(safe_file_name("...Dots... ", "podcast"), "Dots");
+}
+
+#[test]
+fn test_parse_clock() {
+    assert_eq!(parse_clock("90"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_clock(" 1:02:05 "), Some(Duration::from_secs(3725)));
+    assert_eq!(parse_clock("2:30.5"), Some(Duration::from_millis(150_500)));
+    assert_eq!(parse_clock("-5"), Some(Duration::ZERO));
+    for bad in ["", "1:xx", "inf", "1:-inf", "NaN", "1e30", "1e300:00"] {
+        assert_eq!(parse_clock(bad), None, "{:?}", bad);
+    }
+}
+
+#[test]
+fn test_parse_atom_and_opml() {
+    let feed = parse_feed(ATOM, "https://example.com/atom.xml").unwrap();
+    assert_eq!(feed.title, "Atom Cast");
+    assert_eq!(feed.episodes.len(), 1);
+    assert_eq!(feed.episodes[0].id, "urn:uuid:1");
+    assert_eq!(feed.episodes[0].url, "https://example.com/first.ogg");
+    assert_eq!(
+        format_date(feed.episodes[0].published.unwrap()),
+        "2023-03-01"
+    );
+
+    assert!(is_opml(OPML));
+    assert!(!is_opml(RSS));
+    let subscriptions = parse_opml(OPML).unwrap();
+    assert_eq!(subscriptions.len(), 2);
+    assert_eq!(subscriptions[0].title, "Rust Talk");
+    assert_eq!(subscriptions[1].url, "http://localhost/atom.xml");
+    assert_eq!(subscriptions[1].title, "Atom Cast");
+}
+
+#[test]
+fn test_podcasts_file() {
+    let text = r#"
+[[podcast]]
+url = "http://localhost/rust.xml"
+title = "Rust Talk"
+
+[episode."rust-talk-1"]
+position = 754
+
+[episode."rust-talk-2"]
+played = true
+file = "/music/Podcasts/Rust Talk/ep2.mp3"
+"#;
+    let file = PodcastsFile::parse(text).unwrap();
+    assert_eq!(file.podcast[0].title, "Rust Talk");
+    assert_eq!(
+        file.episode["rust-talk-1"],
+        EpisodeState {
+            position: 754,
+            ..Default::default()
+        }
+    );
+    assert!(file.episode["rust-talk-2"].played);
+    assert_eq!(PodcastsFile::parse(&file.to_toml().unwrap()).unwrap(), file);
+    assert!(PodcastsFile::parse("[[podcast]]\nurl = \"x\"\nvolume = 3\n").is_err());
+}
+
+#[test]
+fn test_fetch_feed() {
+    let audio: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+    let base = serve(vec![
+        ("feeds/rust.xml", RSS.as_bytes().to_vec()),
+        ("feeds/media/ep1.mp3", audio.clone()),
+    ]);
+    let (text, url) = net::fetch_text(format!("{}feeds/rust.xml", base).as_str(), &[]).unwrap();
+    let feed = parse_feed(text.as_str(), url.as_str()).unwrap();
+    let episode = &feed.episodes[1];
+    assert_eq!(episode.url, format!("{}feeds/media/ep1.mp3", base));
+
+    let dir = std::env::temp_dir().join(format!("rustplayer-feed-{}", std::process::id()));
+    let download = EpisodeDownload::start(episode.url.as_str(), &dir.join("ep1.mp3")).unwrap();
+    wait_until(|| download.is_finished());
+    assert_eq!(download.total(), Some(5000));
+    assert_eq!(download.written(), 5000);
+    assert_eq!(std::fs::read(&download.path).unwrap(), audio);
+    let _ = std::fs::remove_dir_all(dir);
+
+    let missing = net::fetch_text(format!("{}feeds/gone.xml", base).as_str(), &[]);
+    assert!(matches!(missing, Err(net::NetError::Status(..))));
+}
+
+// poll `done` for a few seconds
+fn wait_until<F: FnMut() -> bool>(mut done: F) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_partial_file() {
+    let audio: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+    let served = audio.clone();
+    let base = serve_with(move |request| {
+        let mut reply = Reply::ok(served.clone());
+        // 1000 bytes, then the rest after a while
+        reply.stall = Duration::from_millis(300);
+        reply.stall_after = 1000;
+        reply.length = !request.contains("unknown-length");
+        reply
+    });
+    let dir = std::env::temp_dir().join(format!("rustplayer-partial-{}", std::process::id()));
+
+    let download =
+        EpisodeDownload::start(format!("{}ep.mp3", base).as_str(), &dir.join("ep.mp3")).unwrap();
+    wait_until(|| download.written() >= 1000);
+    assert!(!download.is_finished());
+    let mut reader = download.reader().unwrap();
+    // what is there, without waiting for the rest
+    let mut buf = vec![0u8; 4096];
+    assert_eq!(reader.read(&mut buf).unwrap(), 1000);
+    assert_eq!(&buf[..1000], &audio[..1000]);
+    // the length is known from the headers, reading there waits
+    assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 4990);
+    let mut tail = vec![];
+    reader.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, &audio[4990..]);
+    assert!(download.is_finished());
+
+    let download = EpisodeDownload::start(
+        format!("{}unknown-length.mp3", base).as_str(),
+        &dir.join("unknown-length.mp3"),
+    )
+    .unwrap();
+    wait_until(|| download.written() >= 1000);
+    assert_eq!(download.total(), None);
+    let mut reader = download.reader().unwrap();
+    // the end is not known yet, rather than waiting for it
+    assert!(reader.seek(SeekFrom::End(0)).is_err());
+    wait_until(|| download.is_finished());
+    assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), 5000);
+
+    // a stopped download ends the reads waiting for it
+    let download =
+        EpisodeDownload::start(format!("{}ep.mp3", base).as_str(), &dir.join("stop.mp3")).unwrap();
+    wait_until(|| download.written() >= 1000);
+    let mut reader = download.reader().unwrap();
+    reader.seek(SeekFrom::Start(2000)).unwrap();
+    drop(download);
+    assert!(reader.read(&mut buf).is_err());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+// `seconds` of 8 kHz mono 16 bit PCM, no sample is silent
+fn wav(seconds: u32) -> Vec<u8> {
+    let samples: Vec<i16> = (0..8000 * seconds)
+        .map(|i| 1000 + (i % 8000) as i16)
+        .collect();
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = vec![];
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&16000u32.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+// every sample of `audio` but the silence played while decoding lags
+fn decoded(audio: EpisodeAudio) -> Vec<f32> {
+    audio.filter(|sample| *sample != 0.0).collect()
+}
+
+#[test]
+fn test_resume() {
+    let base = serve(vec![("ep.wav", wav(3))]);
+    let dir = std::env::temp_dir().join(format!("rustplayer-resume-{}", std::process::id()));
+    let download =
+        EpisodeDownload::start(format!("{}ep.wav", base).as_str(), &dir.join("ep.wav")).unwrap();
+    wait_until(|| download.is_finished());
+
+    let reader = download.reader().unwrap();
+    let resume = Duration::from_secs(1);
+    let decoder = EpisodeDecoder::start(reader, download.total(), Some("wav".into()), None, resume);
+    let mut ready = None;
+    wait_until(|| {
+        ready = decoder.try_ready();
+        ready.is_some()
+    });
+    let audio = ready.unwrap().unwrap();
+    assert_eq!(audio.duration, Duration::from_secs(3));
+    assert_eq!(audio.start, resume);
+    // from the sample at one second on
+    let samples = decoded(audio);
+    assert_eq!(samples.len(), 16000);
+    assert_eq!(samples[0], 1000.0 / 32768.0);
+
+    let failed = EpisodeDecoder::start(
+        std::io::Cursor::new(b"not audio".to_vec()),
+        None,
+        None,
+        None,
+        Duration::ZERO,
+    );
+    let mut ready = None;
+    wait_until(|| {
+        ready = failed.try_ready();
+        ready.is_some()
+    });
+    assert!(ready.unwrap().is_err());
+    let _ = std::fs::remove_dir_all(dir);
+
+    // the byte to wait for before starting
+    let hour = Some(Duration::from_secs(3600));
+    assert_eq!(
+        resume_offset(Some(3600), hour, Duration::from_secs(900)),
+        900
+    );
+    assert_eq!(resume_offset(None, hour, Duration::from_secs(900)), 0);
+    assert_eq!(resume_offset(Some(3600), None, Duration::from_secs(900)), 0);
+    assert_eq!(
+        resume_offset(Some(3600), hour, Duration::from_secs(7200)),
+        3600
+    );
+}
+
+#[test]
+fn test_played() {
+    let hour = Duration::from_secs(3600);
+    let mut state = EpisodeState {
+        position: 754,
+        ..Default::default()
+    };
+    assert_eq!(state.resume(), Duration::from_secs(754));
+    // stopped in the middle, resumed there
+    state.stopped(hour);
+    assert!(!state.played);
+    assert_eq!(state.position, 754);
+    // within 30 seconds of the end it was played, and starts over
+    state.position = 3580;
+    state.stopped(hour);
+    assert!(state.played);
+    assert_eq!(state.position, 0);
+    assert_eq!(state.resume(), Duration::ZERO);
+    state.position = 120;
+    assert_eq!(state.resume(), Duration::ZERO);
+}
@@ -0,0 +1,153 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/stations.rs");
+
+const STATIONS: &str = r#"# my stations
+[[station]]
+name = "经济之声"
+url = "http://ngcdn002.cnr.cn/live/jjzs/index.m3u8"
+group = "News"
+favourite = true
+variant = "lowest"
+
+[[station]]
+name = "Broken"
+
+[[station]]
+name = "Groove Salad"
+urls = ["https://ice1.somafm.com/groovesalad-128-mp3", "https://ice2.somafm.com/groovesalad-128-mp3"]
+user_agent = "RustPlayer"
+headers = { Referer = "https://somafm.com" }
+
+[[station]]
+name = "Typo"
+url = "http://example.com/live.m3u8"
+favorite = true
+
+[[station]]
+name = "Bad header"
+url = "http://example.com/live"
+headers = { "Bad Name" = "x" }
+
+[[playlist]]
+location = "/home/me/stations.pls"
+"#;
+
+#[test]
+fn test_parse_stations() {
+    let parsed = parse_stations(STATIONS).unwrap();
+    let names: Vec<_> = parsed.stations.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["经济之声", "Groove Salad"]);
+    let news = &parsed.stations[0];
+    assert_eq!(news.group.as_deref(), Some("News"));
+    assert!(news.favourite);
+    assert_eq!(news.variant.as_deref(), Some("lowest"));
+    let groove = &parsed.stations[1];
+    assert_eq!(groove.urls.len(), 2);
+    assert_eq!(groove.user_agent.as_deref(), Some("RustPlayer"));
+    assert_eq!(
+        groove.headers,
+        vec![("Referer".to_string(), "https://somafm.com".to_string())]
+    );
+    assert_eq!(parsed.playlists, vec!["/home/me/stations.pls"]);
+    // invalid entries are reported with their line
+    assert_eq!(parsed.errors.len(), 3);
+    assert!(parsed.errors[0].starts_with("line 9: Broken has no url"));
+    assert!(parsed.errors[1].starts_with("line 18: "));
+    assert!(parsed.errors[1].contains("favorite"));
+    assert_eq!(
+        parsed.errors[2],
+        "line 23: Bad header: Bad Name is not a header name"
+    );
+
+    let err = parse_stations("[[station]\nname = 1").unwrap_err();
+    assert!(err.contains("line 1"));
+}
+
+#[test]
+fn test_stations_document() {
+    let parsed = parse_stations(STATIONS).unwrap();
+    let entries: Vec<_> = parsed.stations.iter().map(|s| s.entry).collect();
+    assert_eq!(entries, vec![0, 2]);
+    let mut document = StationsDocument::parse(STATIONS).unwrap();
+    // untouched, the file is written back as it was
+    assert_eq!(document.to_string(), STATIONS);
+
+    let groove = Station {
+        favourite: true,
+        ..parsed.stations[1].clone()
+    };
+    assert_eq!(document.set_station(Some(2), &groove), 2);
+    document.swap_stations(0, 2);
+    document.remove_station(1);
+    let added = Station {
+        name: "Jazz".to_string(),
+        urls: vec!["http://stream.srg-ssr.ch/m/rsj/mp3_128".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(document.set_station(None, &added), 4);
+    document.add_playlist("https://somafm.com/groovesalad.pls");
+    let text = document.to_string();
+    // comments and the skipped entries are kept
+    assert!(text.starts_with("# my stations\n[[station]]\nname = \"Groove Salad\""));
+    assert!(text.contains("headers = { Referer = \"https://somafm.com\" }"));
+    assert!(text.contains("favorite = true"));
+    assert!(text.contains("\"Bad Name\" = \"x\""));
+    assert!(!text.contains("Broken"));
+
+    let again = parse_stations(text.as_str()).unwrap();
+    let names: Vec<_> = again.stations.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Groove Salad", "经济之声", "Jazz"]);
+    assert!(again.stations[0].favourite);
+    assert_eq!(again.stations[1].entry, 1);
+    assert_eq!(again.errors.len(), 2);
+    assert_eq!(
+        again.playlists,
+        vec![
+            "/home/me/stations.pls",
+            "https://somafm.com/groovesalad.pls"
+        ]
+    );
+    // the new station goes after the other stations, not after the playlist
+    let jazz = text.find("Jazz").unwrap();
+    assert!(jazz < text.find("[[playlist]]").unwrap());
+
+    // the comments of a file without tables stay on top
+    let mut document = StationsDocument::parse("# header\n\n").unwrap();
+    document.set_station(None, &added);
+    assert!(document
+        .to_string()
+        .starts_with("# header\n\n[[station]]\n"));
+
+    assert!(StationsDocument::parse("[[station]\nname = 1").is_err());
+    assert!(StationsDocument::parse("station = [{ name = \"a\" }]").is_err());
+}
+
+#[test]
+fn test_migrate_radio_ini() {
+    let ini = "经济之声 http://ngcdn002.cnr.cn/live/jjzs/index.m3u8\n\
+               # comment\n\
+               Radio Swiss Jazz http://stream.srg-ssr.ch/m/rsj/mp3_128 http://backup/rsj\n\
+               https://somafm.com/groovesalad.pls\n";
+    let parsed = parse_stations(migrate_radio_ini(ini).as_str()).unwrap();
+    assert!(parsed.errors.is_empty());
+    let names: Vec<_> = parsed.stations.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["经济之声", "Radio Swiss Jazz"]);
+    assert_eq!(parsed.stations[1].urls.len(), 2);
+    assert_eq!(parsed.playlists, vec!["https://somafm.com/groovesalad.pls"]);
+}
@@ -0,0 +1,58 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+include!("../src/util/timeshift.rs");
+
+fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+}
+
+#[test]
+fn test_timeshift() {
+    let mut timeshift = Timeshift::new(secs(30));
+    for i in 0..3 {
+        timeshift.push(secs(10), i);
+    }
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 0)));
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 1)));
+    // 4s into segment 0, segment 1 queued behind it
+    let position = timeshift.position(2, secs(4));
+    assert_eq!(position, secs(4));
+    assert_eq!(timeshift.behind(position), secs(26));
+    // 15s is 5s into segment 1
+    assert_eq!(timeshift.seek(secs(15)), secs(5));
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 1)));
+    timeshift.seek_live();
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 2)));
+    assert_eq!(timeshift.next_segment(), None);
+}
+
+#[test]
+fn test_timeshift_capacity() {
+    let mut timeshift = Timeshift::new(secs(30));
+    timeshift.push(secs(10), 0);
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 0)));
+    // paused, the buffer keeps growing past the capacity
+    for i in 1..6 {
+        timeshift.push(secs(10), i);
+    }
+    assert_eq!(timeshift.total(), secs(30));
+    // playback continues with the oldest segment still buffered
+    assert_eq!(timeshift.next_segment(), Some((secs(10), 3)));
+    assert_eq!(format_clock(secs(83)), "01:23");
+    assert_eq!(format_clock(secs(3723)), "1:02:03");
+}
@@ -3,14 +3,18 @@ use crate::error::Error;
 use ffmpeg_sys_next::{
     self, av_frame_alloc, av_frame_free, av_frame_unref, av_freep, av_get_alt_sample_fmt,
     av_get_bytes_per_sample, av_get_sample_fmt_name,
-    av_init_packet, av_packet_unref, av_read_frame, av_sample_fmt_is_planar,
+    av_init_packet, av_malloc, av_packet_unref, av_read_frame, av_sample_fmt_is_planar,
     av_samples_alloc, av_samples_get_buffer_size, avcodec_alloc_context3, avcodec_close,
     avcodec_find_decoder, avcodec_free_context, avcodec_open2, avcodec_parameters_to_context,
-    avcodec_receive_frame, avcodec_send_packet, avformat_close_input, avformat_find_stream_info,
-    avformat_open_input, swr_alloc_set_opts2, swr_convert, swr_get_out_samples, swr_init, AVCodec,
-    AVCodecContext, AVFormatContext, AVFrame, AVMediaType, AVPacket, AVSampleFormat, AVStream, AVChannelLayout
+    avcodec_receive_frame, avcodec_send_packet, avformat_alloc_context, avformat_close_input,
+    avformat_find_stream_info, avformat_open_input, avio_alloc_context, avio_context_free,
+    swr_alloc_set_opts2, swr_convert, swr_free, swr_get_out_samples, swr_init, AVCodec, AVCodecContext,
+    AVFormatContext, AVFrame, AVIOContext, AVMediaType, AVPacket, AVSampleFormat, AVStream,
+    AVChannelLayout
 };
 use std::ffi::{CStr, CString};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::os::raw::{c_int, c_void};
 use std::path::Path;
 use std::ptr;
 use std::slice;
@@ -22,6 +26,15 @@ const AVERROR_EOF: i32 = -0x20_464_F45;
 const AVERROR_EAGAIN: i32 = -11;
 const AVERROR_EDEADLK: i32 = -35;
 const DEFAULT_CONVERSION_FORMAT: AVSampleFormat = AVSampleFormat::AV_SAMPLE_FMT_S16;
+const AVSEEK_SIZE: c_int = 0x10000;
+const AVSEEK_FORCE: c_int = 0x20000;
+const AVFMT_FLAG_CUSTOM_IO: c_int = 0x0080;
+const IO_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Anything the decoder can read from memory, e.g. a `Cursor` over bytes.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
 
 pub struct Decoder {
     format_ctx: FormatContext,
@@ -32,6 +45,8 @@ pub struct Decoder {
     swr_ctx: Option<SwrContext>,
     current_frame: Vec<u8>,
     first_frame_stored: bool,
+    // custom io of decoders reading from memory, freed after the input
+    io: Option<IoContext>,
 }
 
 impl Decoder {
@@ -42,6 +57,59 @@ impl Decoder {
         // Open the file and get the format context
         let format_ctx = FormatContext::open(&path.as_ref().display().to_string())?;
 
+        Decoder::with_format_context(format_ctx, None)
+    }
+
+    /// Decode an in-memory file, e.g. a downloaded stream segment, without
+    /// writing it to disk.
+    pub fn from_bytes(data: impl AsRef<[u8]> + Send + 'static) -> Result<Decoder, Error> {
+        Decoder::from_reader(Cursor::new(data))
+    }
+
+    /// Decode a live stream, e.g. an HTTP response body, which cannot seek.
+    pub fn from_stream(reader: impl Read + Send + 'static) -> Result<Decoder, Error> {
+        Decoder::from_reader(Unseekable(reader))
+    }
+
+    /// Decode from any reader through a custom AVIO context.
+    pub fn from_reader(reader: impl ReadSeek + 'static) -> Result<Decoder, Error> {
+        let io = IoContext::new(Box::new(reader))?;
+        let format_ctx = FormatContext::open_io(&io)?;
+
+        Decoder::with_format_context(format_ctx, Some(io))
+    }
+
+    fn with_format_context(
+        mut format_ctx: FormatContext,
+        io: Option<IoContext>,
+    ) -> Result<Decoder, Error> {
+        let (stream, codec_ctx, frame, packet, swr_ctx) = match Decoder::open_codec(&format_ctx) {
+            Ok(opened) => opened,
+            Err(err) => {
+                // The input first, custom io is not closed with it.
+                format_ctx.close();
+                drop(io);
+                return Err(err);
+            }
+        };
+
+        Ok(Decoder {
+            format_ctx,
+            stream,
+            codec_ctx,
+            frame,
+            packet,
+            swr_ctx,
+            current_frame: vec![],
+            first_frame_stored: false,
+            io,
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn open_codec(
+        format_ctx: &FormatContext,
+    ) -> Result<(Stream, CodecContext, Frame, Packet, Option<SwrContext>), Error> {
         // Find first audio stream in file
         format_ctx.find_stream_info()?;
         let stream = format_ctx.get_audio_stream()?;
@@ -70,16 +138,7 @@ impl Decoder {
             None
         };
 
-        Ok(Decoder {
-            format_ctx,
-            stream,
-            codec_ctx,
-            frame,
-            packet,
-            swr_ctx,
-            current_frame: vec![],
-            first_frame_stored: false,
-        })
+        Ok((stream, codec_ctx, frame, packet, swr_ctx))
     }
 
     fn read_next_frame(&mut self) -> ReadFrameStatus {
@@ -237,23 +296,36 @@ impl Decoder {
         Some(Ok(()))
     }
 
+    // Runs at the end of iteration and again on drop, freed pointers are
+    // nulled so the second time does nothing.
     fn cleanup(&mut self) {
-        // Drain the decoder.
-        drain_decoder(self.codec_ctx.inner).unwrap();
-
         unsafe {
-            // Free all data used by the frame.
-            av_frame_free(&mut self.frame.inner);
+            if !self.codec_ctx.inner.is_null() {
+                // Drain the decoder.
+                let _ = drain_decoder(self.codec_ctx.inner);
 
-            // Close the context and free all data associated to it, but not the context itself.
-            avcodec_close(self.codec_ctx.inner);
+                // Close the context and free all data associated to it, but not the context itself.
+                avcodec_close(self.codec_ctx.inner);
 
-            // Free the context itself.
-            avcodec_free_context(&mut self.codec_ctx.inner);
+                // Free the context itself.
+                avcodec_free_context(&mut self.codec_ctx.inner);
+            }
 
-            // Close the input.
-            avformat_close_input(&mut self.format_ctx.inner);
+            if !self.frame.inner.is_null() {
+                // Free all data used by the frame.
+                av_frame_free(&mut self.frame.inner);
+            }
+
+            if let Some(mut swr_ctx) = self.swr_ctx.take() {
+                swr_free(&mut swr_ctx.inner);
+            }
         }
+
+        // Close the input.
+        self.format_ctx.close();
+
+        // Custom io is not closed with the input.
+        self.io.take();
     }
 
     pub(crate) fn _current_frame_len(&self) -> Option<usize> {
@@ -276,11 +348,23 @@ impl Decoder {
 
 unsafe impl Send for Decoder {}
 
+impl Drop for Decoder {
+    // Decoders are dropped part-way through, e.g. when a stream is stopped.
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
 impl Iterator for Decoder {
     type Item = f32;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        // Cleaned up at the end.
+        if self.codec_ctx.inner.is_null() {
+            return None;
+        }
+
         if !self.first_frame_stored {
             if self.process_next_frame().is_none() {
                 self.cleanup();
@@ -343,6 +427,41 @@ impl FormatContext {
         Ok(FormatContext { inner })
     }
 
+    fn open_io(io: &IoContext) -> Result<FormatContext, Error> {
+        let mut inner = unsafe { avformat_alloc_context() };
+        if inner.is_null() {
+            return Err(Error::InitializeFormatContext);
+        }
+
+        unsafe {
+            let ctx = inner.as_mut().unwrap();
+            ctx.pb = io.inner;
+            ctx.flags |= AVFMT_FLAG_CUSTOM_IO;
+        }
+
+        // Frees the context on failure
+        let status = unsafe {
+            avformat_open_input(
+                &mut inner,
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if status != 0 {
+            return Err(Error::InitializeFormatContext);
+        }
+
+        Ok(FormatContext { inner })
+    }
+
+    /// Close the input, once.
+    fn close(&mut self) {
+        if !self.inner.is_null() {
+            unsafe { avformat_close_input(&mut self.inner) };
+        }
+    }
+
     /// Look at first few frames to determine stream info
     fn find_stream_info(&self) -> Result<(), Error> {
         let status = unsafe { avformat_find_stream_info(self.inner, ptr::null_mut()) };
@@ -365,6 +484,104 @@ impl FormatContext {
     }
 }
 
+// Seeks fail, ffmpeg then only reads forward.
+struct Unseekable<R>(R);
+
+impl<R: Read> Read for Unseekable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R> Seek for Unseekable<R> {
+    fn seek(&mut self, _: SeekFrom) -> std::io::Result<u64> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+struct IoContext {
+    inner: *mut AVIOContext,
+    reader: *mut Box<dyn ReadSeek>,
+}
+
+impl IoContext {
+    fn new(reader: Box<dyn ReadSeek>) -> Result<IoContext, Error> {
+        let reader = Box::into_raw(Box::new(reader));
+
+        let mut buffer = unsafe { av_malloc(IO_BUFFER_SIZE) as *mut u8 };
+        if buffer.is_null() {
+            unsafe { drop(Box::from_raw(reader)) };
+            return Err(Error::InitializeIo);
+        }
+
+        let inner = unsafe {
+            avio_alloc_context(
+                buffer,
+                IO_BUFFER_SIZE as c_int,
+                0,
+                reader as *mut c_void,
+                Some(read_packet),
+                None,
+                Some(seek),
+            )
+        };
+        if inner.is_null() {
+            unsafe {
+                av_freep(&mut buffer as *mut _ as _);
+                drop(Box::from_raw(reader));
+            }
+            return Err(Error::InitializeIo);
+        }
+
+        Ok(IoContext { inner, reader })
+    }
+}
+
+impl Drop for IoContext {
+    fn drop(&mut self) {
+        unsafe {
+            // The buffer may have been reallocated by ffmpeg, free the current one.
+            av_freep(&mut self.inner.as_mut().unwrap().buffer as *mut _ as _);
+            avio_context_free(&mut self.inner);
+            drop(Box::from_raw(self.reader));
+        }
+    }
+}
+
+unsafe extern "C" fn read_packet(opaque: *mut c_void, buf: *mut u8, buf_size: c_int) -> c_int {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let buf = slice::from_raw_parts_mut(buf, buf_size as usize);
+
+    match reader.read(buf) {
+        Ok(0) => AVERROR_EOF,
+        Ok(read) => read as c_int,
+        Err(_) => AVERROR_EOF,
+    }
+}
+
+unsafe extern "C" fn seek(opaque: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+
+    if whence & AVSEEK_SIZE != 0 {
+        let current = match reader.stream_position() {
+            Ok(current) => current,
+            Err(_) => return -1,
+        };
+        let size = reader.seek(SeekFrom::End(0));
+        let _ = reader.seek(SeekFrom::Start(current));
+        return size.map_or(-1, |size| size as i64);
+    }
+
+    // AVSEEK_FORCE only asks to seek even when it is slow
+    let pos = match whence & !(AVSEEK_SIZE | AVSEEK_FORCE) {
+        0 => SeekFrom::Start(offset.max(0) as u64),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => return -1,
+    };
+    reader.seek(pos).map_or(-1, |pos| pos as i64)
+}
+
 struct SwrContext {
     inner: *mut ffmpeg_sys_next::SwrContext,
 }
@@ -623,3 +840,76 @@ fn drain_decoder(codec_ctx: *mut AVCodecContext) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one second of a 440 Hz tone as 8 kHz mono 16-bit PCM
+    fn wav() -> Vec<u8> {
+        let samples: Vec<i16> = (0..8000)
+            .map(|i| {
+                ((i as f32 * 440.0 * 2.0 * std::f32::consts::PI / 8000.0).sin() * 8000.0) as i16
+            })
+            .collect();
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = vec![];
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono, 8 kHz, 16 kB/s, 2 byte frames, 16 bits
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let decoder = Decoder::from_bytes(wav()).unwrap();
+        assert_eq!(decoder._sample_rate(), 8000);
+        assert_eq!(decoder._channels(), 1);
+        let samples: Vec<f32> = decoder.collect();
+        assert_eq!(samples.len(), 8000);
+        assert!(samples.iter().any(|sample| *sample > 0.2));
+
+        assert!(Decoder::from_bytes(vec![0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_drop_part_way() {
+        let mut decoder = Decoder::from_bytes(wav()).unwrap();
+        assert_eq!(decoder.by_ref().take(100).count(), 100);
+        drop(decoder);
+
+        // freed at the end of iteration, and not again on drop
+        let mut decoder = Decoder::from_bytes(wav()).unwrap();
+        assert_eq!(decoder.by_ref().count(), 8000);
+        assert!(decoder.codec_ctx.inner.is_null());
+        assert!(decoder.format_ctx.inner.is_null());
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn test_seek_flags() {
+        let mut reader: Box<dyn ReadSeek> = Box::new(Cursor::new(vec![0u8; 100]));
+        let opaque = &mut reader as *mut Box<dyn ReadSeek> as *mut c_void;
+        unsafe {
+            assert_eq!(seek(opaque, 10, AVSEEK_FORCE), 10);
+            assert_eq!(seek(opaque, 5, 1 | AVSEEK_FORCE), 15);
+            assert_eq!(seek(opaque, 0, AVSEEK_SIZE | AVSEEK_FORCE), 100);
+            // the size query leaves the position alone
+            assert_eq!(seek(opaque, 0, 1), 15);
+            assert_eq!(seek(opaque, 0, 3), -1);
+        }
+    }
+}
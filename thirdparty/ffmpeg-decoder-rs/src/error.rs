@@ -4,6 +4,8 @@ use thiserror::Error;
 pub enum Error {
     #[error("Failed to initialize format context")]
     InitializeFormatContext,
+    #[error("Failed to initialize custom io")]
+    InitializeIo,
     #[error("Could not find stream in file")]
     FindStreamInfo,
     #[error("Could not find any audio stream")]
@@ -1,6 +1,8 @@
 //! Decodes audio files using ffmpeg bindings
 //!
-//! Create a [`Decoder`](struct.Decoder.html) by supplying a `Path` to an audio file. [`Decoder`](struct.Decoder.html)
+//! Create a [`Decoder`](struct.Decoder.html) by supplying a `Path` to an audio file, or the file's bytes
+//! with [`Decoder::from_bytes`](struct.Decoder.html#method.from_bytes), or a live stream with
+//! [`Decoder::from_stream`](struct.Decoder.html#method.from_stream). [`Decoder`](struct.Decoder.html)
 //! implies `Iterator` where each iteration returns a single `i16` signed 16bit sample.
 //! Also implements [rodio's](https://github.com/RustAudio/rodio) [`Source`](https://docs.rs/rodio/latest/rodio/source/trait.Source.html) trait, where
 //! the [`Decoder`](struct.Decoder.html) can be supplied as a sink source for playback.
@@ -30,7 +32,7 @@
 //! }
 //! ```
 mod decoder;
-pub use decoder::Decoder;
+pub use decoder::{Decoder, ReadSeek};
 
 mod error;
 pub use error::Error;